@@ -0,0 +1,67 @@
+use chess::{BitBoard, Board, Color, Piece, EMPTY};
+use crate::bitboard_operators::{self, king_attacks};
+use crate::utils::get_piece_type;
+
+// every square the pieces of one side attack, built once per evaluation and shared by the terms that need it
+#[derive(Debug, Clone)]
+pub struct AttackMaps{
+    // indexed by piece
+    pub by_piece: [BitBoard; 6],
+    pub all: BitBoard,
+    // squares attacked at least twice
+    pub double: BitBoard,
+    // the ring around the enemy king, the number of pieces that attack it and
+    // the number of ring squares attacked by every piece type
+    pub enemy_king_ring: BitBoard,
+    pub king_attackers: usize,
+    pub king_ring_attacks: [u32; 6]
+}
+
+impl AttackMaps{
+    pub fn new(board: &Board, color: Color) -> AttackMaps{
+        let their_king = BitBoard::from_square(board.king_square(!color));
+        let mut maps = AttackMaps{
+            by_piece: [EMPTY; 6],
+            all: EMPTY,
+            double: EMPTY,
+            enemy_king_ring: king_attacks(their_king) | their_king,
+            king_attackers: 0,
+            king_ring_attacks: [0; 6]
+        };
+
+        let pawns = get_piece_type(board, Piece::Pawn, color);
+        let pawn_attacks = bitboard_operators::pawn_any_attacks(pawns, color);
+        maps.double = bitboard_operators::pawn_double_attacks(pawns, color);
+        maps.add(Piece::Pawn, pawn_attacks);
+
+        let occupied = *board.combined();
+        for piece in [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]{
+            for square in get_piece_type(board, piece, color){
+                let attacks = match piece{
+                    Piece::Knight=>chess::get_knight_moves(square),
+                    Piece::Bishop=>chess::get_bishop_moves(square, occupied),
+                    Piece::Rook=>chess::get_rook_moves(square, occupied),
+                    _=>chess::get_bishop_moves(square, occupied) | chess::get_rook_moves(square, occupied)
+                };
+                let ring_attacks = (attacks & maps.enemy_king_ring).popcnt();
+                if ring_attacks > 0{
+                    maps.king_attackers += 1;
+                    maps.king_ring_attacks[piece.to_index()] += ring_attacks;
+                }
+                maps.add(piece, attacks);
+            }
+        }
+        maps.add(Piece::King, chess::get_king_moves(board.king_square(color)));
+        maps
+    }
+
+    fn add(&mut self, piece: Piece, attacks: BitBoard){
+        self.double |= self.all & attacks;
+        self.all |= attacks;
+        self.by_piece[piece.to_index()] |= attacks;
+    }
+
+    pub fn piece(&self, piece: Piece) -> BitBoard{
+        self.by_piece[piece.to_index()]
+    }
+}
@@ -0,0 +1,156 @@
+use chess::{Board, Piece, ALL_SQUARES};
+use std::sync::LazyLock;
+use crate::utils::relative_square;
+
+// the result of every king and pawn against king position, one bit per position that the pawn side wins.
+// the pawn is white and on the a-d files (the other files are mirrored), a position is numbered by
+// the pawn (a2-d7), the white king, the black king and the side to move: 24 * 64 * 64 * 2 positions.
+// the table is built backwards from the positions known right away: a safe promotion is a win, a stalemate or
+// a pawn that is taken is a draw. a position is won when white has a move to a won one and black only moves to
+// won ones, and drawn the other way around. every pass settles more positions until nothing changes
+const SIZE: usize = 24 * 64 * 64 * 2;
+
+// the state of a position while the table is built, the results of the moves are or-ed together
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+const WHITE: usize = 0;
+const BLACK: usize = 1;
+
+fn index(stm: usize, white_king: usize, black_king: usize, pawn: usize) -> usize{
+    let pawn_index = (pawn & 7) * 6 + (pawn >> 3) - 1;
+    ((pawn_index * 64 + white_king) * 64 + black_king) * 2 + stm
+}
+
+fn king_attacks(square: usize) -> u64{
+    chess::get_king_moves(ALL_SQUARES[square]).0
+}
+
+fn pawn_attacks(square: usize) -> u64{
+    let mut attacks = 0;
+    if square & 7 > 0{
+        attacks |= 1 << (square + 7);
+    }
+    if square & 7 < 7{
+        attacks |= 1 << (square + 9);
+    }
+    attacks
+}
+
+fn distance(a: usize, b: usize) -> usize{
+    ((a & 7) as i32 - (b & 7) as i32).unsigned_abs().max(((a >> 3) as i32 - (b >> 3) as i32).unsigned_abs()) as usize
+}
+
+// the position of an index: side to move, white king, black king, pawn
+fn position(index: usize) -> (usize, usize, usize, usize){
+    let pawn_index = index >> 13;
+    let pawn = (pawn_index % 6 + 1) * 8 + pawn_index / 6;
+    (index & 1, (index >> 7) & 63, (index >> 1) & 63, pawn)
+}
+
+// the result known without looking at the moves
+fn initial_result(stm: usize, white_king: usize, black_king: usize, pawn: usize) -> u8{
+    let bit = |square: usize| 1u64 << square;
+    if distance(white_king, black_king) <= 1 || white_king == pawn || black_king == pawn || (stm == WHITE && pawn_attacks(pawn) & bit(black_king) != 0){
+        return INVALID;
+    }
+    let push = pawn + 8;
+    if stm == WHITE && pawn >> 3 == 6 && white_king != push && (distance(black_king, push) > 1 || distance(white_king, push) == 1){
+        return WIN;
+    }
+    if stm == BLACK{
+        let escapes = king_attacks(black_king) & !(king_attacks(white_king) | pawn_attacks(pawn));
+        let takes_pawn = king_attacks(black_king) & bit(pawn) & !king_attacks(white_king);
+        if escapes == 0 || takes_pawn != 0{
+            return DRAW;
+        }
+    }
+    UNKNOWN
+}
+
+// the result from the results after every move, unknown while a move still leads to an unknown position
+fn classify(table: &[u8], stm: usize, white_king: usize, black_king: usize, pawn: usize) -> u8{
+    let mut results = INVALID;
+    if stm == WHITE{
+        for square in chess::BitBoard(king_attacks(white_king)){
+            results |= table[index(BLACK, square.to_index(), black_king, pawn)];
+        }
+        if pawn >> 3 < 6{
+            results |= table[index(BLACK, white_king, black_king, pawn + 8)];
+        }
+        if pawn >> 3 == 1 && pawn + 8 != white_king && pawn + 8 != black_king{
+            results |= table[index(BLACK, white_king, black_king, pawn + 16)];
+        }
+    }else{
+        for square in chess::BitBoard(king_attacks(black_king)){
+            results |= table[index(WHITE, white_king, square.to_index(), pawn)];
+        }
+    }
+    let (good, bad) = if stm == WHITE { (WIN, DRAW) } else { (DRAW, WIN) };
+    if results & good != 0{
+        good
+    }else if results & UNKNOWN != 0{
+        UNKNOWN
+    }else{
+        bad
+    }
+}
+
+static KPK: LazyLock<Vec<u64>> = LazyLock::new(|| {
+    let mut table: Vec<u8> = (0..SIZE).map(|index| {
+        let (stm, white_king, black_king, pawn) = position(index);
+        initial_result(stm, white_king, black_king, pawn)
+    }).collect();
+
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for index in 0..SIZE{
+            if table[index] != UNKNOWN{
+                continue;
+            }
+            let (stm, white_king, black_king, pawn) = position(index);
+            let result = classify(&table, stm, white_king, black_king, pawn);
+            if result != UNKNOWN{
+                table[index] = result;
+                changed = true;
+            }
+        }
+    }
+
+    let mut bits = vec![0u64; SIZE / 64];
+    for (index, result) in table.iter().enumerate(){
+        if *result == WIN{
+            bits[index / 64] |= 1 << (index % 64);
+        }
+    }
+    bits
+});
+
+// builds the table now instead of in the first search that needs it
+pub fn init(){
+    LazyLock::force(&KPK);
+}
+
+// whether the pawn side wins, the squares are seen from the pawn side (its pawn goes up the board)
+pub fn kpk_win(strong_king: usize, pawn: usize, weak_king: usize, strong_to_move: bool) -> bool{
+    // the table only has the pawn on the a-d files
+    let flip = if pawn & 7 > 3 { 7 } else { 0 };
+    let stm = if strong_to_move { WHITE } else { BLACK };
+    let index = index(stm, strong_king ^ flip, weak_king ^ flip, pawn ^ flip);
+    KPK[index / 64] & (1 << (index % 64)) != 0
+}
+
+// whether the pawn side wins a king and pawn against king position, none for any other material
+pub fn probe_kpk(board: &Board) -> Option<bool>{
+    let pawns = board.pieces(Piece::Pawn);
+    if board.combined().popcnt() != 3 || pawns.popcnt() != 1{
+        return None;
+    }
+    let pawn = pawns.to_square();
+    let strong = board.color_on(pawn)?;
+    let square = |square: chess::Square| relative_square(square, strong).to_index();
+    Some(kpk_win(square(board.king_square(strong)), square(pawn), square(board.king_square(!strong)), board.side_to_move() == strong))
+}
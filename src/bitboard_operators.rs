@@ -1,9 +1,10 @@
 use std::ops::Not;
 
-use chess::{BitBoard};
+use chess::{BitBoard, Color, Square};
 
 const NOT_A_FILE: u64 = 0xfefefefefefefefe; // ~0x0101010101010101
 const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f; // ~0x8080808080808080
+pub const DARK_SQUARES: u64 = 0xAA55AA55AA55AA55;
 
 fn east_one (b: BitBoard) -> BitBoard {return BitBoard::new((b.0 << 1) & NOT_A_FILE)}
 fn north_east_one (b: BitBoard) -> BitBoard {return BitBoard::new((b.0 << 9) & NOT_A_FILE)}
@@ -146,4 +147,141 @@ pub fn black_passed_pawns(bpawns: BitBoard, wpawns: BitBoard) -> BitBoard {
     all_front_spans |= east_one(all_front_spans)
                   |  west_one(all_front_spans);
     return bpawns & all_front_spans.not();
- }
\ No newline at end of file
+ }
+
+pub fn black_backward(bpawns: BitBoard, wpawns: BitBoard) -> BitBoard {
+    let  stops = BitBoard::new(bpawns.0 >> 8);
+    let black_attack_spans = file_fill(black_pawn_any_attacks(bpawns));
+
+    let white_attacks     = white_pawn_any_attacks(wpawns);
+    return BitBoard::new((stops & white_attacks & black_attack_spans.not()).0 << 8);
+ }
+
+
+// the same operations for the pawns of one color against the pawns of the other
+pub fn pawn_any_attacks(pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_pawn_any_attacks(pawns),
+        Color::Black=>black_pawn_any_attacks(pawns)
+    }
+}
+
+pub fn front_spans(pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_front_spans(pawns),
+        Color::Black=>black_front_spans(pawns)
+    }
+}
+
+pub fn pawns_behind_own(pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_pawns_behind_own(pawns),
+        Color::Black=>black_pawns_behind_own(pawns)
+    }
+}
+
+pub fn backward(pawns: BitBoard, enemy_pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_backward(pawns, enemy_pawns),
+        Color::Black=>black_backward(pawns, enemy_pawns)
+    }
+}
+
+pub fn passed_pawns(pawns: BitBoard, enemy_pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_passed_pawns(pawns, enemy_pawns),
+        Color::Black=>black_passed_pawns(pawns, enemy_pawns)
+    }
+}
+
+// squares attacked by two pawns at once
+pub fn pawn_double_attacks(pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_pawn_east_attacks(pawns) & white_pawn_west_attacks(pawns),
+        Color::Black=>black_pawn_east_attacks(pawns) & black_pawn_west_attacks(pawns)
+    }
+}
+
+// the squares the pawns can move to with a single step
+pub fn pawn_single_pushes(pawns: BitBoard, empty: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>nort_one(pawns) & empty,
+        Color::Black=>sout_one(pawns) & empty
+    }
+}
+
+// the squares the pawns can attack now or after moving forward
+pub fn pawn_attack_spans(pawns: BitBoard, color: Color) -> BitBoard {
+    let fronts = front_spans(pawns, color) | pawns;
+    pawn_any_attacks(fronts, color)
+}
+
+pub fn rear_spans(pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>white_rear_spans(pawns),
+        Color::Black=>black_rear_spans(pawns)
+    }
+}
+
+// pawns with an own pawn next to them on the same rank
+pub fn phalanx(pawns: BitBoard) -> BitBoard {
+    return pawns & (east_one(pawns) | west_one(pawns));
+}
+
+// pawns defended by an own pawn
+pub fn supported_pawns(pawns: BitBoard, color: Color) -> BitBoard {
+    return pawns & pawn_any_attacks(pawns, color);
+}
+
+// pawns attacking an enemy pawn
+pub fn pawn_levers(pawns: BitBoard, enemy_pawns: BitBoard, color: Color) -> BitBoard {
+    return pawns & pawn_any_attacks(enemy_pawns, !color);
+}
+
+// pawns with an enemy pawn right in front of them
+pub fn blocked_pawns(pawns: BitBoard, enemy_pawns: BitBoard, color: Color) -> BitBoard {
+    match color{
+        Color::White=>pawns & sout_one(enemy_pawns),
+        Color::Black=>pawns & nort_one(enemy_pawns)
+    }
+}
+
+// not passed pawns without an enemy pawn in front of them on their file, that have at least as many own pawns
+// next to them (level or behind) as there are enemy pawns guarding the squares in front of them
+pub fn candidate_passed_pawns(pawns: BitBoard, enemy_pawns: BitBoard, color: Color) -> BitBoard {
+    let open = pawns & front_spans(enemy_pawns, !color).not() & passed_pawns(pawns, enemy_pawns, color).not();
+    let mut candidates = BitBoard::new(0);
+    for square in open{
+        let pawn = BitBoard::from_square(square);
+        let level_or_behind = pawn | rear_spans(pawn, color);
+        let helpers = pawns & (east_one(level_or_behind) | west_one(level_or_behind));
+        let sentries = enemy_pawns & pawn_any_attacks(front_spans(pawn, color), color);
+        if helpers.popcnt() >= sentries.popcnt(){
+            candidates |= pawn;
+        }
+    }
+    return candidates;
+}
+
+// the central squares (files c to f) of our third and fourth rank that our pawns can never attack
+pub fn pawn_holes(pawns: BitBoard, color: Color) -> BitBoard {
+    let camp = match color{
+        Color::White=>BitBoard::new(0x000000003C3C0000),
+        Color::Black=>BitBoard::new(0x00003C3C00000000)
+    };
+    return camp & pawn_attack_spans(pawns, color).not();
+}
+
+// the number of groups of pawns on adjacent files
+pub fn pawn_islands(pawns: BitBoard) -> u32 {
+    let files = (file_fill(pawns).0 & 0xFF) as u8;
+    return (files & (files << 1).not()).count_ones();
+}
+
+// the squares of the same color as the square
+pub fn same_color_squares(square: Square) -> BitBoard {
+    if BitBoard::from_square(square).0 & DARK_SQUARES != 0{
+        return BitBoard::new(DARK_SQUARES);
+    }
+    return BitBoard::new(!DARK_SQUARES);
+}
@@ -13,27 +13,97 @@ pub const ROOK_ON_OPEN_FILE: Score = (10, 10);
 pub const ROOK_ON_HALF_OPEN_FILE: Score = (5, 5);
 pub const ROOK_ATTACKING_OPEN_KING_ZONE: Score = (20, 10);
 pub const ROOK_ATTACKING_HALF_OPEN_KING_ZONE: Score = (10, 0);
+// minor pieces. the bad bishop penalty is for every own pawn on the color of the bishop, the knight
+// penalty for every square between the knight and the closer king
+pub const BAD_BISHOP: Score = (-2, -4);
+pub const BISHOP_OUTPOST: Score = (20, 5);
+pub const LONG_DIAGONAL_BISHOP: Score = (30, 0);
+pub const FIANCHETTO: Score = (10, 0);
+pub const TRAPPED_BISHOP: Score = (-100, -120);
+pub const KNIGHT_KING_DISTANCE: Score = (-3, -2);
+// rooks and queens. the early queen penalty is for every minor piece still on its starting square, the queen threat
+// penalty for every safe square a knight or bishop of theirs can attack the queen from
+pub const ROOK_ON_SEVENTH: Score = (20, 40);
+pub const DOUBLED_ROOKS: Score = (10, 5);
+pub const CONNECTED_ROOKS: Score = (5, 5);
+pub const TRAPPED_ROOK: Score = (-40, -10);
+pub const EARLY_QUEEN: Score = (-8, 0);
+pub const QUEEN_MINOR_THREAT: Score = (-10, -5);
 pub const TEMPO_BONUS: Score = (20, 10);
 
-// mobility values
-pub const KNIGHT_MOBILITY_VAL: Score = (4, 4);
-pub const BISHOP_MOBILITY_VAL: Score = (5, 5);
-pub const ROOK_MOBILITY_VAL: Score = (2, 4);
+// mobility values, the bonus for every number of squares a piece can go to (not counting squares attacked by enemy pawns)
+pub const KNIGHT_MOBILITY: &[Score] = &[(-31, -40), (-26, -28), (-6, -16), (-2, -8), (2, 2), (6, 6), (11, 8), (14, 10), (16, 12)];
+pub const BISHOP_MOBILITY: &[Score] = &[(-24, -30), (-10, -12), (8, -2), (13, 6), (19, 12), (26, 21), (28, 27), (32, 28), (32, 32), (34, 36), (40, 39), (40, 43), (46, 44), (49, 48)];
+pub const ROOK_MOBILITY: &[Score] = &[(-30, -39), (-10, -8), (1, 12), (2, 20), (2, 35), (6, 50), (11, 52), (16, 60), (20, 67), (20, 70), (20, 79), (24, 82), (28, 84), (28, 84), (31, 86)];
+pub const QUEEN_MOBILITY: &[Score] = &[(-15, -24), (-6, -15), (-4, -4), (-4, 10), (10, 20), (12, 28), (12, 30), (18, 38), (19, 39), (26, 48), (32, 48), (32, 50), (32, 60), (33, 64), (34, 66), (34, 66), (36, 68), (36, 70), (38, 74), (40, 75), (46, 76), (54, 84), (54, 84), (54, 86), (55, 91), (57, 91), (57, 96), (58, 110)];
+
+// threats, indexed by the threatened piece (pawn, knight, bishop, rook, queen, king)
+pub const HANGING_PIECE: &[Score] = &[(10, 20), (35, 30), (35, 30), (45, 40), (60, 50), (0, 0)];
+pub const THREAT_BY_LESSER: &[Score] = &[(0, 0), (40, 30), (40, 30), (60, 40), (80, 60), (0, 0)];
+pub const PAWN_PUSH_THREAT: Score = (20, 15);
+pub const MULTIPLE_THREATS: Score = (40, 30);
+
+// space is counted in safe squares times minor pieces, out of 16
+pub const SPACE_WEIGHT: i32 = 6;
+pub const CENTRE_CONTROL: Score = (5, 2);
+pub const EXTENDED_CENTRE_CONTROL: Score = (2, 1);
 
 // pruning margins
 
 pub const RAZORING_MARGIN: i32 = 910; 
 pub const EXTENDED_FUTILITY_MARGIN: i32 = 510;
 pub const FUTILITY_MARGIN: i32 = 350; 
+
+// mate scores. a mate n plies from the root scores MATE_SCORE - n, anything above MATE_BOUND is a mate
+pub const MATE_SCORE: i32 = 10999;
+pub const MAX_PLY: i32 = 1000;
+pub const MATE_BOUND: i32 = MATE_SCORE - MAX_PLY;
+// a tablebase win n plies from the root scores TB_WIN_SCORE - n, below every mate
+pub const TB_WIN_SCORE: i32 = MATE_BOUND - MAX_PLY;
+
+// the number of entries of the transposition table, the pawn hash table and the evaluation cache of a search
+pub const HASH_ENTRIES: usize = 65536;
+
+// late move reductions
+pub const LMR_LATE_MOVES: u32 = 6;
+pub const LMR_HISTORY_DIVISOR: i32 = 4096;
 // pawns
 pub const DOUBLED_PAWNS_DEBUFF: Score = (-50, -60);
 pub const ISOLATED_PAWNS_DEBUFF: Score = (-10, -20);
 pub const BACKWARD_PAWNS_DEBUFF: Score = (-8, -10);
 pub const UNSTOPPABLE_PAWN: Score = (0, 800);
 
+// passed pawns, the bonus for the rank and the bonuses that are scaled by the passed pawn percent of the rank
+pub const PASSED_PAWN: &[Score] = &[(0, 0), (10, 20), (10, 20), (16, 32), (28, 56), (46, 92), (70, 140), (0, 0)];
+pub const PASSED_THEIR_KING_DISTANCE: Score = (2, 20);
+pub const PASSED_OUR_KING_DISTANCE: Score = (-1, -5);
+pub const PASSED_FREE_PATH: Score = (5, 20);
+pub const PASSED_SAFE_PATH: Score = (10, 40);
+pub const PASSED_BLOCKADED: Score = (-10, -30);
+pub const ROOK_BEHIND_PASSER: Score = (10, 30);
+
+// pawn structure bonuses indexed by the rank of the pawn from its own side
+pub const SUPPORTED_PAWN: &[Score] = &[(0, 0), (0, 0), (6, 4), (8, 6), (14, 14), (25, 30), (45, 60), (0, 0)];
+pub const PHALANX_PAWN: &[Score] = &[(0, 0), (0, 0), (4, 2), (8, 6), (14, 14), (30, 40), (55, 80), (0, 0)];
+pub const CANDIDATE_PASSED_PAWN: &[Score] = &[(0, 0), (0, 0), (5, 10), (5, 12), (10, 20), (20, 40), (0, 0), (0, 0)];
+pub const PAWN_LEVER: Score = (5, 3);
+pub const BLOCKED_PAWN: Score = (-6, -4);
+pub const PAWN_HOLE: Score = (-5, 0);
+pub const PAWN_ISLAND: Score = (-4, -8);
+
+
+// game phase. every minor piece counts 1, a rook 2 and a queen 4, the starting position is the full opening phase
+pub const PHASE_MAX: i32 = 256;
+pub const KNIGHT_PHASE: i32 = 1;
+pub const BISHOP_PHASE: i32 = 1;
+pub const ROOK_PHASE: i32 = 2;
+pub const QUEEN_PHASE: i32 = 4;
+pub const TOTAL_PHASE: i32 = 4 * KNIGHT_PHASE + 4 * BISHOP_PHASE + 4 * ROOK_PHASE + 2 * QUEEN_PHASE;
+
 
 pub trait Access{
     fn access_endgame(&self, endgame: bool) -> i32;
+    fn taper(&self, phase: i32) -> i32;
 }
 
 impl Access for Score {
@@ -43,10 +113,15 @@ impl Access for Score {
         }
         return self.0;
     }
+
+    // blends the opening and endgame values, a phase of PHASE_MAX is the opening and 0 is the endgame
+    fn taper(&self, phase: i32) -> i32 {
+        (self.0 * phase + self.1 * (PHASE_MAX - phase)) / PHASE_MAX
+    }
 }
 
 
-pub static PAWN_SQUARES_TABLE: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
+pub static PAWN_SQUARES_TABLE: &[i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 5, 10, 10, -20, -20, 10, 10, 5,
 5, -5, -10, 0, 0, -10, -5, 5,
 0, 0, 0, 20, 20, 0, 0, 0,
@@ -55,7 +130,7 @@ pub static PAWN_SQUARES_TABLE: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 50, 50, 50, 50, 50, 50, 50, 50,
 0, 0, 0, 0, 0, 0, 0, 0];
 
-pub static PAWN_SQUARES_TABLE_BLACK: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
+pub static PAWN_SQUARES_TABLE_BLACK: &[i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 50, 50, 50, 50, 50, 50, 50, 50,
 10, 10, 20, 30, 30, 20, 10, 10,
 5, 5, 10, 25, 25, 10, 5, 5,
@@ -65,7 +140,7 @@ pub static PAWN_SQUARES_TABLE_BLACK: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 0, 0, 0, 0, 0, 0, 0, 0];
 
 
-pub static KNIGHT_OUTPOST_TABLE_WHITE: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
+pub static KNIGHT_OUTPOST_TABLE_WHITE: &[i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 0, 0, 0, 0, 0, 0, 0, 0,
 0, 0, 0, 0, 0, 0, 0, 0,
 0, 2, 5, 10, 10, 5, 2, 0,
@@ -75,7 +150,7 @@ pub static KNIGHT_OUTPOST_TABLE_WHITE: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0
 0, 0, 0, 0, 0, 0, 0, 0];
 
 
-pub static KNIGHT_OUTPOST_TABLE_BLACK: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
+pub static KNIGHT_OUTPOST_TABLE_BLACK: &[i32] = &[0, 0, 0, 0, 0, 0, 0, 0,
 0, 0, 0, 0, 0, 0, 0, 0,
 0, 0, 4, 5, 5, 4, 0, 0,
 0, 2, 5, 10, 10, 5, 2, 0,
@@ -85,7 +160,7 @@ pub static KNIGHT_OUTPOST_TABLE_BLACK: &'static [i32] = &[0, 0, 0, 0, 0, 0, 0, 0
 0, 0, 0, 0, 0, 0, 0, 0];
 
 
-pub static BISHOP_SQUARES_TABLE: &'static [i32] = &[-20,-10,-10,-10,-10,-10,-10,-20,
+pub static BISHOP_SQUARES_TABLE: &[i32] = &[-20,-10,-10,-10,-10,-10,-10,-20,
 -10,  0,  0,  0,  0,  0,  0,-10,
 -10,  0,  5, 10, 10,  5,  0,-10,
 -10,  5,  5, 10, 10,  5,  5,-10,
@@ -94,7 +169,7 @@ pub static BISHOP_SQUARES_TABLE: &'static [i32] = &[-20,-10,-10,-10,-10,-10,-10,
 -10,  5,  0,  0,  0,  0,  5,-10,
 -20,-10,-10,-10,-10,-10,-10,-20,];
 
-pub static BISHOP_SQUARES_TABLE_BLACK: &'static [i32] = &[-20, -10, -10, -10, -10, -10, -10, -20,
+pub static BISHOP_SQUARES_TABLE_BLACK: &[i32] = &[-20, -10, -10, -10, -10, -10, -10, -20,
 -10, 5, 0, 0, 0, 0, 5, -10, 
 -10, 10, 10, 10, 10, 10, 10, -10,
 -10, 0, 10, 10, 10, 10, 0, -10,
@@ -104,7 +179,7 @@ pub static BISHOP_SQUARES_TABLE_BLACK: &'static [i32] = &[-20, -10, -10, -10, -1
 -20, -10, -10, -10, -10, -10, -10, -20];
 
 
-pub static QUEEN_SQUARES_TABLE: &'static [i32] = &[-20,-10,-10, -5, -5,-10,-10,-20,
+pub static QUEEN_SQUARES_TABLE: &[i32] = &[-20,-10,-10, -5, -5,-10,-10,-20,
 -10,  0,  0,  0,  0,  0,  0,-10,
 -10,  0,  5,  5,  5,  5,  0,-10,
  -5,  0,  5,  5,  5,  5,  0, -5,
@@ -114,9 +189,9 @@ pub static QUEEN_SQUARES_TABLE: &'static [i32] = &[-20,-10,-10, -5, -5,-10,-10,-
 -20,-10,-10, -5, -5,-10,-10,-20];
 
 
-pub static QUEEN_SQUARES_TABLE_BLACK: &'static [i32] = &[-20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 5, 0, -10, -10, 0, 5, 5, 5, 5, 5, -10, -5, 0, 5, 5, 5, 5, 0, 0, -5, 0, 5, 5, 5, 5, 0, -5, -10, 0, 5, 5, 5, 5, 0, -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20];
+pub static QUEEN_SQUARES_TABLE_BLACK: &[i32] = &[-20, -10, -10, -5, -5, -10, -10, -20, -10, 0, 0, 0, 0, 5, 0, -10, -10, 0, 5, 5, 5, 5, 5, -10, -5, 0, 5, 5, 5, 5, 0, 0, -5, 0, 5, 5, 5, 5, 0, -5, -10, 0, 5, 5, 5, 5, 0, -10, -10, 0, 0, 0, 0, 0, 0, -10, -20, -10, -10, -5, -5, -10, -10, -20];
 
-pub static KING_SQUARES_TABLE: &'static [i32] = &[
+pub static KING_SQUARES_TABLE: &[i32] = &[
     20, 30, 10, 0, 0, 10, 30, 20,
     20, 20, 0, 0, 0, 0, 20, 20,
     -10, -20, -20, -20, -20, -20, -20, -10,
@@ -126,9 +201,9 @@ pub static KING_SQUARES_TABLE: &'static [i32] = &[
     -30, -40, -40, -50, -50, -40, -40, -30,
     -30, -40, -40, -50, -50, -40, -40, -30];
 
-pub static KING_SQUARES_TABLE_BLACK: &'static [i32] = &[-30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40, -40, -30, -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20, 30, 10, 0, 0, 10, 30, 20];
+pub static KING_SQUARES_TABLE_BLACK: &[i32] = &[-30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -30, -40, -40, -50, -50, -40, -40, -30, -20, -30, -30, -40, -40, -30, -30, -20, -10, -20, -20, -20, -20, -20, -20, -10, 20, 20, 0, 0, 0, 0, 20, 20, 20, 30, 10, 0, 0, 10, 30, 20];
 
-pub static KING_ENDGAME_TABLE: &'static [i32] = &[
+pub static KING_ENDGAME_TABLE: &[i32] = &[
     -50,-40,-30,-20,-20,-30,-40,-50,
     -30,-20,-10,  0,  0,-10,-20,-30,
     -30,-10, 20, 30, 30, 20,-10,-30,
@@ -139,10 +214,10 @@ pub static KING_ENDGAME_TABLE: &'static [i32] = &[
     -50,-30,-30,-30,-30,-30,-30,-50
 ];
 
-pub static KING_ENDGAME_TABLE_BLACK: &'static [i32] = &[-50, -30, -30, -30, -30, -30, -30, -50, -30, -30, 0, 0, 0, 0, -30, -30, -30, -10, 20, 30, 30, 20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 20, 30, 30, 20, -10, -30, -30, -20, -10, 0, 0, -10, -20, -30, -50, -40, -30, -20, -20, -30, -40, -50];
+pub static KING_ENDGAME_TABLE_BLACK: &[i32] = &[-50, -30, -30, -30, -30, -30, -30, -50, -30, -30, 0, 0, 0, 0, -30, -30, -30, -10, 20, 30, 30, 20, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 30, 40, 40, 30, -10, -30, -30, -10, 20, 30, 30, 20, -10, -30, -30, -20, -10, 0, 0, -10, -20, -30, -50, -40, -30, -20, -20, -30, -40, -50];
 
 
-pub static KNIGHT_SQUARES_TABLE: &'static [i32] = &[-50, -40, -30, -30, -30, -30, -40, -50,
+pub static KNIGHT_SQUARES_TABLE: &[i32] = &[-50, -40, -30, -30, -30, -30, -40, -50,
 -40, -20, 0, 5, 5, 0, -20, -40,
 -30, 5, 10, 15, 15, 10, 5, -30,
 -30, 0, 15, 20, 20, 15, 0, -30,
@@ -151,9 +226,9 @@ pub static KNIGHT_SQUARES_TABLE: &'static [i32] = &[-50, -40, -30, -30, -30, -30
 -40, -20, 0, 0, 0, 0, -20, -40,
 -50, -40, -30, -30, -30, -30, -40, -50];
 
-pub static KNIGHT_SQUARES_TABLE_BLACK: &'static [i32] = &[-50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15, 10, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15, 15, 10, 5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50];
+pub static KNIGHT_SQUARES_TABLE_BLACK: &[i32] = &[-50, -40, -30, -30, -30, -30, -40, -50, -40, -20, 0, 0, 0, 0, -20, -40, -30, 0, 10, 15, 15, 10, 0, -30, -30, 5, 15, 20, 20, 15, 5, -30, -30, 0, 15, 20, 20, 15, 0, -30, -30, 5, 10, 15, 15, 10, 5, -30, -40, -20, 0, 5, 5, 0, -20, -40, -50, -40, -30, -30, -30, -30, -40, -50];
 
-pub static ROOK_SQUARES_TABLE: &'static [i32] = &[ 0,  0,  0,  0,  0,  0,  0,  0,
+pub static ROOK_SQUARES_TABLE: &[i32] = &[ 0,  0,  0,  0,  0,  0,  0,  0,
 5, 10, 10, 10, 10, 10, 10,  5,
 -5,  0,  0,  0,  0,  0,  0, -5,
 -5,  0,  0,  0,  0,  0,  0, -5,
@@ -162,12 +237,15 @@ pub static ROOK_SQUARES_TABLE: &'static [i32] = &[ 0,  0,  0,  0,  0,  0,  0,  0
 -5,  0,  0,  0,  0,  0,  0, -5,
 0,  0,  0,  5,  5,  0,  0,  0];
 
-pub static ROOK_SQUARES_TABLE_BLACK: &'static [i32] = &[0, 0, 0, 5, 5, 0, 0, 0, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 5, 10, 10, 10, 10, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0];
+pub static ROOK_SQUARES_TABLE_BLACK: &[i32] = &[0, 0, 0, 5, 5, 0, 0, 0, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, -5, 0, 0, 0, 0, 0, 0, -5, 5, 10, 10, 10, 10, 10, 10, 5, 0, 0, 0, 0, 0, 0, 0, 0];
+
+// the king safety penalty for every number of attack units
+pub static KING_DANGER: &[i32] = &[0, 0, 1, 2, 3, 5, 7, 9, 12, 15, 18, 22, 26, 30, 35, 39, 44, 50, 56, 62, 68, 75, 82, 85, 89, 97, 105, 113, 122, 131, 140, 150, 169, 180, 191, 202, 213, 225, 237, 248, 260, 272, 283, 295, 307, 319, 330, 342, 354, 366, 377, 389, 401, 412, 424, 436, 448, 459, 471, 483, 494, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 500];
 
-pub static PIECES_ATTACKING_KING: &'static [f32] = &[0.0, 0.0, 0.5, 0.75, 0.88, 0.94, 0.97, 0.99, 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1.];
+pub static PIECES_ATTACKING_KING: &[f32] = &[0.0, 0.0, 0.5, 0.75, 0.88, 0.94, 0.97, 0.99, 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1., 1.];
 
-pub static PASSED_PAWNS_WHITE: &'static [i32] = &[0, 0, 16, 31, 63, 405, 810, 900];
-pub static PASSED_PAWNS_WHITE_OPENING: &'static [f32] = &[0., 0., 0., 0.1, 0.3, 0.6, 1., 1.];
+pub static PASSED_PAWNS_WHITE: &[i32] = &[0, 0, 16, 31, 63, 405, 810, 900];
+pub static PASSED_PAWNS_WHITE_OPENING: &[f32] = &[0., 0., 0., 0.1, 0.3, 0.6, 1., 1.];
 
-pub static PASSED_PAWNS_BLACK: &'static [i32] = &[900, 810, 405, 63, 31, 16, 0, 0];
-pub static PASSED_PAWNS_BLACK_OPENING: &'static [f32] = &[1., 1., 0.6, 0.3, 0.1, 0., 0., 0.];
\ No newline at end of file
+pub static PASSED_PAWNS_BLACK: &[i32] = &[900, 810, 405, 63, 31, 16, 0, 0];
+pub static PASSED_PAWNS_BLACK_OPENING: &[f32] = &[1., 1., 0.6, 0.3, 0.1, 0., 0., 0.];
\ No newline at end of file
@@ -0,0 +1,96 @@
+use chess::{BitBoard, Board, ChessMove, Color, Piece};
+
+const DARK_SQUARES: u64 = 0xAA55AA55AA55AA55;
+
+// keeps the positions that were played since the game started (and those made by the search on top of them)
+// so repetitions and the fifty-move rule can be detected, and scores draws with the contempt of the engine
+#[derive(Debug, Clone)]
+pub struct DrawTracker{
+    pub contempt: i32,
+    pub engine_color: Color,
+    hashes: Vec<u64>,
+    halfmove_clocks: Vec<u32>
+}
+
+impl DrawTracker{
+    pub fn new(board: &Board, halfmove_clock: u32) -> DrawTracker{
+        DrawTracker{contempt: 0, engine_color: board.side_to_move(), hashes: vec![board.get_hash()], halfmove_clocks: vec![halfmove_clock]}
+    }
+
+    pub fn halfmove_clock(&self) -> u32{
+        *self.halfmove_clocks.last().unwrap_or(&0)
+    }
+
+    pub fn push(&mut self, board: &Board, chess_move: ChessMove, new_board: &Board){
+        let irreversible = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn) || board.piece_on(chess_move.get_dest()).is_some();
+        let clock = if irreversible { 0 } else { self.halfmove_clock() + 1 };
+        self.hashes.push(new_board.get_hash());
+        self.halfmove_clocks.push(clock);
+    }
+
+    // a null move is handled as an irreversible move, positions before it can't be repeated
+    pub fn push_null(&mut self, new_board: &Board){
+        self.hashes.push(new_board.get_hash());
+        self.halfmove_clocks.push(0);
+    }
+
+    pub fn pop(&mut self){
+        self.hashes.pop();
+        self.halfmove_clocks.pop();
+    }
+
+    // the current position already happened since the last irreversible move (with the same side to move)
+    pub fn is_repetition(&self) -> bool{
+        let len = self.hashes.len();
+        let clock = self.halfmove_clock() as usize;
+        let current = self.hashes[len - 1];
+        let mut back = 4;
+        while back <= clock && back < len{
+            if self.hashes[len - 1 - back] == current{
+                return true;
+            }
+            back += 2;
+        }
+        false
+    }
+
+    pub fn is_fifty_moves(&self) -> bool{
+        self.halfmove_clock() >= 100
+    }
+
+    // the position is drawn by repetition, the fifty-move rule or insufficient material
+    pub fn is_draw(&self, board: &Board) -> bool{
+        self.is_fifty_moves() || self.is_repetition() || insufficient_material(board)
+    }
+
+    // the draw score from the side to move's point of view. a positive contempt makes the engine avoid draws
+    pub fn score(&self, board: &Board) -> i32{
+        if board.side_to_move() == self.engine_color{
+            return -self.contempt;
+        }
+        self.contempt
+    }
+}
+
+
+// neither side can mate: bare kings, a single minor piece or bishops that all stand on the same color
+pub fn insufficient_material(board: &Board) -> bool{
+    if (board.pieces(Piece::Pawn) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen)).popcnt() > 0{
+        return false;
+    }
+    let knights = board.pieces(Piece::Knight).popcnt();
+    let bishops = *board.pieces(Piece::Bishop);
+    if knights + bishops.popcnt() <= 1{
+        return true;
+    }
+    if knights == 0{
+        let dark = (bishops & BitBoard::new(DARK_SQUARES)).popcnt();
+        return dark == 0 || dark == bishops.popcnt();
+    }
+    false
+}
+
+// the halfmove clock is the fifth field of a fen, missing or broken fields count as 0
+pub fn halfmove_clock_from_fen(fen: &str) -> u32{
+    fen.split_whitespace().nth(4).and_then(|x| x.parse().ok()).unwrap_or(0)
+}
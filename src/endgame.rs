@@ -0,0 +1,235 @@
+use chess::{BitBoard, Board, Color, File, Piece, Rank, Square};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use crate::bitboard_operators::DARK_SQUARES;
+use crate::bitbase;
+use crate::constants;
+use crate::utils::{distance, get_piece_type, relative_square};
+
+// endgames the general evaluation gets wrong. the material of a position is looked up in a registry of
+// specialised evaluators, the scale factors make the general evaluation of drawish material smaller
+
+// a won position, enough to keep the search from trading into something worse but far from a mate score
+pub const KNOWN_WIN: i32 = 3000;
+
+// the general evaluation is multiplied by scale / SCALE_NORMAL
+pub const SCALE_NORMAL: i32 = 64;
+
+// the score of the strong side
+type Evaluator = fn(&Board, Color) -> i32;
+
+const ENDGAMES: &[(&str, Evaluator)] = &[
+    ("KBNK", kbnk),
+    ("KNNK", knnk),
+    ("KPK", kpk),
+    ("KRKP", krkp),
+    ("KQKP", kqkp)
+];
+
+// the number of every piece of both sides packed in four bits each
+fn material_key(board: &Board) -> u64{
+    let mut key = 0;
+    for color in [Color::White, Color::Black]{
+        for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]{
+            let count = get_piece_type(board, piece, color).popcnt() as u64;
+            key |= count.min(15) << ((color.to_index() * 5 + piece.to_index()) * 4);
+        }
+    }
+    key
+}
+
+// the key of a signature like "KRKP", the pieces of the strong side come before the second king
+fn signature_key(signature: &str, strong: Color) -> u64{
+    let weak_start = signature[1..].find('K').unwrap() + 1;
+    let sides = [(&signature[..weak_start], strong), (&signature[weak_start..], !strong)];
+    let mut key = 0;
+    for (pieces, color) in sides{
+        for piece in pieces.chars(){
+            let piece = match piece{
+                'P'=>Piece::Pawn,
+                'N'=>Piece::Knight,
+                'B'=>Piece::Bishop,
+                'R'=>Piece::Rook,
+                'Q'=>Piece::Queen,
+                _=>continue
+            };
+            key += 1 << ((color.to_index() * 5 + piece.to_index()) * 4);
+        }
+    }
+    key
+}
+
+static REGISTRY: LazyLock<HashMap<u64, (Evaluator, Color)>> = LazyLock::new(|| {
+    let mut registry = HashMap::new();
+    for (signature, evaluator) in ENDGAMES{
+        for strong in [Color::White, Color::Black]{
+            registry.insert(signature_key(signature, strong), (*evaluator, strong));
+        }
+    }
+    registry
+});
+
+// the score of a known endgame from the side to move's point of view, none when the general evaluation has to do
+pub fn evaluate(board: &Board) -> Option<i32>{
+    let (score, strong) = match REGISTRY.get(&material_key(board)){
+        Some((evaluator, strong))=>(evaluator(board, *strong), *strong),
+        None=>{
+            let strong = [Color::White, Color::Black].into_iter().find(|color| is_kxk(board, *color))?;
+            (kxk(board, strong), strong)
+        }
+    };
+    if board.side_to_move() == strong{
+        Some(score)
+    }else{
+        Some(-score)
+    }
+}
+
+// how much of the general evaluation is kept for the side that is ahead, out of SCALE_NORMAL
+pub fn scale_factor(board: &Board, strong: Color) -> i32{
+    let weak = !strong;
+    let strong_material = non_pawn_material(board, strong);
+    let weak_material = non_pawn_material(board, weak);
+
+    // without pawns the strong side needs at least a rook more to win
+    if get_piece_type(board, Piece::Pawn, strong).popcnt() == 0 && strong_material - weak_material <= constants::BISHOP_VAL.1{
+        if strong_material < constants::ROOK_VAL.1{
+            return 0;
+        }else if weak_material <= constants::BISHOP_VAL.1{
+            return 4;
+        }
+        return 14;
+    }
+
+    // bishops of opposite colors, drawish even a few pawns up when nothing else is left
+    let strong_bishops = get_piece_type(board, Piece::Bishop, strong);
+    let weak_bishops = get_piece_type(board, Piece::Bishop, weak);
+    if strong_bishops.popcnt() == 1 && weak_bishops.popcnt() == 1 && is_dark(strong_bishops) != is_dark(weak_bishops){
+        if strong_material == constants::BISHOP_VAL.1 && weak_material == constants::BISHOP_VAL.1{
+            return 16;
+        }
+        return 46;
+    }
+    SCALE_NORMAL
+}
+
+// scales a general evaluation from the side to move's point of view
+pub fn scale(board: &Board, score: i32) -> i32{
+    let strong = if score > 0 { board.side_to_move() } else { !board.side_to_move() };
+    score * scale_factor(board, strong) / SCALE_NORMAL
+}
+
+fn non_pawn_material(board: &Board, color: Color) -> i32{
+    get_piece_type(board, Piece::Knight, color).popcnt() as i32 * constants::KNIGHT_VAL.1
+        + get_piece_type(board, Piece::Bishop, color).popcnt() as i32 * constants::BISHOP_VAL.1
+        + get_piece_type(board, Piece::Rook, color).popcnt() as i32 * constants::ROOK_VAL.1
+        + get_piece_type(board, Piece::Queen, color).popcnt() as i32 * constants::QUEEN_VAL.1
+}
+
+fn is_dark(bitboard: BitBoard) -> bool{
+    (bitboard & BitBoard::new(DARK_SQUARES)).popcnt() > 0
+}
+
+// a bonus for the weak king on the edge, the most in the corners
+fn push_to_edge(square: Square) -> i32{
+    let file = square.get_file().to_index() as i32;
+    let rank = square.get_rank().to_index() as i32;
+    15 * ((3 - file.min(7 - file)) + (3 - rank.min(7 - rank)))
+}
+
+// a bonus for the strong king close to the weak king
+fn push_close(a: Square, b: Square) -> i32{
+    140 - 20 * distance(a, b)
+}
+
+fn manhattan(a: Square, b: Square) -> i32{
+    (a.get_file().to_index() as i32 - b.get_file().to_index() as i32).abs() + (a.get_rank().to_index() as i32 - b.get_rank().to_index() as i32).abs()
+}
+
+// the strong side has mating material against a bare king
+fn is_kxk(board: &Board, strong: Color) -> bool{
+    if board.color_combined(!strong).popcnt() != 1{
+        return false;
+    }
+    let bishops = get_piece_type(board, Piece::Bishop, strong);
+    get_piece_type(board, Piece::Queen, strong).popcnt() > 0
+        || get_piece_type(board, Piece::Rook, strong).popcnt() > 0
+        || (is_dark(bishops) && (bishops & !BitBoard::new(DARK_SQUARES)).popcnt() > 0)
+}
+
+// mop up: drive the bare king to the edge and bring our king closer
+fn kxk(board: &Board, strong: Color) -> i32{
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(!strong);
+    let pawns = get_piece_type(board, Piece::Pawn, strong).popcnt() as i32;
+    KNOWN_WIN + non_pawn_material(board, strong) + pawns * constants::PAWN_VAL.1 + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+}
+
+// the bare king can only be mated in a corner of the color of the bishop
+fn kbnk(board: &Board, strong: Color) -> i32{
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(!strong);
+    let corners = if is_dark(get_piece_type(board, Piece::Bishop, strong)){
+        [Square::A1, Square::H8]
+    }else{
+        [Square::A8, Square::H1]
+    };
+    let corner_distance = manhattan(weak_king, corners[0]).min(manhattan(weak_king, corners[1]));
+    KNOWN_WIN + push_close(strong_king, weak_king) + 20 * (14 - corner_distance) + push_to_edge(weak_king) / 3
+}
+
+// two knights can't force a mate
+fn knnk(board: &Board, strong: Color) -> i32{
+    push_to_edge(board.king_square(!strong)) / 10
+}
+
+// the bitbase knows every position, the won ones score more as the pawn gets closer to promoting
+fn kpk(board: &Board, strong: Color) -> i32{
+    if bitbase::probe_kpk(board) != Some(true){
+        return 0;
+    }
+    let pawn = relative_square(get_piece_type(board, Piece::Pawn, strong).to_square(), strong);
+    KNOWN_WIN + constants::PAWN_VAL.1 + 20 * pawn.get_rank().to_index() as i32
+}
+
+// a rook wins against a pawn unless the pawn is far advanced with its king next to it
+fn krkp(board: &Board, strong: Color) -> i32{
+    let weak = !strong;
+    let strong_king = relative_square(board.king_square(strong), weak);
+    let weak_king = relative_square(board.king_square(weak), weak);
+    let rook = relative_square(get_piece_type(board, Piece::Rook, strong).to_square(), weak);
+    let pawn = relative_square(get_piece_type(board, Piece::Pawn, weak).to_square(), weak);
+    let queening = Square::make_square(Rank::Eighth, pawn.get_file());
+    let push_square = pawn.forward(Color::White).unwrap_or(queening);
+    let tempo = (board.side_to_move() == strong) as i32;
+    let rook_value = constants::ROOK_VAL.1 - constants::PAWN_VAL.1;
+
+    // our king is in front of the pawn
+    if strong_king.get_file() == pawn.get_file() && strong_king.get_rank() > pawn.get_rank(){
+        return rook_value - distance(strong_king, pawn);
+    }
+    // their king is too far from the pawn and the rook
+    if distance(weak_king, pawn) >= 3 + 1 - tempo && distance(weak_king, rook) >= 3{
+        return rook_value - distance(strong_king, pawn);
+    }
+    // an advanced pawn next to its king and our king far away
+    if weak_king.get_rank().to_index() >= 5 && distance(weak_king, pawn) == 1 && strong_king.get_rank().to_index() <= 4 && distance(strong_king, pawn) > 2 + tempo{
+        return 80 - 8 * distance(strong_king, pawn);
+    }
+    200 - 8 * (distance(strong_king, push_square) - distance(weak_king, push_square) - distance(pawn, queening))
+}
+
+// the queen wins unless a rook or bishop pawn on the seventh rank is protected by its king
+fn kqkp(board: &Board, strong: Color) -> i32{
+    let weak = !strong;
+    let strong_king = board.king_square(strong);
+    let weak_king = board.king_square(weak);
+    let pawn = get_piece_type(board, Piece::Pawn, weak).to_square();
+    let mut score = push_close(strong_king, weak_king);
+    let file = pawn.get_file();
+    let drawish_file = matches!(file, File::A | File::C | File::F | File::H);
+    if relative_square(pawn, weak).get_rank() != Rank::Seventh || distance(weak_king, pawn) != 1 || !drawish_file{
+        score += constants::QUEEN_VAL.1 - constants::PAWN_VAL.1;
+    }
+    score
+}
@@ -0,0 +1,45 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// the static evaluations of the positions searched before, by zobrist hash. the table can be shared between threads
+// without a lock: an entry keeps the score and the key xored with the score, an entry another thread was writing at
+// the same time doesn't match its key and is read as a miss
+pub struct EvalCache{
+    keys: Vec<AtomicU64>,
+    scores: Vec<AtomicU64>,
+    mask: usize
+}
+
+impl EvalCache{
+    // the size is rounded down to a power of two
+    pub fn new(size: usize) -> EvalCache{
+        let size = if size.is_power_of_two() { size } else { (size.next_power_of_two() / 2).max(1) };
+        EvalCache{
+            keys: (0..size).map(|_| AtomicU64::new(0)).collect(),
+            scores: (0..size).map(|_| AtomicU64::new(0)).collect(),
+            mask: size - 1
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<i32>{
+        let index = key as usize & self.mask;
+        let score = self.scores[index].load(Ordering::Relaxed);
+        if self.keys[index].load(Ordering::Relaxed) ^ score == key{
+            Some(score as i64 as i32)
+        }else{
+            None
+        }
+    }
+
+    pub fn add(&self, key: u64, score: i32){
+        let index = key as usize & self.mask;
+        let score = score as i64 as u64;
+        self.keys[index].store(key ^ score, Ordering::Relaxed);
+        self.scores[index].store(score, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Debug for EvalCache{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result{
+        write!(f, "EvalCache({} entries)", self.mask + 1)
+    }
+}
@@ -0,0 +1,132 @@
+use chess::Color;
+use crate::constants::{Access, Score};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Term{
+    Material,
+    Pst,
+    KnightMobility,
+    BishopMobility,
+    RookMobility,
+    QueenMobility,
+    QueenTropism,
+    KnightOutpost,
+    MinorPieces,
+    KingSafety,
+    Threats,
+    Space,
+    CentreControl,
+    PawnStorm,
+    PawnShelter,
+    BlockedPieces,
+    RookFiles,
+    HeavyPieces,
+    BishopPair,
+    PassedPawns,
+    PawnStructure
+}
+
+pub const TERMS: [Term; 21] = [Term::Material, Term::Pst, Term::KnightMobility, Term::BishopMobility, Term::RookMobility, Term::QueenMobility, Term::QueenTropism,
+    Term::KnightOutpost, Term::MinorPieces, Term::KingSafety, Term::Threats, Term::Space, Term::CentreControl,
+    Term::PawnStorm, Term::PawnShelter, Term::BlockedPieces, Term::RookFiles, Term::HeavyPieces, Term::BishopPair,
+    Term::PassedPawns, Term::PawnStructure];
+
+impl Term{
+    pub fn name(&self) -> &'static str{
+        match self{
+            Term::Material=>"material",
+            Term::Pst=>"pst",
+            Term::KnightMobility=>"knight mobility",
+            Term::BishopMobility=>"bishop mobility",
+            Term::RookMobility=>"rook mobility",
+            Term::QueenMobility=>"queen mobility",
+            Term::QueenTropism=>"queen tropism",
+            Term::KnightOutpost=>"knight outposts",
+            Term::MinorPieces=>"minor pieces",
+            Term::KingSafety=>"king safety",
+            Term::Threats=>"threats",
+            Term::Space=>"space",
+            Term::CentreControl=>"centre control",
+            Term::PawnStorm=>"pawn storm",
+            Term::PawnShelter=>"pawn shelter",
+            Term::BlockedPieces=>"blocked pieces",
+            Term::RookFiles=>"rook files",
+            Term::HeavyPieces=>"rooks and queens",
+            Term::BishopPair=>"bishop pair",
+            Term::PassedPawns=>"passed pawns",
+            Term::PawnStructure=>"pawn structure"
+        }
+    }
+}
+
+
+// the opening and endgame values of every evaluation term for both sides.
+// the evaluation always fills one, so the trace can't drift from the real score
+#[derive(Debug, Clone)]
+pub struct EvalTrace{
+    pub phase: i32,
+    terms: [[Score; 2]; TERMS.len()]
+}
+
+impl EvalTrace{
+    pub fn new(phase: i32) -> EvalTrace{
+        EvalTrace{phase, terms: [[(0, 0); 2]; TERMS.len()]}
+    }
+
+    pub fn add(&mut self, term: Term, color: Color, score: Score){
+        let entry = &mut self.terms[term as usize][color.to_index()];
+        entry.0 += score.0;
+        entry.1 += score.1;
+    }
+
+    // a value that is the same in the opening and in the endgame
+    pub fn add_both(&mut self, term: Term, color: Color, value: i32){
+        self.add(term, color, (value, value));
+    }
+
+    pub fn add_scaled(&mut self, term: Term, color: Color, score: Score, count: i32){
+        self.add(term, color, (score.0 * count, score.1 * count));
+    }
+
+    pub fn get(&self, term: Term, color: Color) -> Score{
+        self.terms[term as usize][color.to_index()]
+    }
+
+    // the term blended with the phase, from white's point of view
+    pub fn blended(&self, term: Term) -> i32{
+        let white = self.get(term, Color::White);
+        let black = self.get(term, Color::Black);
+        (white.0 - black.0, white.1 - black.1).taper(self.phase)
+    }
+
+    // the opening and endgame totals from white's point of view
+    pub fn total(&self) -> Score{
+        let mut total = (0, 0);
+        for term in TERMS{
+            let white = self.get(term, Color::White);
+            let black = self.get(term, Color::Black);
+            total.0 += white.0 - black.0;
+            total.1 += white.1 - black.1;
+        }
+        total
+    }
+
+    // the final score from white's point of view
+    pub fn value(&self) -> i32{
+        self.total().taper(self.phase)
+    }
+
+    pub fn table(&self) -> String{
+        let mut table = format!("{:<16}|{:>7}{:>7} |{:>7}{:>7} |{:>7}\n", "term", "w mg", "w eg", "b mg", "b eg", "total");
+        table.push_str(&format!("{}\n", "-".repeat(55)));
+        for term in TERMS{
+            let white = self.get(term, Color::White);
+            let black = self.get(term, Color::Black);
+            table.push_str(&format!("{:<16}|{:>7}{:>7} |{:>7}{:>7} |{:>7}\n", term.name(), white.0, white.1, black.0, black.1, self.blended(term)));
+        }
+        table.push_str(&format!("{}\n", "-".repeat(55)));
+        let total = self.total();
+        table.push_str(&format!("phase {} of 256, mg {} eg {}, final {} (white's point of view)\n", self.phase, total.0, total.1, self.value()));
+        table
+    }
+}
@@ -1,24 +1,24 @@
 use std::ops::BitAnd;
-use std::ops::BitOr;
-use std::ops::BitXor;
 use std::ops::Not;
+use crate::attacks::AttackMaps;
 use crate::bitboard_operators;
 use crate::bitboard_operators::black_passed_pawns;
 use crate::bitboard_operators::white_front_spans;
 use crate::bitboard_operators::white_passed_pawns;
 use crate::constants;
 use crate::constants::Access;
-use crate::constants::BISHOP_SQUARES_TABLE;
-use crate::constants::KING_ENDGAME_TABLE;
-use crate::constants::KING_SQUARES_TABLE;
+use crate::eval_trace::{EvalTrace, Term};
+use crate::params::{EvalParams, DEFAULT_PARAMS};
 use crate::utils;
-use crate::bitboard_operators::{open_files, black_pawns_behind_own, white_pawns_behind_own, king_attacks, black_front_spans, file_fill, half_open_files};
+use crate::pawns::PawnEntry;
+use crate::material::MaterialPst;
+use crate::bitbase;
+use crate::bitboard_operators::{open_files, black_pawns_behind_own, white_pawns_behind_own, king_attacks, black_front_spans, file_fill};
 use crate::utils::distance;
 use crate::utils::{get_piece_type, sum_by_table};
 use chess::BitBoard;
 use chess::Color;
 use chess::Piece;
-use chess::Rank;
 use chess::Square;
 
 pub fn evaluate(board: &chess::Board) -> i32{
@@ -43,19 +43,19 @@ pub fn evaluate(board: &chess::Board) -> i32{
 
     
     // evaluate white material advantage
-    let white_queens = get_piece_type(&board, chess::Piece::Queen, chess::Color::White);
-    let white_bishops = get_piece_type(&board, chess::Piece::Bishop, chess::Color::White);
-    let white_knights = get_piece_type(&board, chess::Piece::Knight, chess::Color::White);
-    let white_rooks = get_piece_type(&board, chess::Piece::Rook, chess::Color::White);
-    let white_pawns = get_piece_type(&board, chess::Piece::Pawn, chess::Color::White);
+    let white_queens = get_piece_type(board, chess::Piece::Queen, chess::Color::White);
+    let white_bishops = get_piece_type(board, chess::Piece::Bishop, chess::Color::White);
+    let white_knights = get_piece_type(board, chess::Piece::Knight, chess::Color::White);
+    let white_rooks = get_piece_type(board, chess::Piece::Rook, chess::Color::White);
+    let white_pawns = get_piece_type(board, chess::Piece::Pawn, chess::Color::White);
     
     
     // evaluate black material advantage
-    let black_queens = get_piece_type(&board, chess::Piece::Queen, chess::Color::Black);
-    let black_bishops = get_piece_type(&board, chess::Piece::Bishop, chess::Color::Black);
-    let black_knights = get_piece_type(&board, chess::Piece::Knight, chess::Color::Black);
-    let black_rooks = get_piece_type(&board, chess::Piece::Rook, chess::Color::Black);
-    let black_pawns = get_piece_type(&board, chess::Piece::Pawn, chess::Color::Black);
+    let black_queens = get_piece_type(board, chess::Piece::Queen, chess::Color::Black);
+    let black_bishops = get_piece_type(board, chess::Piece::Bishop, chess::Color::Black);
+    let black_knights = get_piece_type(board, chess::Piece::Knight, chess::Color::Black);
+    let black_rooks = get_piece_type(board, chess::Piece::Rook, chess::Color::Black);
+    let black_pawns = get_piece_type(board, chess::Piece::Pawn, chess::Color::Black);
     
     let pieces_total_count = board.pieces(chess::Piece::Bishop).popcnt() + board.pieces(chess::Piece::Knight).popcnt() + board.pieces(chess::Piece::Rook).popcnt();
     let endgame = board.pieces(chess::Piece::Queen).popcnt() == 0 && pieces_total_count <= 2;
@@ -96,21 +96,16 @@ pub fn evaluate(board: &chess::Board) -> i32{
 
 
         // print!("{}", white_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(knight)));
-        if (white_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() >= 1{
-
-            if (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() == 0{
-                white_outposted_knights += constants::KNIGHT_OUTPOST_TABLE_WHITE[knight.to_index()];
-            }
+        if (white_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() >= 1 && (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() == 0{
+            white_outposted_knights += constants::KNIGHT_OUTPOST_TABLE_WHITE[knight.to_index()];
         }
     }
 
     let mut black_outposted_knights = 0;
 
     for knight in black_knights{
-        if (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() >= 1{
-            if (black_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() == 0{
-                black_outposted_knights += constants::KNIGHT_OUTPOST_TABLE_BLACK[knight.to_index()];
-            }
+        if (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() >= 1 && (black_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(knight))).popcnt() == 0{
+            black_outposted_knights += constants::KNIGHT_OUTPOST_TABLE_BLACK[knight.to_index()];
         }
     }
     // print!("white k-o: {}", white_outposted_knights);
@@ -120,35 +115,27 @@ pub fn evaluate(board: &chess::Board) -> i32{
     // handle blocked bishops
     let mut w_blocked = 0;
 
-    w_blocked += utils::blocked_bishop(&board, chess::Square::C1, chess::Square::D2, true);
-    w_blocked += utils::blocked_bishop(&board, chess::Square::F1, chess::Square::E2, true);
+    w_blocked += utils::blocked_bishop(board, chess::Square::C1, chess::Square::D2, true);
+    w_blocked += utils::blocked_bishop(board, chess::Square::F1, chess::Square::E2, true);
 
     let mut b_blocked = 0;
 
-    b_blocked += utils::blocked_bishop(&board, chess::Square::C8, chess::Square::D7, false);
-    b_blocked += utils::blocked_bishop(&board, chess::Square::F8, chess::Square::E7, false);
+    b_blocked += utils::blocked_bishop(board, chess::Square::C8, chess::Square::D7, false);
+    b_blocked += utils::blocked_bishop(board, chess::Square::F8, chess::Square::E7, false);
 
     // handle blocked rooks
-    if white_king == Square::B1 || white_king == Square::C1{
-        if board.piece_on(Square::A1) == Some(Piece::Rook) || board.piece_on(Square::A2) == Some(Piece::Rook) || board.piece_on(Square::B1) == Some(Piece::Rook){
-            w_blocked += -50;
-        }
+    if (white_king == Square::B1 || white_king == Square::C1) && (board.piece_on(Square::A1) == Some(Piece::Rook) || board.piece_on(Square::A2) == Some(Piece::Rook) || board.piece_on(Square::B1) == Some(Piece::Rook)){
+        w_blocked += -50;
     }
-    if white_king == Square::F1 || white_king == Square::G1{
-        if board.piece_on(Square::H1) == Some(Piece::Rook) || board.piece_on(Square::H2) == Some(Piece::Rook) || board.piece_on(Square::G1) == Some(Piece::Rook){
-            w_blocked += -50;
-        }
+    if (white_king == Square::F1 || white_king == Square::G1) && (board.piece_on(Square::H1) == Some(Piece::Rook) || board.piece_on(Square::H2) == Some(Piece::Rook) || board.piece_on(Square::G1) == Some(Piece::Rook)){
+        w_blocked += -50;
     }
 
-    if black_king == Square::B8 || black_king == Square::C8{
-        if board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::B8) == Some(Piece::Rook){
-            b_blocked += -50;
-        }
+    if (black_king == Square::B8 || black_king == Square::C8) && (board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::B8) == Some(Piece::Rook)){
+        b_blocked += -50;
     }
-    if black_king == Square::F8 || black_king == Square::G8{
-        if board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::G8) == Some(Piece::Rook){
-            b_blocked += -50;
-        }
+    if (black_king == Square::F8 || black_king == Square::G8) && (board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::G8) == Some(Piece::Rook)){
+        b_blocked += -50;
     }
 
 
@@ -156,7 +143,7 @@ pub fn evaluate(board: &chess::Board) -> i32{
     // evaluate king safety
     // calculate pawn shield for white
     let white_king_zone = king_attacks(BitBoard::from_square( white_king));
-    let mut white_pawn_shield = 3 as i32 - white_pawns.bitand(white_king_zone).popcnt() as i32;
+    let mut white_pawn_shield = 3_i32 - white_pawns.bitand(white_king_zone).popcnt() as i32;
     white_pawn_shield *= -25;
     
     // calculate pawns going for the king white
@@ -171,7 +158,7 @@ pub fn evaluate(board: &chess::Board) -> i32{
     
     // calculate pawn shield for black
     let black_king_zone = king_attacks(BitBoard::from_square( black_king));
-    let mut black_pawn_shield = 3 as i32 - black_pawns.bitand(black_king_zone).popcnt() as i32;
+    let mut black_pawn_shield = 3_i32 - black_pawns.bitand(black_king_zone).popcnt() as i32;
     black_pawn_shield *= -25;
 
     // calculate pawns going for the king white
@@ -184,12 +171,11 @@ pub fn evaluate(board: &chess::Board) -> i32{
     }
 
 
-    let kingsq_w;
-    if endgame{
-        kingsq_w = sum_by_table(&BitBoard::from_square(white_king), constants::KING_ENDGAME_TABLE, false);
+    let kingsq_w = if endgame{
+        sum_by_table(&BitBoard::from_square(white_king), constants::KING_ENDGAME_TABLE, false)
     }else{
-        kingsq_w = sum_by_table(&BitBoard::from_square(white_king), constants::KING_SQUARES_TABLE, false);
-    }
+        sum_by_table(&BitBoard::from_square(white_king), constants::KING_SQUARES_TABLE, false)
+    };
 
     let mut num_white: i32 = white_queens.popcnt() as i32 * constants::QUEEN_VAL.access_endgame(endgame) + 
         white_bishops.popcnt() as i32 * constants::BISHOP_VAL.access_endgame(endgame) + 
@@ -218,13 +204,11 @@ pub fn evaluate(board: &chess::Board) -> i32{
     let rooksq_b = sum_by_table(&black_rooks, constants::ROOK_SQUARES_TABLE, true);
     
     
-    let kingsq_b;
-    if endgame{
-        kingsq_b = sum_by_table(&BitBoard::from_square(black_king), constants::KING_ENDGAME_TABLE, true);
+    let kingsq_b = if endgame{
+        sum_by_table(&BitBoard::from_square(black_king), constants::KING_ENDGAME_TABLE, true)
     }else{
-        kingsq_b = sum_by_table(&BitBoard::from_square(black_king), constants::KING_SQUARES_TABLE, true);
-
-    }
+        sum_by_table(&BitBoard::from_square(black_king), constants::KING_SQUARES_TABLE, true)
+    };
 
 
     let mut num_black: i32 = black_queens.popcnt() as i32 * constants::QUEEN_VAL.access_endgame(endgame) + 
@@ -253,652 +237,530 @@ pub fn evaluate(board: &chess::Board) -> i32{
 
 
 pub fn evaluate_rework(board: &chess::Board) -> i32{
+    evaluate_with_params(board, 0, &DEFAULT_PARAMS)
+}
+
+
+// the draw score is returned for a stalemate, from the side to move's point of view
+pub fn evaluate_with_params(board: &chess::Board, draw_score: i32, params: &EvalParams) -> i32{
+    evaluate_with_pawns(board, draw_score, params, &PawnEntry::new(board, params), &MaterialPst::new(board, params))
+}
+
+
+// the evaluation (but the pawn structure) with the pawn data the search took from the pawn hash table
+// and the material and piece square values it keeps up to date move by move
+pub fn evaluate_with_pawns(board: &chess::Board, draw_score: i32, params: &EvalParams, pawns: &PawnEntry, material: &MaterialPst) -> i32{
     let status = board.status();
     if status == chess::BoardStatus::Checkmate{
-        match board.side_to_move(){
-            chess::Color::Black=>{
-                return 9999;
-            }
-            chess::Color::White=>{
-                return -9999;
-            }
-        }
+        // the score is from the side to move's point of view, and the side to move is the one that got mated
+        return -9999;
     }else if status == chess::BoardStatus::Stalemate{
-        return 0;
+        return draw_score;
     }
-    
 
-    let white_king = board.king_square(chess::Color::White);
-    let black_king = board.king_square(chess::Color::Black);
+    let mut trace = EvalTrace::new(game_phase(board));
+    evaluate_terms(board, &mut trace, params, pawns, material);
+    let score = trace.value();
+    match board.side_to_move(){
+        chess::Color::Black=>return -score,
+        chess::Color::White=>return score
+    }
+}
 
-    let white_king_zone = king_attacks(BitBoard::from_square( white_king));
-    let black_king_zone = king_attacks(BitBoard::from_square( black_king));
 
+// the evaluation term by term, including the pawn structure that the search takes from the pawn hash table
+pub fn trace(board: &chess::Board) -> EvalTrace{
+    trace_with_params(board, &DEFAULT_PARAMS)
+}
 
 
-    let mut total_black_score = 0;
-    let mut total_white_score = 0;
-    
-    let pieces_total_count = board.pieces(chess::Piece::Bishop).popcnt() + board.pieces(chess::Piece::Knight).popcnt() + board.pieces(chess::Piece::Rook).popcnt();
-    let endgame = board.pieces(chess::Piece::Queen).popcnt() == 0 && pieces_total_count <= 2;
+pub fn trace_with_params(board: &chess::Board, params: &EvalParams) -> EvalTrace{
+    let mut trace = EvalTrace::new(game_phase(board));
+    let pawns = PawnEntry::new(board, params);
+    evaluate_terms(board, &mut trace, params, &pawns, &MaterialPst::new(board, params));
+    for color in [Color::White, Color::Black]{
+        trace.add(Term::PawnStructure, color, pawns.structure[color.to_index()]);
+    }
+    trace
+}
 
-    let black_pawns = get_piece_type(&board, chess::Piece::Pawn, chess::Color::Black);
-    let white_pawns = get_piece_type(&board, chess::Piece::Pawn, chess::Color::White);
-
-    let all_pieces = board.combined();
-    let mut pieces_attacking_white_king = 0;
-    let mut pieces_attacking_black_king = 0;
-
-    let mut pieces_attacking_white_king_units = 0;
-    let mut pieces_attacking_black_king_units = 0;
-
-    for square in *all_pieces{
-        let piece_type = board.piece_on(square);
-        let piece_color = board.color_on(square);
-        let mut cur_score = 0;
-        match piece_type{
-            None=>{},
-            Some(piece)=>{
-                if let Some(color) = piece_color{
-                    match piece {
-                        Piece::Bishop=>{
-                            cur_score += constants::BISHOP_VAL.access_endgame(endgame);
-                            if color == Color::White{
-                                cur_score += BISHOP_SQUARES_TABLE[square.to_index()];
-
-                                if chess::get_bishop_rays(square).bitand(black_king_zone).popcnt() > 0{
-                                    // check if the queen is attacking the black king on the real board within 2 squares
-                            
-                                    pieces_attacking_black_king += 1;
-                                    pieces_attacking_black_king_units += 1;
-                                    
-                                }
-
-                                // get the squares that are attacked by the bishop
-                                let bishop_rays = chess::get_bishop_rays(square);
-                                let mobility = bishop_rays.bitxor(board.combined()).bitand(bishop_rays) .popcnt();
-                                cur_score += (mobility as i32 - 6) * constants::BISHOP_MOBILITY_VAL.access_endgame(endgame);
-                            }else{
-                                cur_score += constants::BISHOP_SQUARES_TABLE_BLACK[square.to_index()];
-
-                                if chess::get_bishop_rays(square).bitand(white_king_zone).popcnt() > 0{
-                                    // check if the queen is attacking the black king on the real board within 2 squares
-                                        pieces_attacking_white_king += 1;
-                                        pieces_attacking_white_king_units += 1;
-                                    
-                                }
-
-                                // get the squares that are attacked by the bishop
-                                let bishop_rays = chess::get_bishop_rays(square);
-                                let mobility = bishop_rays.bitxor(board.combined()).bitand(bishop_rays) .popcnt();
-                                cur_score += (mobility as i32 - 6) * constants::BISHOP_MOBILITY_VAL.access_endgame(endgame);
-                            }
-                            
-                        },
-                        Piece::King=>{
-                            if endgame{
-                                if color == Color::White{
-                                    cur_score += KING_ENDGAME_TABLE[square.to_index()];
-
-                                }else{
-                                    cur_score += KING_ENDGAME_TABLE[utils::mirror_square(&square).to_index()];
-
-                                }
-                            }else{
-                                if color == Color::White{
-                                    cur_score += KING_SQUARES_TABLE[square.to_index()];
-
-                                }else{
-                                    cur_score += constants::KING_SQUARES_TABLE_BLACK[square.to_index()];
-
-                                }
-                            }
-                        },
-                        Piece::Knight=>{
-                            cur_score += constants::KNIGHT_VAL.access_endgame(endgame);
-                            
-                            // get the squares that the knight is attacking
-                            let knight_attacks = chess::get_knight_moves(square);
-
-
-                            if color == Color::White{
-                                cur_score += constants::KNIGHT_SQUARES_TABLE[square.to_index()];
-                                // check if the knight is attacking the king
-                                if chess::get_knight_moves(square).bitand(black_king_zone).popcnt() > 0{
-                                    pieces_attacking_black_king += 1;
-                                    pieces_attacking_black_king_units += 1;
-                                }
-
-                                // get the squares that the knight is attacking
-                                let mobility = knight_attacks.bitxor(board.combined()).bitand(knight_attacks).popcnt();
-                                cur_score += (mobility as i32 - 4) * constants::KNIGHT_MOBILITY_VAL.access_endgame(endgame) ;
-
-                            }else{
-                                cur_score += constants::KNIGHT_SQUARES_TABLE_BLACK[square.to_index()];
-                                // check if the knight is attacking the king
-                                if chess::get_knight_moves(square).bitand(white_king_zone).popcnt() > 0{
-                                    pieces_attacking_white_king += 1;
-                                    pieces_attacking_white_king_units += 1;
-                                }
-
-                                // get the squares that the knight is attacking
-                                let mobility = knight_attacks.bitxor(board.combined()).bitand(knight_attacks).popcnt();
-                                cur_score += (mobility as i32 - 4) * constants::KNIGHT_MOBILITY_VAL.access_endgame(endgame) ;
-                            }
-
-                            if let Some(color) = piece_color{
-                                match color{
-                                Color::White=>{
-                                    if (white_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(square))).popcnt() >= 1{
-
-                                        if (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(square))).popcnt() == 0{
-                                            cur_score += constants::KNIGHT_OUTPOST_TABLE_WHITE[square.to_index()];
-                                        }
-                                    }
-                                }
-                                Color::Black=>{
-                                    if (black_pawns & bitboard_operators::white_pawn_any_attacks(BitBoard::from_square(square))).popcnt() >= 1{
-                                        if (black_pawns & bitboard_operators::black_pawn_any_attacks(BitBoard::from_square(square))).popcnt() == 0{
-                                            cur_score += constants::KNIGHT_OUTPOST_TABLE_BLACK[square.to_index()];
-                                        }
-                                    }
-                                }
-                            }
-                            // add bonus for outpost
-                        }
-                        
-                        },
-                        Piece::Rook=>{
-                            cur_score += constants::ROOK_VAL.access_endgame(endgame);
-                            if color == Color::White{
-                                cur_score += constants::ROOK_SQUARES_TABLE[square.to_index()];
-                                // check if the rook is attacking the king
-                                if chess::get_rook_rays(square).bitand(black_king_zone).popcnt() > 0{
-                                    pieces_attacking_black_king += 1;
-                                    pieces_attacking_black_king_units += 2;
-                                }
-
-                                // get the squares that the rook is attacking
-                                let rook_squares = chess::get_rook_rays(square);
-                                let mobility = rook_squares.bitxor(board.combined()).bitand(rook_squares).popcnt();
-                                cur_score += (mobility as i32 - 7) * constants::ROOK_MOBILITY_VAL.access_endgame(endgame);
-                            }else{
-                                cur_score += constants::ROOK_SQUARES_TABLE[utils::mirror_square(&square).to_index()];
-                                // check if the rook is attacking the king
-                                if chess::get_rook_rays(square).bitand(white_king_zone).popcnt() > 0{
-                                    pieces_attacking_white_king += 1;
-                                    pieces_attacking_white_king_units += 2;
-                                }
-
-                                // get the squares that the rook is attacking
-                                let rook_squares = chess::get_rook_rays(square);
-                                let mobility = rook_squares.bitxor(board.combined()).bitand(rook_squares).popcnt();
-                                cur_score += (mobility as i32 - 7) * constants::ROOK_MOBILITY_VAL.access_endgame(endgame);
-                            }
-
-                        },
-                        Piece::Pawn=>{
-                            cur_score += constants::PAWN_VAL.access_endgame(endgame);
-                            
-                            if color == Color::White{
-                                cur_score += constants::PAWN_SQUARES_TABLE[square.to_index()];
-                            }else{
-                                cur_score += constants::PAWN_SQUARES_TABLE_BLACK[square.to_index()];
-                            }
-
-                            // calculate pawn storm and shelter
-                            if !endgame{
-                                if let Some(color) = piece_color {
-                                    let file = square.get_file();
-                                    let rank = square.get_rank();
-                                    match color{
-                                        Color::Black=>{
-                                            let king_file = white_king.get_file(); 
-                                            let own_king_file = black_king.get_file(); 
-                                            if file == king_file || file.left() == king_file || file.right() == king_file{
-                                                if rank == Rank::Fifth{
-                                                    total_white_score -= 10;
-                                                }else if rank == Rank::Fourth{
-                                                    total_white_score -= 30;
-                                                }else if rank == Rank::Third{
-                                                    total_white_score -= 60;
-                                                }
-                                            }
-
-                                            // calcualte pawn shelter for black king
-                                            if file == own_king_file || file.left() == own_king_file || file.right() == own_king_file{
-                                                if rank != Rank::Seventh{
-                                                    let score = rank.to_index() as i32 + 1;
-                                                    let black_shlter =  36 - score*score;
-                                                    total_black_score -= black_shlter;
-                                                }
-                                            }
-                                        }
-                                        Color::White=>{
-                                            let king_file = black_king.get_file(); 
-                                            let own_king_file = white_king.get_file(); 
-                                            if file == king_file || file.left() == king_file || file.right() == king_file{
-                                                if rank == Rank::Fourth{
-                                                    total_black_score -= 10;
-                                                }else if rank == Rank::Fifth{
-                                                    total_black_score -= 30;
-                                                }else if rank == Rank::Sixth{
-                                                    total_black_score -= 60;
-                                                }
-                                            }
-
-                                            // calcualte pawn shelter for white king
-                                            if file == own_king_file || file.left() == own_king_file || file.right() == own_king_file{
-                                                if rank != Rank::Second{
-                                                    let score = 8-(rank.to_index() as i32);
-                                                    let white_shelter = 36 - score*score;
-                                                    total_white_score -= white_shelter
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        
-                        },
-                        Piece::Queen=>{
-                            cur_score += constants::QUEEN_VAL.access_endgame(endgame);
-                            if color == Color::White{
-                                cur_score += constants::QUEEN_SQUARES_TABLE[square.to_index()];
-                                // check if the queen is attacking the black king
-                                
-                                if chess::get_rook_rays(square).bitor(chess::get_bishop_rays(square)).bitand(black_king_zone).popcnt() > 0{
-                                    // check if the queen is attacking the black king on the real board within 2 squares
-
-                                    pieces_attacking_black_king += 1;
-                                    pieces_attacking_black_king_units += 4;
-                                    
-                                }
-
-                                // add bonus for queen mobility
-                                // distance from king in ranks
-                                let king_rank = black_king.get_rank();
-                                let queen_rank = square.get_rank();
-                                let rank_diff = (king_rank.to_index() as i32 - queen_rank.to_index() as i32).abs();
-                                // distance from king in files
-                                let king_file = black_king.get_file();
-                                let queen_file = square.get_file();
-                                let file_diff = (king_file.to_index() as i32 - queen_file.to_index() as i32).abs();
-                                cur_score += 10 - rank_diff as i32 - file_diff as i32;
-
-                            }else{
-                                cur_score += constants::QUEEN_SQUARES_TABLE_BLACK[square.to_index()];
-                                if chess::get_rook_rays(square).bitor(chess::get_bishop_rays(square)).bitand(white_king_zone).popcnt() > 0{
-                                    // check if the queen is attacking the white king on the real board within 2 squares
-                                    
-                                    pieces_attacking_white_king += 1;
-                                    pieces_attacking_white_king_units += 4;
-                                    
-                                }
-                            
-                                // add bonus for queen mobility
-                                // distance from king in ranks
-                                let king_rank = white_king.get_rank();
-                                let queen_rank = square.get_rank();
-                                let rank_diff = (king_rank.to_index() as i32 - queen_rank.to_index() as i32).abs();
-                                // distance from king in files
-                                let king_file = white_king.get_file();
-                                let queen_file = square.get_file();
-                                let file_diff = (king_file.to_index() as i32 - queen_file.to_index() as i32).abs();
-                                cur_score += 10 - rank_diff as i32 - file_diff as i32;
-                            }
-                        },
-                    }
-                }
-                if let Some(color) = piece_color {
-                    match color{
-                        Color::Black=>{
-                            total_black_score += cur_score;
-                        }
-                        Color::White=>{
-                            total_white_score += cur_score;
-                        }
-                    }
-                }
+
+// adds every term of the evaluation (but the pawn structure) to the trace, with its opening and endgame values
+fn evaluate_terms(board: &chess::Board, trace: &mut EvalTrace, params: &EvalParams, pawns: &PawnEntry, material: &MaterialPst){
+    for color in [Color::White, Color::Black]{
+        trace.add(Term::Material, color, material.material[color.to_index()]);
+        trace.add(Term::Pst, color, material.pst[color.to_index()]);
+    }
+    let attacks = [AttackMaps::new(board, Color::White), AttackMaps::new(board, Color::Black)];
+    for color in [Color::White, Color::Black]{
+        evaluate_side(board, color, &attacks, trace, params, pawns);
+    }
+}
+
+
+// the danger our king is in from the attacks of the other side. the attacks on the ring around the king, the safe checks,
+// the weak squares of the ring and the open files next to the king add up to attack units that are turned into a penalty
+// by the danger table, a few units cost little but the penalty grows fast once many pieces join the attack
+fn king_safety(board: &chess::Board, us: Color, ours: &AttackMaps, theirs: &AttackMaps, params: &EvalParams) -> constants::Score{
+    let them = !us;
+    let king = board.king_square(us);
+    let ring = theirs.enemy_king_ring;
+    let occupied = *board.combined();
+
+    // a lone attacker is rarely dangerous, the ring attacks count more the more pieces take part
+    let mut ring_units = 0;
+    for piece in chess::ALL_PIECES{
+        ring_units += params.king_attack_weight[piece.to_index()] * theirs.king_ring_attacks[piece.to_index()] as i32;
+    }
+    let mut units = ring_units * params.king_attackers_percent[theirs.king_attackers.min(23)] / 100;
+
+    // squares they attack that only our king or queen defend
+    let weak = theirs.all & !ours.double & (!ours.all | ours.piece(Piece::King) | ours.piece(Piece::Queen));
+    units += params.king_weak_square * (weak & ring).popcnt() as i32;
+
+    // checks from squares we don't defend (or only defend weakly while they attack twice)
+    let safe = !*board.color_combined(them) & (!ours.all | (weak & theirs.double));
+    let bishop_lines = chess::get_bishop_moves(king, occupied);
+    let rook_lines = chess::get_rook_moves(king, occupied);
+    let checks = [
+        (Piece::Knight, chess::get_knight_moves(king)),
+        (Piece::Bishop, bishop_lines),
+        (Piece::Rook, rook_lines),
+        (Piece::Queen, bishop_lines | rook_lines)
+    ];
+    for (piece, squares) in checks{
+        if (squares & theirs.piece(piece) & safe).popcnt() > 0{
+            units += params.safe_check[piece.to_index()];
+        }
+    }
+
+    // files without our pawns next to the king are roads for their rooks and queens
+    let our_pawns = get_piece_type(board, Piece::Pawn, us);
+    let their_pawns = get_piece_type(board, Piece::Pawn, them);
+    let king_file = king.get_file().to_index();
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7){
+        let file = chess::get_file(chess::File::from_index(file));
+        if (file & our_pawns).popcnt() == 0{
+            if (file & their_pawns).popcnt() == 0{
+                units += params.king_open_file;
+            }else{
+                units += params.king_semi_open_file;
             }
         }
     }
 
-    // handle blocked bisops and rooks
-    let (white_blocked, black_blocked) = handle_blocked_pieces(board, black_king, white_king);
+    // without a queen the attack rarely gets through
+    if get_piece_type(board, Piece::Queen, them).popcnt() == 0{
+        units /= 2;
+    }
 
-    // give bonus to rook on open file
-    let white_rooks = get_piece_type(&board, chess::Piece::Rook, chess::Color::White);
-    let black_rooks = get_piece_type(&board, chess::Piece::Rook, chess::Color::Black);
-    
-    let white_rooks_on_open_file = white_rooks.bitand(open_files(white_pawns, black_pawns));
-    let white_rooks_on_open_file_bonus = constants::ROOK_ON_OPEN_FILE.access_endgame(endgame) as u32 * white_rooks_on_open_file.popcnt();
-    
-    let black_rooks_on_open_file = black_rooks.bitand(open_files(white_pawns, black_pawns));
-    let black_rooks_on_open_file_bonus = constants::ROOK_ON_OPEN_FILE.access_endgame(endgame) as u32 * black_rooks_on_open_file.popcnt();
+    let danger = params.king_danger[units.clamp(0, params.king_danger.len() as i32 - 1) as usize];
+    (-danger, -danger / 4)
+}
 
-    // if the rook is on open file and attacking the king, give bonus
-    let white_rooks_attacking_open_king_zone = file_fill(white_rooks_on_open_file) & black_king_zone;
-    let black_rooks_attacking_open_king_zone = file_fill(black_rooks_on_open_file) & white_king_zone;
 
-    // give bonus to rook on half-open file
-    let white_rooks_on_half_open_file = white_rooks.bitand(half_open_files(white_pawns));
-    let white_rooks_on_half_open_file_bonus = constants::ROOK_ON_HALF_OPEN_FILE.access_endgame(endgame) as u32 * white_rooks_on_half_open_file.popcnt();
-    let black_rooks_on_half_open_file = black_rooks.bitand(half_open_files(black_pawns));
-    let black_rooks_on_half_open_file_bonus = constants::ROOK_ON_HALF_OPEN_FILE.access_endgame(endgame) as u32 * black_rooks_on_half_open_file.popcnt();
+// the safe squares in the centre of our half: squares their pawns don't attack on the central files of our second to
+// fourth rank, the ones behind our pawns that they don't attack at all count twice. space is worth more with more
+// minor pieces that need room to move
+fn space(board: &chess::Board, us: Color, theirs: &AttackMaps, params: &EvalParams) -> constants::Score{
+    let our_pawns = get_piece_type(board, chess::Piece::Pawn, us);
+    let area = match us{
+        Color::White=>BitBoard::new(0x000000003C3C3C00),
+        Color::Black=>BitBoard::new(0x003C3C3C00000000)
+    };
+    let safe = area & our_pawns.not() & theirs.piece(Piece::Pawn).not();
+    let behind = bitboard_operators::rear_spans(our_pawns, us);
+    let bonus = safe.popcnt() + (safe & behind & theirs.all.not()).popcnt();
+    let minors = (board.pieces(Piece::Knight) | board.pieces(Piece::Bishop)) & board.color_combined(us);
+    (bonus as i32 * minors.popcnt() as i32 * params.space_weight / 16, 0)
+}
 
-    // if the rook is on half-open file and attacking the king, give bonus
-    let white_rooks_attacking_half_open_king_zone = file_fill(white_rooks_on_half_open_file) & black_king_zone;
-    let black_rooks_attacking_half_open_king_zone = file_fill(black_rooks_on_half_open_file) & white_king_zone;
 
+// the squares of the centre and around it that we attack, twice attacked squares of the centre count again
+fn centre_control(ours: &AttackMaps, params: &EvalParams) -> constants::Score{
+    let centre = BitBoard::new(0x0000001818000000);
+    let extended_centre = BitBoard::new(0x00003C3C3C3C0000) & centre.not();
+    let centre_count = ((ours.all & centre).popcnt() + (ours.double & centre).popcnt()) as i32;
+    let extended_count = (ours.all & extended_centre).popcnt() as i32;
+    (centre_count * params.centre_control.0 + extended_count * params.extended_centre_control.0,
+        centre_count * params.centre_control.1 + extended_count * params.extended_centre_control.1)
+}
 
-    // Give bonus to bishop pair
-    let white_bishops = get_piece_type(&board, chess::Piece::Bishop, chess::Color::White);
-    let black_bishops = get_piece_type(&board, chess::Piece::Bishop, chess::Color::Black);
-    let white_bishop_pair = constants::BISHOP_PAIR.access_endgame(endgame) as u32 * white_bishops.popcnt() / 2;
-    let black_bishop_pair = constants::BISHOP_PAIR.access_endgame(endgame) as u32 * black_bishops.popcnt() / 2;
 
-    total_black_score += black_rooks_on_open_file_bonus as i32 + black_rooks_on_half_open_file_bonus as i32 + black_bishop_pair as i32 + black_blocked as i32;
-    total_white_score += white_rooks_on_open_file_bonus as i32 + white_rooks_on_half_open_file_bonus as i32 + white_bishop_pair as i32 + white_blocked as i32;
-    
-    total_black_score += black_rooks_attacking_open_king_zone.popcnt() as i32 * constants::ROOK_ATTACKING_OPEN_KING_ZONE.access_endgame(endgame) as i32;
-    total_white_score += white_rooks_attacking_open_king_zone.popcnt() as i32 * constants::ROOK_ATTACKING_OPEN_KING_ZONE.access_endgame(endgame) as i32;
-    total_black_score += black_rooks_attacking_half_open_king_zone.popcnt() as i32 * constants::ROOK_ATTACKING_HALF_OPEN_KING_ZONE.access_endgame(endgame) as i32;
-    total_white_score += white_rooks_attacking_half_open_king_zone.popcnt() as i32 * constants::ROOK_ATTACKING_HALF_OPEN_KING_ZONE.access_endgame(endgame) as i32;
+// the bonus for the pieces of the other side that we threaten: pieces they don't defend, pieces attacked by a piece
+// worth less and pieces a safe pawn push would attack. the side to move can carry out one of several threats
+fn threats(board: &chess::Board, us: Color, ours: &AttackMaps, theirs: &AttackMaps, trace: &mut EvalTrace, params: &EvalParams){
+    let them = !us;
+    let mut threat_count = 0;
+
+    let minors = ours.piece(Piece::Knight) | ours.piece(Piece::Bishop);
+    for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]{
+        let targets = get_piece_type(board, piece, them);
+        let index = piece.to_index();
+
+        let hanging = (targets & ours.all & !theirs.all).popcnt() as i32;
+        let lesser_attackers = match piece{
+            Piece::Pawn=>chess::EMPTY,
+            Piece::Knight | Piece::Bishop=>ours.piece(Piece::Pawn),
+            Piece::Rook=>ours.piece(Piece::Pawn) | minors,
+            _=>ours.piece(Piece::Pawn) | minors | ours.piece(Piece::Rook)
+        };
+        let attacked_by_lesser = (targets & lesser_attackers).popcnt() as i32;
+
+        trace.add_scaled(Term::Threats, us, params.hanging_piece[index], hanging);
+        trace.add_scaled(Term::Threats, us, params.threat_by_lesser[index], attacked_by_lesser);
+        threat_count += hanging + attacked_by_lesser;
+    }
 
+    // a pawn push to a square they don't attack (or we defend) that attacks one of their pieces
+    let our_pawns = get_piece_type(board, Piece::Pawn, us);
+    let empty = !*board.combined();
+    let third_rank = chess::get_rank(match us{
+        Color::White=>chess::Rank::Third,
+        Color::Black=>chess::Rank::Sixth
+    });
+    let single_pushes = bitboard_operators::pawn_single_pushes(our_pawns, empty, us);
+    let pushes = single_pushes | bitboard_operators::pawn_single_pushes(single_pushes & third_rank, empty, us);
+    let safe_pushes = pushes & (!theirs.all | ours.all);
+    let their_pieces = *board.color_combined(them) & !get_piece_type(board, Piece::Pawn, them) & !BitBoard::from_square(board.king_square(them));
+    let push_threats = (bitboard_operators::pawn_any_attacks(safe_pushes, us) & their_pieces & !ours.piece(Piece::Pawn)).popcnt() as i32;
+    trace.add_scaled(Term::Threats, us, params.pawn_push_threat, push_threats);
+
+    if threat_count >= 2 && board.side_to_move() == us{
+        trace.add(Term::Threats, us, params.multiple_threats);
+    }
+}
 
-    // pieces attacking the other king 
-    total_black_score += (-20. * pieces_attacking_black_king_units as f32 * constants::PIECES_ATTACKING_KING[pieces_attacking_black_king]) as i32;
-    total_white_score += (-20. * pieces_attacking_white_king_units as f32 * constants::PIECES_ATTACKING_KING[pieces_attacking_white_king]) as i32;
 
+// the terms of one side. squares and ranks are taken relative to the side, so the tables are written from white's point of view
+fn evaluate_side(board: &chess::Board, us: Color, attacks: &[AttackMaps; 2], trace: &mut EvalTrace, params: &EvalParams, pawns: &PawnEntry){
+    let them = !us;
+    let our_king = board.king_square(us);
+    let their_king = board.king_square(them);
+    let their_king_zone = king_attacks(BitBoard::from_square(their_king));
+
+    let our_pawns = get_piece_type(board, chess::Piece::Pawn, us);
+    let their_pawns = get_piece_type(board, chess::Piece::Pawn, them);
+
+    // mobility counts the squares a piece attacks through the real occupancy, leaving out our own pawns and king
+    // and the squares the enemy pawns attack
+    let occupied = *board.combined();
+    let mobility_area = !(our_pawns | BitBoard::from_square(our_king) | bitboard_operators::pawn_any_attacks(their_pawns, them));
+
+    // an outpost is a square of the fourth to sixth rank defended by our pawns that no enemy pawn can ever attack
+    let is_outpost = |piece: BitBoard, relative: Square| {
+        let rank = relative.get_rank().to_index();
+        (3..=5).contains(&rank) && (piece & pawns.attacks[us.to_index()]).popcnt() > 0 && (piece & pawns.attack_spans[them.to_index()]).popcnt() == 0
+    };
+    let our_rooks = get_piece_type(board, chess::Piece::Rook, us);
+    // the rooks that got the trapped rook penalty, the blocked rook rule leaves them out
+    let mut trapped_rooks = BitBoard::new(0);
+    let centre = BitBoard::new(0x0000001818000000);
+    let central_files = BitBoard::new(0x3C3C3C3C3C3C3C3C);
+
+    for square in *board.color_combined(us){
+        let relative = utils::relative_square(square, us);
+        let piece = match board.piece_on(square){
+            Some(piece)=>piece,
+            None=>continue
+        };
+        match piece {
+            Piece::Bishop=>{
+                let mobility = (chess::get_bishop_moves(square, occupied) & mobility_area).popcnt();
+                trace.add(Term::BishopMobility, us, params.bishop_mobility[mobility as usize]);
+
+                // our pawns on the color of the bishop, more so when our central pawns are blocked
+                let own_color_pawns = (our_pawns & bitboard_operators::same_color_squares(square)).popcnt() as i32;
+                let central_pawns = our_pawns & central_files;
+                let blocked_central_pawns = central_pawns.popcnt() as i32 - bitboard_operators::pawn_single_pushes(central_pawns, occupied.not(), us).popcnt() as i32;
+                trace.add_scaled(Term::MinorPieces, us, params.bad_bishop, own_color_pawns * (1 + blocked_central_pawns));
+
+                let bishop = BitBoard::from_square(square);
+                if is_outpost(bishop, relative){
+                    trace.add(Term::MinorPieces, us, params.bishop_outpost);
+                }
 
-    // passed pawns endgame evaluation
-    let mut black_passed_pawns_value = 0;
-    let mut white_passed_pawns_value = 0;
-    if endgame {
-        let black_passed_pawns = black_passed_pawns(black_pawns, white_pawns);
-        let black_pieces = board.color_combined(chess::Color::Black);
-        let white_pieces = board.color_combined(chess::Color::White);
-        let black_has_no_pieces = ((black_pieces & black_pawns.not()) & BitBoard::from_square(black_king).not()).popcnt() == 0;
-        let white_has_no_pieces = ((white_pieces & white_pawns.not()) & BitBoard::from_square(white_king).not()).popcnt() == 0;
-        for black_pawn in black_passed_pawns{
-            let kings_distance = 20 * utils::distance(white_king, black_pawn) - 5 * utils::distance(black_king, black_pawn);
-            let promotion_square = chess::Square::make_square(chess::Rank::First ,black_pawn.get_file());
-            let mut unstoppable_pawn = 0;
-            if white_has_no_pieces{
-                // check if there is no friendly piece on the square infront of the pawn
-                if (black_pieces & BitBoard::from_maybe_square(black_pawn.forward(chess::Color::Black)).unwrap()).popcnt() == 0{
-                    if distance(black_pawn, promotion_square) < distance(white_king, promotion_square){
-                    
-                        unstoppable_pawn = constants::UNSTOPPABLE_PAWN.access_endgame(endgame);
-                    }
+                // a bishop that sees both central squares of its diagonal through the pawns
+                if (chess::get_bishop_moves(square, our_pawns | their_pawns) & centre).popcnt() >= 2{
+                    trace.add(Term::MinorPieces, us, params.long_diagonal_bishop);
                 }
-            }
-            black_passed_pawns_value += 20 + ((120 + kings_distance + unstoppable_pawn) as f32 * constants::PASSED_PAWNS_BLACK_OPENING[black_pawn.get_rank().to_index()]) as i32;
-        } 
-        total_black_score += black_passed_pawns_value;
-        let white_passed_pawns = white_passed_pawns(white_pawns, black_pawns);
-        
-        for white_pawn in white_passed_pawns{
-            let kings_distance = 20 * utils::distance(black_king, white_pawn) - 5 * utils::distance(white_king, white_pawn);
-            let promotion_square = chess::Square::make_square(chess::Rank::Eighth ,white_pawn.get_file());
-            let mut unstoppable_pawn = 0;
-            if black_has_no_pieces{
-                // check if there is no friendly piece on the square infront of the pawn
-                if (white_pieces & BitBoard::from_maybe_square(white_pawn.forward(chess::Color::White)).unwrap()).popcnt() == 0{
-                    if distance(white_pawn, promotion_square) < distance(black_king, promotion_square){
-                        unstoppable_pawn = constants::UNSTOPPABLE_PAWN.access_endgame(endgame);
+
+                // a fianchettoed bishop behind its pawn
+                for (bishop_square, pawn_square) in [(Square::B2, Square::B3), (Square::G2, Square::G3)]{
+                    if relative == bishop_square && (our_pawns & BitBoard::from_square(utils::relative_square(pawn_square, us))).popcnt() > 0{
+                        trace.add(Term::MinorPieces, us, params.fianchetto);
                     }
                 }
-            }   
 
-            white_passed_pawns_value += 20 + ((120 + kings_distance + unstoppable_pawn) as f32 * constants::PASSED_PAWNS_WHITE_OPENING[white_pawn.get_rank().to_index()]) as i32;
-        }
-        total_white_score += white_passed_pawns_value;
-    }
+                // a bishop that took the a7 or h7 pawn and is shut in by the pawn on b6 or g6
+                for (bishop_square, pawn_square) in [(Square::A7, Square::B6), (Square::H7, Square::G6)]{
+                    if relative == bishop_square && (their_pawns & BitBoard::from_square(utils::relative_square(pawn_square, us))).popcnt() > 0{
+                        trace.add(Term::MinorPieces, us, params.trapped_bishop);
+                    }
+                }
+            },
+            Piece::Knight=>{
+                // get the squares that the knight is attacking
+                let knight_attacks = chess::get_knight_moves(square);
+                let mobility = (knight_attacks & mobility_area).popcnt();
+                trace.add(Term::KnightMobility, us, params.knight_mobility[mobility as usize]);
+
+                // add bonus for outpost, a square defended by our pawns that no enemy pawn can ever attack
+                let knight = BitBoard::from_square(square);
+                if (knight & pawns.attacks[us.to_index()]).popcnt() >= 1 && (knight & pawns.attack_spans[them.to_index()]).popcnt() == 0{
+                    trace.add_both(Term::KnightOutpost, us, params.knight_outpost[relative.to_index()]);
+                }
 
+                // a knight far from both kings neither defends nor attacks
+                let king_distance = utils::distance(square, our_king).min(utils::distance(square, their_king));
+                trace.add_scaled(Term::MinorPieces, us, params.knight_king_distance, king_distance);
+            },
+            Piece::Rook=>{
+                let rook_moves = chess::get_rook_moves(square, occupied);
+                let mobility = (rook_moves & mobility_area).popcnt();
+                trace.add(Term::RookMobility, us, params.rook_mobility[mobility as usize]);
+
+                // a rook on the seventh rank cuts off the king on the eighth or attacks the pawns that didn't move
+                if relative.get_rank() == chess::Rank::Seventh{
+                    let their_seventh = chess::get_rank(utils::relative_square(Square::A7, us).get_rank());
+                    if utils::relative_square(their_king, us).get_rank() == chess::Rank::Eighth || (their_pawns & their_seventh).popcnt() > 0{
+                        trace.add(Term::HeavyPieces, us, params.rook_on_seventh);
+                    }
+                }
 
+                // another rook on the same file, and another rook this one defends. every pair is counted
+                // once, by the rook on the lower square
+                for other in our_rooks{
+                    if other <= square{
+                        continue;
+                    }
+                    if other.get_file() == square.get_file(){
+                        trace.add(Term::HeavyPieces, us, params.doubled_rooks);
+                    }
+                    if (BitBoard::from_square(other) & rook_moves).popcnt() > 0{
+                        trace.add(Term::HeavyPieces, us, params.connected_rooks);
+                    }
+                }
 
+                // a rook with little room on the side of the king that can't castle to free it anymore
+                if mobility <= 3 && relative.get_rank() == chess::Rank::First && utils::relative_square(our_king, us).get_rank() == chess::Rank::First{
+                    let rook_file = square.get_file();
+                    let king_file = our_king.get_file();
+                    let castle_rights = board.castle_rights(us);
+                    if (rook_file < king_file && king_file <= chess::File::D && !castle_rights.has_queenside())
+                        || (rook_file > king_file && king_file >= chess::File::E && !castle_rights.has_kingside()){
+                        trace.add(Term::HeavyPieces, us, params.trapped_rook);
+                        trapped_rooks |= BitBoard::from_square(square);
+                    }
+                }
+            },
+            // the king and the pawns are evaluated on their own
+            Piece::King | Piece::Pawn=>{},
+            Piece::Queen=>{
+                let mobility = ((chess::get_bishop_moves(square, occupied) | chess::get_rook_moves(square, occupied)) & mobility_area).popcnt();
+                trace.add(Term::QueenMobility, us, params.queen_mobility[mobility as usize]);
+
+                // a queen out before the minor pieces, for every minor piece still on its square
+                if relative != Square::D1{
+                    let mut undeveloped = 0;
+                    for (minor_square, minor) in [(Square::B1, Piece::Knight), (Square::C1, Piece::Bishop), (Square::F1, Piece::Bishop), (Square::G1, Piece::Knight)]{
+                        let minor_square = utils::relative_square(minor_square, us);
+                        if board.piece_on(minor_square) == Some(minor) && board.color_on(minor_square) == Some(us){
+                            undeveloped += 1;
+                        }
+                    }
+                    trace.add_scaled(Term::HeavyPieces, us, params.early_queen, undeveloped);
+                }
 
-    match board.side_to_move(){
-        chess::Color::Black=>return total_black_score - total_white_score,// + constants::TEMPO_BONUS.access_endgame(endgame),
-        chess::Color::White=>return total_white_score - total_black_score// + constants::TEMPO_BONUS.access_endgame(endgame),
+                // the safe squares from where their knights and bishops can attack the queen with their next move
+                let theirs = &attacks[them.to_index()];
+                let safe = (attacks[us.to_index()].all.not() | theirs.double) & board.color_combined(them).not();
+                let knight_threats = chess::get_knight_moves(square) & theirs.piece(Piece::Knight) & safe;
+                let bishop_threats = chess::get_bishop_moves(square, occupied) & theirs.piece(Piece::Bishop) & safe;
+                trace.add_scaled(Term::HeavyPieces, us, params.queen_minor_threat, (knight_threats | bishop_threats).popcnt() as i32);
+
+                // bonus for a queen close to the enemy king
+                // distance from king in ranks
+                let rank_diff = (their_king.get_rank().to_index() as i32 - square.get_rank().to_index() as i32).abs();
+                // distance from king in files
+                let file_diff = (their_king.get_file().to_index() as i32 - square.get_file().to_index() as i32).abs();
+                trace.add_both(Term::QueenTropism, us, params.queen_tropism - rank_diff - file_diff);
+            },
+        }
     }
-}
 
+    // the shelter of our pawns and the storm of their pawns around our king, they only count in the opening
+    let king_file = our_king.get_file().to_index();
+    trace.add(Term::PawnShelter, us, (pawns.shelter[us.to_index()][king_file], 0));
+    trace.add(Term::PawnStorm, us, (pawns.storm[us.to_index()][king_file], 0));
 
-fn handle_blocked_pieces(board: &chess::Board, black_king: Square, white_king: Square) -> (i32, i32){
-    // handle blocked bishops
-    let mut w_blocked = 0;
+    trace.add(Term::KingSafety, us, king_safety(board, us, &attacks[us.to_index()], &attacks[them.to_index()], params));
+    threats(board, us, &attacks[us.to_index()], &attacks[them.to_index()], trace, params);
+    trace.add(Term::Space, us, space(board, us, &attacks[them.to_index()], params));
+    trace.add(Term::CentreControl, us, centre_control(&attacks[us.to_index()], params));
 
-    w_blocked += utils::blocked_bishop(&board, chess::Square::C1, chess::Square::D2, true);
-    w_blocked += utils::blocked_bishop(&board, chess::Square::F1, chess::Square::E2, true);
+    // handle blocked bisops and rooks
+    trace.add_both(Term::BlockedPieces, us, handle_blocked_pieces(board, us, trapped_rooks, params));
 
-    let mut b_blocked = 0;
+    // give bonus to rook on open file
+    let our_semi_open_files = pawns.semi_open_files[us.to_index()];
+    let rooks_on_open_file = our_rooks.bitand(our_semi_open_files & pawns.semi_open_files[them.to_index()]);
+    trace.add_scaled(Term::RookFiles, us, params.rook_on_open_file, rooks_on_open_file.popcnt() as i32);
+
+    // if the rook is on open file and attacking the king, give bonus
+    let rooks_attacking_open_king_zone = file_fill(rooks_on_open_file) & their_king_zone;
+    trace.add_scaled(Term::RookFiles, us, params.rook_attacking_open_king_zone, rooks_attacking_open_king_zone.popcnt() as i32);
 
-    b_blocked += utils::blocked_bishop(&board, chess::Square::C8, chess::Square::D7, false);
-    b_blocked += utils::blocked_bishop(&board, chess::Square::F8, chess::Square::E7, false);
+    // give bonus to rook on half-open file
+    let rooks_on_half_open_file = our_rooks.bitand(our_semi_open_files);
+    trace.add_scaled(Term::RookFiles, us, params.rook_on_half_open_file, rooks_on_half_open_file.popcnt() as i32);
 
-    // handle blocked rooks
-    if white_king == Square::B1 || white_king == Square::C1{
-        if board.piece_on(Square::A1) == Some(Piece::Rook) || board.piece_on(Square::A2) == Some(Piece::Rook) || board.piece_on(Square::B1) == Some(Piece::Rook){
-            w_blocked += -50;
+    // if the rook is on half-open file and attacking the king, give bonus
+    let rooks_attacking_half_open_king_zone = file_fill(rooks_on_half_open_file) & their_king_zone;
+    trace.add_scaled(Term::RookFiles, us, params.rook_attacking_half_open_king_zone, rooks_attacking_half_open_king_zone.popcnt() as i32);
+
+    // Give bonus to bishop pair
+    let our_bishops = get_piece_type(board, chess::Piece::Bishop, us);
+    trace.add_scaled(Term::BishopPair, us, params.bishop_pair, our_bishops.popcnt() as i32 / 2);
+
+    // passed pawns in every phase. the bonus for the rank is raised or lowered by what helps or stops the pawn
+    // on its way, scaled by how far the pawn is
+    let our_pieces = board.color_combined(us);
+    let their_pieces = board.color_combined(them);
+    let they_have_no_pieces = ((their_pieces & their_pawns.not()) & BitBoard::from_square(their_king).not()).popcnt() == 0;
+    let rooks = *board.pieces(Piece::Rook);
+    // with a lone pawn against the bare king the bitbase has the exact result
+    let kpk_win = bitbase::probe_kpk(board);
+
+    for pawn in pawns.passed[us.to_index()]{
+        let rank = utils::relative_square(pawn, us).get_rank().to_index();
+        let percent = params.passed_pawn_percent[rank];
+        let scaled = |value: constants::Score, count: i32| (value.0 * count * percent / 100, value.1 * count * percent / 100);
+        trace.add(Term::PassedPawns, us, params.passed_pawn[rank]);
+
+        // the kings close to the pawn, most of all in the endgame
+        trace.add(Term::PassedPawns, us, scaled(params.passed_their_king_distance, utils::distance(their_king, pawn)));
+        trace.add(Term::PassedPawns, us, scaled(params.passed_our_king_distance, utils::distance(our_king, pawn)));
+
+        // the squares to the promotion square, free of pieces and not attacked by the other side
+        let path = bitboard_operators::front_spans(BitBoard::from_square(pawn), us);
+        if (path & occupied).popcnt() == 0{
+            trace.add(Term::PassedPawns, us, scaled(params.passed_free_path, 1));
         }
-    }
-    if white_king == Square::F1 || white_king == Square::G1{
-        if board.piece_on(Square::H1) == Some(Piece::Rook) || board.piece_on(Square::H2) == Some(Piece::Rook) || board.piece_on(Square::G1) == Some(Piece::Rook){
-            w_blocked += -50;
+        if (path & attacks[them.to_index()].all).popcnt() == 0{
+            trace.add(Term::PassedPawns, us, scaled(params.passed_safe_path, 1));
         }
-    }
 
-    if black_king == Square::B8 || black_king == Square::C8{
-        if board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::A8) == Some(Piece::Rook) || board.piece_on(Square::B8) == Some(Piece::Rook){
-            b_blocked += -50;
+        // a piece of theirs right in front of the pawn
+        if let Some(stop) = pawn.forward(us){
+            if board.color_on(stop) == Some(them){
+                trace.add(Term::PassedPawns, us, scaled(params.passed_blockaded, 1));
+            }
         }
-    }
-    if black_king == Square::F8 || black_king == Square::G8{
-        if board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::H8) == Some(Piece::Rook) || board.piece_on(Square::G8) == Some(Piece::Rook){
-            b_blocked += -50;
+
+        // a rook behind the passed pawn, ours pushes it and theirs holds it back
+        let behind = bitboard_operators::rear_spans(BitBoard::from_square(pawn), us) & rooks;
+        for rook in behind{
+            if (chess::get_rook_moves(rook, occupied) & BitBoard::from_square(pawn)).popcnt() > 0{
+                let sign = if board.color_on(rook) == Some(us) { 1 } else { -1 };
+                trace.add(Term::PassedPawns, us, scaled(params.rook_behind_passer, sign));
+            }
+        }
+
+        // the rule of the square: without pieces to stop it the enemy king has to catch the pawn, a pawn on its
+        // starting rank can move two squares and the king gets a tempo when it is their move
+        if they_have_no_pieces && (path & our_pieces).popcnt() == 0{
+            let promotion_square = utils::relative_square(chess::Square::make_square(chess::Rank::Eighth, pawn.get_file()), us);
+            let pawn_distance = (7 - rank as i32).min(5);
+            let tempo = (board.side_to_move() == them) as i32;
+            if kpk_win.unwrap_or(pawn_distance < distance(their_king, promotion_square) - tempo){
+                trace.add(Term::PassedPawns, us, scaled(params.unstoppable_pawn, 1));
+            }
         }
     }
-    return (w_blocked, b_blocked)
 }
 
 
-pub fn evaluate_pawn_structure(black_pawns: BitBoard, white_pawns: BitBoard, endgame: bool) -> i32{
-    let mut score = 0;
-    // evaluate pawns
-    // evaluate double pawns
-    let black_doubled_pawns = black_pawns_behind_own(black_pawns).popcnt() as i32 * constants::DOUBLED_PAWNS_DEBUFF.access_endgame(endgame);
-    let white_doubled_pawns = white_pawns_behind_own(white_pawns).popcnt() as i32 * constants::DOUBLED_PAWNS_DEBUFF.access_endgame(endgame);
+// bishops blocked by their own pawns and rooks locked in by the uncastled king, but for the rooks that are already
+// penalized as trapped
+fn handle_blocked_pieces(board: &chess::Board, us: Color, trapped_rooks: BitBoard, params: &EvalParams) -> i32{
+    let relative = |square: Square| utils::relative_square(square, us);
+    let our_king = board.king_square(us);
+    let our_rooks = get_piece_type(board, chess::Piece::Rook, us) & !trapped_rooks;
+    let rook_on = |squares: [Square; 3]| squares.iter().any(|square| (our_rooks & BitBoard::from_square(relative(*square))).popcnt() > 0);
 
-    // evaluate isolated pawns
-    let black_isolated_pawns = bitboard_operators::isolanis(black_pawns).popcnt() as i32 * constants::ISOLATED_PAWNS_DEBUFF.access_endgame(endgame);
-    let white_isolated_pawns = bitboard_operators::isolanis(white_pawns).popcnt() as i32 * constants::ISOLATED_PAWNS_DEBUFF.access_endgame(endgame);
+    // handle blocked bishops
+    let mut blocked = 0;
+    for (bishop, pawn) in [(Square::C1, Square::D2), (Square::F1, Square::E2)]{
+        if utils::blocked_bishop(board, relative(bishop), relative(pawn), us == Color::White) != 0{
+            blocked += params.blocked_bishop;
+        }
+    }
 
-    // evaluate backward pawns
-    let black_backward_pawns = bitboard_operators::white_backward(white_pawns, black_pawns).popcnt() as i32 * constants::BACKWARD_PAWNS_DEBUFF.access_endgame(endgame);
-    let white_backward_pawns = bitboard_operators::white_backward(white_pawns, black_pawns).popcnt() as i32 * constants::BACKWARD_PAWNS_DEBUFF.access_endgame(endgame);
+    // handle blocked rooks
+    if (our_king == relative(Square::B1) || our_king == relative(Square::C1)) && rook_on([Square::A1, Square::A2, Square::B1]){
+        blocked += params.blocked_rook;
+    }
+    if (our_king == relative(Square::F1) || our_king == relative(Square::G1)) && rook_on([Square::H1, Square::H2, Square::G1]){
+        blocked += params.blocked_rook;
+    }
+    blocked
+}
 
-    // TODO: evaluate candidate passed pawns
-    // let black_candidate_passed_pawns = bitboard_operators::candidate_passed_pawns(black_pawns, white_pawns).popcnt() as i32 * constants::CANDIDATE_PASSED_PAWNS_DEBUFF.access_endgame(endgame);
-    // let white_candidate_passed_pawns = bitboard_operators::candidate_passed_pawns(white_pawns, black_pawns).popcnt() as i32 * constants::CANDIDATE_PASSED_PAWNS_DEBUFF.access_endgame(endgame);
 
+// the pawn structure score of white and of black
+pub fn pawn_structure_sides(black_pawns: BitBoard, white_pawns: BitBoard, params: &EvalParams) -> (constants::Score, constants::Score){
+    (pawn_structure_side(white_pawns, black_pawns, Color::White, params), pawn_structure_side(black_pawns, white_pawns, Color::Black, params))
+}
 
-    // evaluate passed pawns
-    let mut white_passed_pawns_value = 0;
-    let mut black_passed_pawns_value = 0;
-    if endgame{
-        // ? if endgame is true, then we have to evaluate passed pawns differently with respect to the kings
 
+fn pawn_structure_side(our_pawns: BitBoard, their_pawns: BitBoard, us: Color, params: &EvalParams) -> constants::Score{
+    // evaluate double pawns
+    let doubled_pawns = bitboard_operators::pawns_behind_own(our_pawns, us).popcnt() as i32;
 
-    }else{
-        let black_passed_pawns = black_passed_pawns(black_pawns, white_pawns);
-        
-        for black_pawn in black_passed_pawns{
-            black_passed_pawns_value += (10. + 60.* constants::PASSED_PAWNS_BLACK_OPENING[black_pawn.get_rank().to_index()]) as i32;
-        }
+    // evaluate isolated pawns
+    let isolated_pawns = bitboard_operators::isolanis(our_pawns).popcnt() as i32;
 
-        let white_passed_pawns = white_passed_pawns(white_pawns, black_pawns);
-        
-        for white_pawn in white_passed_pawns{
-            white_passed_pawns_value += (10. + 60.* constants::PASSED_PAWNS_WHITE_OPENING[white_pawn.get_rank().to_index()]) as i32;
-        }
+    // evaluate backward pawns
+    let backward_pawns = bitboard_operators::backward(our_pawns, their_pawns, us).popcnt() as i32;
+
+    // passed pawns are evaluated with the pieces, in evaluate_side
+    let mut score = (doubled_pawns * params.doubled_pawns.0 + isolated_pawns * params.isolated_pawns.0 + backward_pawns * params.backward_pawns.0,
+        doubled_pawns * params.doubled_pawns.1 + isolated_pawns * params.isolated_pawns.1 + backward_pawns * params.backward_pawns.1);
+    let mut add = |value: constants::Score, count: i32| {
+        score.0 += value.0 * count;
+        score.1 += value.1 * count;
+    };
+    let rank = |pawn: Square| utils::relative_square(pawn, us).get_rank().to_index();
+
+    // pawns that defend each other or stand next to each other, worth more the further they are
+    for pawn in bitboard_operators::supported_pawns(our_pawns, us){
+        add(params.supported_pawn[rank(pawn)], 1);
+    }
+    for pawn in bitboard_operators::phalanx(our_pawns){
+        add(params.phalanx_pawn[rank(pawn)], 1);
     }
-    score += white_doubled_pawns - black_doubled_pawns + white_passed_pawns_value - black_passed_pawns_value + white_isolated_pawns - black_isolated_pawns + white_backward_pawns - black_backward_pawns;
-    score
-}
 
-pub fn material_balance(board: &chess::Board) -> i32{
-    let mut score = 0;
-    let mut white_material = 0;
-    let mut black_material = 0;
-    let squares = board.combined();
-    for square in *squares{
-        match board.color_on(square){
-            Some(chess::Color::White) => {
-                match board.piece_on(square){
-                    Some(chess::Piece::Pawn) => {
-                        white_material += constants::PAWN_VAL.0;
-                    },
-                    Some(chess::Piece::Knight) => {
-                        white_material += constants::KNIGHT_VAL.0;
-                    },
-                    Some(chess::Piece::Bishop) => {
-                        white_material += constants::BISHOP_VAL.0;
-                    },
-                    Some(chess::Piece::Rook) => {
-                        white_material += constants::ROOK_VAL.0;
-                    },
-                    Some(chess::Piece::Queen) => {
-                        white_material += constants::QUEEN_VAL.0;
-                    },
-                    Some(chess::Piece::King) => {
-                        
-                    },
-                    _ => {
-                        panic!("Invalid piece on square");
-                    }
-                }
-            },
-            Some(chess::Color::Black) => {
-                match board.piece_on(square){
-                    Some(chess::Piece::Pawn) => {
-                        black_material += constants::PAWN_VAL.0;
-                    },
-                    Some(chess::Piece::Knight) => {
-                        black_material += constants::KNIGHT_VAL.0;
-                    },
-                    Some(chess::Piece::Bishop) => {
-                        black_material += constants::BISHOP_VAL.0;
-                    },
-                    Some(chess::Piece::Rook) => {
-                        black_material += constants::ROOK_VAL.0;
-                    },
-                    Some(chess::Piece::Queen) => {
-                        black_material += constants::QUEEN_VAL.0;
-                    },
-                    Some(chess::Piece::King) => {
-                       
-                    },
-                    _ => {
-                        panic!("Invalid piece on square");
-                    }
-                }
-            },
-            _ => {
-                panic!("Invalid color on square");
-            }
-        }
+    // pawns that can become passed by pushing them with the help of their neighbours
+    for pawn in bitboard_operators::candidate_passed_pawns(our_pawns, their_pawns, us){
+        add(params.candidate_passed_pawn[rank(pawn)], 1);
     }
-        match board.side_to_move(){
-            chess::Color::White => {
-                score += white_material - black_material;
-            },
-            chess::Color::Black => {
-                score -= white_material - black_material;
-            }
-        }
+
+    // tension with the enemy pawns, pawns stuck behind an enemy pawn, squares of our camp no pawn of ours
+    // can ever defend and the groups of pawns that can't protect each other
+    add(params.pawn_lever, bitboard_operators::pawn_levers(our_pawns, their_pawns, us).popcnt() as i32);
+    add(params.blocked_pawn, bitboard_operators::blocked_pawns(our_pawns, their_pawns, us).popcnt() as i32);
+    add(params.pawn_hole, bitboard_operators::pawn_holes(our_pawns, us).popcnt() as i32);
+    add(params.pawn_island, bitboard_operators::pawn_islands(our_pawns) as i32);
     score
 }
 
 
-
-// fn get_smallest_attacker(board: &chess::Board, square: Square, side: chess::Color) -> chess::Piece{
-//     let mut smallest_attacker = chess::Piece::Pawn;
-//     let mut smallest_attacker_value = constants::PAWN_VAL.0;
-//     for attacker in board.attackers_to(square, side){
-//         let attacker_value = constants::PIECE_VAL[attacker.to_index()];
-//         if attacker_value < smallest_attacker_value{
-//             smallest_attacker = attacker;
-//             smallest_attacker_value = attacker_value;
-//         }
-//     }
-//     smallest_attacker
-// }
-
-// // static exchange evaluation
-// pub fn static_exchange(board: &chess::Board, square: Square, side: chess::Color) -> i32{
-//     let mut value = 0;
-//     let piece = get_smallest_attacker(board, square);
-//     if let Some(piece) = piece{
-//         let passed_board = board.make_move_new(chess::ChessMove::new(square, square, piece, None));
-//         value = cmp::max(value, passed_board.static_exchange(square, side));
-
-// get piece value
-// fn get_piece_value(piece: chess::Piece) -> i32{
-//     match piece{
-//         chess::Piece::Pawn => {
-//             constants::PAWN_VAL.0
-//         },
-//         chess::Piece::Knight => {
-//             constants::KNIGHT_VAL.0
-//         },
-//         chess::Piece::Bishop => {
-//             constants::BISHOP_VAL.0
-//         },
-//         chess::Piece::Rook => {
-//             constants::ROOK_VAL.0
-//         },
-//         chess::Piece::Queen => {
-//             constants::QUEEN_VAL.0
-//         },
-//         chess::Piece::King => {
-//             999
-//         },
-//         _ => {
-//             panic!("Invalid piece");
-//         }
-//     }
-// }
-
-
-
-
-// fn see ( board: &chess::Board, toSq: Square,  target: Piece, frSq: Square,  aPiece: Piece) -> i32{
-// {
-//     let gain: [i32; 32] = [0; 32];
-//     let mut d = 0;
-//     let mayXray: BitBoard = board.pieces(Piece::Pawn) | board.pieces(Piece::Bishop) | board.pieces(Piece::Rook) | board.pieces(Piece::Queen);
-//     let fromSet = BitBoard::from_square(frSq);
-//     let mut occ     = board.combined();
-//     let mut attadef = attacksTo( occ, toSq );
-//     gain[d]     = get_piece_value(target);
-//     loop {
-//         d += 1; // next depth and side
-//         gain[d]  = value[aPiece] - gain[d-1]; // speculative store, if defended
-//         if std::cmp::max(-gain[d-1], gain[d]) < 0{ 
-//             break;
-//         } // pruning does not influence the result
-//         attadef ^= fromSet; // reset bit in set to traverse
-//         *occ     ^= fromSet; // reset bit in temporary occupancy (for x-Rays)
-//         if ( fromSet & mayXray )
-//             attadef |= considerXrays(occ, ..);
-//         fromSet  = getLeastValuablePiece (attadef, d & 1, aPiece);
-//         if (fromSet.0 == 0){ break;}
-//     } 
-//     while (--d){
-//         gain[d-1]= -max (-gain[d-1], gain[d])
-//     }
-//     return gain[0];
-// }
\ No newline at end of file
+// the material that is left on the board, from PHASE_MAX with all the pieces to 0 with only pawns and kings
+pub fn game_phase(board: &chess::Board) -> i32{
+    let phase = board.pieces(Piece::Knight).popcnt() as i32 * constants::KNIGHT_PHASE
+        + board.pieces(Piece::Bishop).popcnt() as i32 * constants::BISHOP_PHASE
+        + board.pieces(Piece::Rook).popcnt() as i32 * constants::ROOK_PHASE
+        + board.pieces(Piece::Queen).popcnt() as i32 * constants::QUEEN_PHASE;
+    (phase.min(constants::TOTAL_PHASE) * constants::PHASE_MAX + constants::TOTAL_PHASE / 2) / constants::TOTAL_PHASE
+}
@@ -0,0 +1,153 @@
+use chess::{Board, ChessMove, Color, Piece, Square};
+
+// history values are kept in [-HISTORY_MAX, HISTORY_MAX] by the gravity update
+pub const HISTORY_MAX: i32 = 16384;
+const MAX_BONUS: i32 = 1200;
+
+const PIECE_TYPES: usize = 12;
+const SQUARES: usize = 64;
+
+// the piece (with its color) that made the move and the square it moved to
+pub type PieceTo = (usize, Square);
+
+pub struct History{
+    // quiet moves history indexed by side to move, from square and to square
+    quiet: Vec<i32>,
+    // continuation history indexed by an earlier move's piece and destination and the current move's piece and destination,
+    // one table for the move one ply back and one for the move two plies back
+    continuation: [Vec<i16>; 2],
+    // capture history indexed by the moving piece, the destination and the captured piece type
+    capture: Vec<i32>,
+    // the moves that led to the current node, None for a null move
+    stack: Vec<Option<PieceTo>>
+}
+
+pub fn piece_index(piece: Piece, color: Color) -> usize{
+    piece.to_index() + 6 * color.to_index()
+}
+
+pub fn piece_to(board: &Board, chess_move: ChessMove) -> Option<PieceTo>{
+    let piece = board.piece_on(chess_move.get_source())?;
+    let color = board.color_on(chess_move.get_source())?;
+    Some((piece_index(piece, color), chess_move.get_dest()))
+}
+
+// moves the entry toward the bonus, the closer it gets to HISTORY_MAX the smaller the change
+fn gravity(entry: i32, bonus: i32) -> i32{
+    entry + bonus - entry * bonus.abs() / HISTORY_MAX
+}
+
+pub fn bonus(depth: u32) -> i32{
+    (depth as i32 * depth as i32 * 16).min(MAX_BONUS)
+}
+
+impl Default for History{
+    fn default() -> History{
+        History::new()
+    }
+}
+
+impl History{
+    pub fn new() -> History{
+        History{
+            quiet: vec![0; 2 * SQUARES * SQUARES],
+            continuation: [vec![0; PIECE_TYPES * SQUARES * PIECE_TYPES * SQUARES], vec![0; PIECE_TYPES * SQUARES * PIECE_TYPES * SQUARES]],
+            capture: vec![0; PIECE_TYPES * SQUARES * 6],
+            stack: Vec::new()
+        }
+    }
+
+    pub fn push(&mut self, previous: Option<PieceTo>){
+        self.stack.push(previous);
+    }
+
+    pub fn pop(&mut self){
+        self.stack.pop();
+    }
+
+    // the move made `plies` plies before the current node
+    fn previous(&self, plies: usize) -> Option<PieceTo>{
+        if self.stack.len() < plies{
+            return None;
+        }
+        self.stack[self.stack.len() - plies]
+    }
+
+    fn quiet_index(color: Color, chess_move: ChessMove) -> usize{
+        (color.to_index() * SQUARES + chess_move.get_source().to_index()) * SQUARES + chess_move.get_dest().to_index()
+    }
+
+    fn continuation_index(previous: PieceTo, current: PieceTo) -> usize{
+        ((previous.0 * SQUARES + previous.1.to_index()) * PIECE_TYPES + current.0) * SQUARES + current.1.to_index()
+    }
+
+    fn capture_index(current: PieceTo, captured: Piece) -> usize{
+        (current.0 * SQUARES + current.1.to_index()) * 6 + captured.to_index()
+    }
+
+    pub fn continuation_score(&self, current: PieceTo) -> i32{
+        let mut score = 0;
+        for plies in [1, 2]{
+            if let Some(previous) = self.previous(plies){
+                score += self.continuation[plies - 1][History::continuation_index(previous, current)] as i32;
+            }
+        }
+        score
+    }
+
+    // the score used to order quiet moves and to decide how much to reduce them
+    pub fn quiet_score(&self, board: &Board, chess_move: ChessMove) -> i32{
+        let mut score = self.quiet[History::quiet_index(board.side_to_move(), chess_move)];
+        if let Some(current) = piece_to(board, chess_move){
+            score += self.continuation_score(current);
+        }
+        score
+    }
+
+    pub fn capture_score(&self, board: &Board, chess_move: ChessMove) -> i32{
+        match (piece_to(board, chess_move), board.piece_on(chess_move.get_dest())){
+            (Some(current), Some(captured)) => self.capture[History::capture_index(current, captured)],
+            // en passant
+            (Some(current), None) => self.capture[History::capture_index(current, Piece::Pawn)],
+            _ => 0
+        }
+    }
+
+    pub fn update_quiet(&mut self, board: &Board, chess_move: ChessMove, bonus: i32){
+        let index = History::quiet_index(board.side_to_move(), chess_move);
+        self.quiet[index] = gravity(self.quiet[index], bonus);
+
+        if let Some(current) = piece_to(board, chess_move){
+            for plies in [1, 2]{
+                if let Some(previous) = self.previous(plies){
+                    let index = History::continuation_index(previous, current);
+                    self.continuation[plies - 1][index] = gravity(self.continuation[plies - 1][index] as i32, bonus) as i16;
+                }
+            }
+        }
+    }
+
+    pub fn update_capture(&mut self, board: &Board, chess_move: ChessMove, bonus: i32){
+        if let Some(current) = piece_to(board, chess_move){
+            let captured = board.piece_on(chess_move.get_dest()).unwrap_or(Piece::Pawn);
+            let index = History::capture_index(current, captured);
+            self.capture[index] = gravity(self.capture[index], bonus);
+        }
+    }
+
+    // reward the move that caused the cutoff and punish the moves of the same kind that were tried before it
+    pub fn update_cutoff(&mut self, board: &Board, best: ChessMove, best_is_capture: bool, tried_quiets: &[ChessMove], tried_captures: &[ChessMove], depth: u32){
+        let bonus = bonus(depth);
+        if best_is_capture{
+            self.update_capture(board, best, bonus);
+        }else{
+            self.update_quiet(board, best, bonus);
+            for chess_move in tried_quiets{
+                self.update_quiet(board, *chess_move, -bonus);
+            }
+        }
+        for chess_move in tried_captures{
+            self.update_capture(board, *chess_move, -bonus);
+        }
+    }
+}
@@ -1,29 +1,47 @@
+// the code returns its results with return, like the rest of the engine
+#![allow(clippy::needless_return)]
 pub mod constants;
 pub mod evaluation;
 pub mod utils;
 pub mod opening;
 pub mod bitboard_operators;
 pub mod search;
-use std::io::{Write, Read};
+pub mod history;
+pub mod trace;
+pub mod draw;
+pub mod eval_trace;
+pub mod params;
+pub mod attacks;
+pub mod endgame;
+pub mod nnue;
+pub mod tune;
+pub mod pawns;
+pub mod material;
+pub mod eval_cache;
+pub mod syzygy;
+pub mod bitbase;
+#[cfg(test)]
+pub mod tbgen;
+use std::io::Write;
 
 use std::{io::{self}};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc};
+use std::thread;
 use chess::{self, ChessMove, Board, Game};
 
 use rand::Rng;
-use std::{time::Instant, str::FromStr};
+use std::{time::{Duration, Instant}, str::FromStr};
 
-use crate::{search::SearchInfo, search::Entry};
+use crate::{search::SearchCounts, search::SearchLimits, search::SearchState, draw::DrawTracker, params::EvalParams};
 
 
-fn iterative_deepening(board: &Board, remaining_time: u128, depth: u32) -> (Option<chess::ChessMove>, SearchInfo){
+fn iterative_deepening(board: &Board, remaining_time: u128, depth: u32, stop: &Arc<AtomicBool>, draws: &DrawTracker, state: &mut SearchState) -> (Option<chess::ChessMove>, SearchCounts){
     let mut table: Option<Vec<(ChessMove, i32)>> = None;
     let mut best_move:Option<chess::ChessMove> = None;
     let mut best_score = -9999; 
     let allowed_time =  remaining_time / 30; 
     let mut total_time = 0; 
-    let mut info: SearchInfo = SearchInfo::new();
-    let mut cachetable = chess::CacheTable::new(65536,  Entry{depth: 0, node_type: search::Nodetype::Pvnode, score: 0});
-    let mut pawn_table = chess::CacheTable::new(65536,  0);
+    let mut info = SearchCounts::default();
 
     let mut _depth = 1;
 
@@ -32,15 +50,37 @@ fn iterative_deepening(board: &Board, remaining_time: u128, depth: u32) -> (Opti
         use_depth = false;
     }
 
-    while ((use_depth && _depth <= depth) || (!use_depth && total_time < allowed_time)) && _depth < 100{
+    // the time per move is soft and checked between root moves, the deadline is hard and polled inside the search
+    let mut deadline = None;
+    if !use_depth{
+        deadline = Instant::now().checked_add(Duration::from_millis((allowed_time * 2).min(u64::MAX as u128) as u64));
+    }
+    // draws are scored from the point of view of the side the engine is playing
+    let mut draws = draws.clone();
+    draws.engine_color = board.side_to_move();
+    state.start(SearchLimits::new(deadline, stop.clone()), draws);
+
+    // a won tablebase position is converted by the move with the shortest distance to zero, without a search
+    if let Some(tablebases) = state.info.tablebases.clone(){
+        if let Some((chess_move, dtz)) = tablebases.root_move(board, state.info.draws.halfmove_clock()){
+            info.tb_hits += chess::MoveGen::new_legal(board).len() as u64;
+            println!("info depth 1 score {} tbhits {} pv {}", search::uci_score(constants::TB_WIN_SCORE - dtz), info.tb_hits, chess_move);
+            return (Some(chess_move), info);
+        }
+    }
+
+    // the first iteration always starts so even a search without time left has a move to play
+    while (_depth == 1 || (use_depth && _depth <= depth) || (!use_depth && total_time < allowed_time)) && _depth < 100{
         let now = Instant::now();
         let time_left = allowed_time - total_time.min(allowed_time);
         
-        let result = search::search_depth(&board, _depth, &table, time_left, (best_move, best_score), &mut cachetable, &mut pawn_table);
+        let result = search::search_depth(board, _depth, &table, time_left, (best_move, best_score), state);
         if result.0.is_none() || result.4{
+            info.nodes_searched += result.3.nodes_searched;
             println!("time took {}", total_time);
             println!("evaluated {} positions. {} transpostions recorded and {} used", info.nodes_searched, info.transpostions_recorded, info.transpostions_used);
             println!("recorded {} pawn stractures. {} used", info.pawn_hash_table_recorded, info.pawn_hash_table_used);
+            println!("recorded {} evaluations. {} used", info.eval_hash_table_recorded, info.eval_hash_table_used);
             if result.0.is_none(){
                 println!("used last depth");
                 return (best_move, info);
@@ -50,16 +90,12 @@ fn iterative_deepening(board: &Board, remaining_time: u128, depth: u32) -> (Opti
         }
         best_move = result.0;
         best_score = result.1;
-        info.nodes_searched += result.3.nodes_searched;
-        info.transpostions_used += result.3.transpostions_used;
-        info.transpostions_recorded += result.3.transpostions_recorded;
-        info.pawn_hash_table_used += result.3.pawn_hash_table_used;
-        info.pawn_hash_table_recorded += result.3.pawn_hash_table_recorded;
+        info.add(&result.3);
 
-        io::stdout().write((format!("info nodes {}\n", info.nodes_searched)).as_bytes()).ok();
+        io::stdout().write_all((format!("info nodes {} tbhits {}\n", info.nodes_searched, info.tb_hits)).as_bytes()).ok();
       
 
-        if best_score >= 9999{
+        if best_score >= constants::MATE_BOUND{
             return (best_move, info);
         }
         table = Some(result.2);  
@@ -71,20 +107,59 @@ fn iterative_deepening(board: &Board, remaining_time: u128, depth: u32) -> (Opti
     println!("time took {}", total_time);
     println!("evaluated {} positions. {} transpostions recorded and {} used", info.nodes_searched, info.transpostions_recorded, info.transpostions_used);
     println!("recorded {} pawn stractures. {} used", info.pawn_hash_table_recorded, info.pawn_hash_table_used);
+    println!("recorded {} evaluations. {} used", info.eval_hash_table_recorded, info.eval_hash_table_used);
     return (best_move, info);
 }
 
 
-fn choose_move(board: chess::Board, depth: u32, remaining_time: u128) -> (Option<chess::ChessMove>, SearchInfo){
+// the outcome of a mate search, a mate for the side to move or against it in that many moves
+#[derive(Debug, PartialEq)]
+enum MateFound{
+    Mate(u32),
+    Mated(u32)
+}
+
+
+// searches one ply deeper every iteration until a mate in at most `mate_in` moves is proven (or the search is stopped).
+// the pruning that could hide a defence is turned off, so a reported mate is forced
+fn search_mate(board: &Board, mate_in: u32, stop: &Arc<AtomicBool>, draws: &DrawTracker, state: &mut SearchState) -> (Option<ChessMove>, Option<MateFound>, SearchCounts){
+    let mut table: Option<Vec<(ChessMove, i32)>> = None;
+    let mut best_move: Option<ChessMove> = None;
+    let mut best_score = -constants::MATE_SCORE;
+    let mut info = SearchCounts::default();
+    let mut draws = draws.clone();
+    draws.engine_color = board.side_to_move();
+    state.start(SearchLimits::mate(mate_in, stop.clone()), draws);
+
+    // the root move is searched on top of the depth, a mate in n takes 2n - 1 plies
+    for depth in 0..(2 * mate_in).max(1) - 1{
+        let result = search::search_depth(board, depth, &table, u128::MAX, (best_move, best_score), state);
+        info.nodes_searched += result.3.nodes_searched;
+        if result.4{
+            break;
+        }
+        best_move = result.0;
+        best_score = result.1;
+        table = Some(result.2);
+        // a negative distance is a mate against the side to move, it is not what was asked for but it is just as proven
+        match search::mate_in(best_score){
+            Some(moves) if moves > 0 && moves <= mate_in as i32=>return (best_move, Some(MateFound::Mate(moves as u32)), info),
+            Some(moves) if moves < 0 && -moves <= mate_in as i32=>return (best_move, Some(MateFound::Mated(-moves as u32)), info),
+            _=>{}
+        }
+    }
+    (best_move, None, info)
+}
+
+
+fn choose_move(board: chess::Board, depth: u32, remaining_time: u128, stop: &Arc<AtomicBool>, draws: &DrawTracker, state: &mut SearchState) -> (Option<chess::ChessMove>, SearchCounts){
     let count = chess::MoveGen::new_legal(&board);
     let moves: Vec<ChessMove> = count.collect();
     if moves.len() == 1{
-        for chess_move in moves{
-            return (Some(chess_move), SearchInfo::new());
-        }
+        return (Some(moves[0]), SearchCounts::default());
     }
 
-    let (best_move, info) = iterative_deepening(&board, remaining_time, depth);
+    let (best_move, info) = iterative_deepening(&board, remaining_time, depth, stop, draws, state);
     
     if let Some(chess_move) = best_move {
         
@@ -99,14 +174,14 @@ fn play_random_move(board: chess::Board) -> Option<chess::ChessMove> {
     let movegen = chess::MoveGen::new_legal(&board);
 
     let moves: Vec<ChessMove> = movegen.collect();
-    if moves.len() == 0{
+    if moves.is_empty(){
         return None
     }
     Some(moves[rand::thread_rng().gen_range(0..moves.len())])
 }
 
 
-fn play_bot_move( board: chess::Board, depth: u32, book_moves: u32, remaining_time: u128) -> ChessMove{
+fn play_bot_move( board: chess::Board, depth: u32, book_moves: u32, remaining_time: u128, stop: &Arc<AtomicBool>, draws: &DrawTracker, state: &mut SearchState) -> ChessMove{
     if book_moves > 0{
         let file = std::fs::File::open("C:\\Users\\משתמש\\Documents\\projects\\RustChess\\target\\release\\book.bin").unwrap(); 
         let book = opening::read_polyglot_book(file).unwrap();
@@ -121,15 +196,16 @@ fn play_bot_move( board: chess::Board, depth: u32, book_moves: u32, remaining_ti
                 // choose a random move from the book
                 let mut rng = rand::thread_rng();
                 let index = rng.gen_range(0..moves.len());
-                let move_ = moves[index];
-                println!("book move found {:#?}", move_);
+                let entry = moves[index];
+                println!("book move found {:#?}", entry);
+                let move_ = entry.move_;
                 let mut s = String::new();
-                s.push(opening::FILE_NAMES[move_.move_.end_file() as usize]);
-                s.push(opening::RANK_NAMES[move_.move_.end_row() as usize]);
-                s.push(opening::FILE_NAMES[move_.move_.start_file() as usize]);
-                s.push(opening::RANK_NAMES[move_.move_.start_row() as usize]);
+                s.push(opening::FILE_NAMES[move_.end_file() as usize]);
+                s.push(opening::RANK_NAMES[move_.end_row() as usize]);
+                s.push(opening::FILE_NAMES[move_.start_file() as usize]);
+                s.push(opening::RANK_NAMES[move_.start_row() as usize]);
                 // println!("{}", s);
-                match move_.move_.promotion_piece() {
+                match move_.promotion_piece() {
                     Some(opening::PromotionPiece::Knight) => s.push('n'),
                     Some(opening::PromotionPiece::Bishop) => s.push('b'),
                     Some(opening::PromotionPiece::Rook) => s.push('r'),
@@ -144,7 +220,7 @@ fn play_bot_move( board: chess::Board, depth: u32, book_moves: u32, remaining_ti
         }
     }
     
-    match choose_move(board, depth, remaining_time).0{
+    match choose_move(board, depth, remaining_time, stop, draws, state).0{
         None=>{return play_random_move(board).expect("error_board has no moves")},
         Some(chess_move)=>{
             return chess_move;
@@ -153,184 +229,172 @@ fn play_bot_move( board: chess::Board, depth: u32, book_moves: u32, remaining_ti
 }
 
 
-fn play_game(starting_position: Option<String>, verbose: bool, bot_white: bool, depth: u32, terminate_after: Option<u32>) -> i32{
-    
-    let both = true;
-    let mut game = chess::Game::new();
-    let mut book_moves = 6;
-    match starting_position{
-        None=>{},
-        Some(pos)=>{
-            match chess::Game::from_str(&pos).ok(){
-                None=>{},
-                Some(g)=>{
-                    game = g
-                }
+// reads the commands on a separate thread so "stop" can reach the search while it is running.
+// the stop flag is raised and cleared here, in the order the commands arrived.
+fn spawn_input_reader(stop: Arc<AtomicBool>) -> mpsc::Receiver<String>{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        loop{
+            let mut buffer = String::new();
+            io::stdin().read_line(&mut buffer).expect("Did not enter a correct string");
+            if let Some('\n')=buffer.chars().next_back() {
+                buffer.pop();
             }
-        }   
-    }
-    let mut move_count = 0;
-    while game.result().is_none()
-    {
-        move_count += 1;
-        let board = game.current_position();
-        let now = Instant::now();
-        match board.side_to_move(){
-            chess::Color::White=>{
-                if verbose{
-                    println!("White");
-                }
-                if bot_white{
-                    game.make_move(play_bot_move(board, depth, book_moves, 4*60*1000));
-                }else{
-                    if both{
-                        game.make_move(play_bot_move(board, depth, book_moves, 4*60*1000));
-                    }else{
-                        game.make_move(play_random_move(board).expect("error_board has no moves"));
-                    }
-
-                }
+            if let Some('\r')=buffer.chars().next_back() {
+                buffer.pop();
             }
-            chess::Color::Black=>{
-                if verbose{
-                    println!("Black")
-                }
-                if !bot_white{
-                    game.make_move(play_bot_move(board, depth, book_moves, 4*60*1000));
-                }else{
-                    if both{
-                        game.make_move(play_bot_move(board, depth, book_moves, 4*60*1000));
-                    } else{
-                        game.make_move(play_random_move(board).expect("error_board has no moves"));
-                    }
-
-
-                }
+            if buffer.starts_with("go"){
+                stop.store(false, Ordering::Relaxed);
+            }else if buffer == "stop" || buffer == "quit"{
+                stop.store(true, Ordering::Relaxed);
             }
-        }
-        if book_moves > 0 {
-            book_moves -= 1;
-        }    
-        if verbose { 
-            // print!("\x1B[2J\x1B[1;1H");
-            let elapsed = now.elapsed();
-            
-            println!("time per move: {:.2?}",  elapsed);
-            println!("{}",&game.current_position());
-            utils::print_board(game.current_position().to_string());
-        }
-
-        if let Some(a) = terminate_after{
-            if a < move_count{
+            let eof = buffer.is_empty();
+            if sender.send(buffer).is_err() || eof{
                 break;
             }
         }
-        
-    }
-    if verbose{
-        match game.result(){
-            None=>{},
-            Some(result)=>{
-                match result{
-                    chess::GameResult::BlackCheckmates=>{
-                        println!("black won!")
-                    },
-                    chess::GameResult::WhiteCheckmates=>{
-                        println!("white won!")
-                    },
-                    chess::GameResult::WhiteResigns=>{
-                        println!("black won!")
-                    },
-                    chess::GameResult::BlackResigns=>{
-                        println!("white won!")
-                    },
-                    chess::GameResult::Stalemate=>{
-                        println!("stalemate!")
-                    },
-                    chess::GameResult::DrawAccepted=>{
-                        println!("draw accepted!")
-                    },
-                    chess::GameResult::DrawDeclared=>{
-                        println!("draw declared!")
-                    }
-                }
-            }
-            
-        }
-    }
-
-    let mut count = 0;
-    for action in game.actions(){
-        
-        match action{
-            chess::Action::MakeMove(m)=>{
-                if verbose{
-                print!("{} ", m);
-                }
-                count += 1
-            },
-            chess::Action::AcceptDraw=>{},
-            chess::Action::DeclareDraw=>{},
-            chess::Action::OfferDraw(_)=>{},
-            chess::Action::Resign(_)=>{},
-        }
-
-    }
-    return count;
-}
-
-
-fn test_match(){
-    let now = Instant::now();
-    // 6k1/1p3pp1/p7/8/r2n4/8/3K4/7q b - - 0 1
-    // 4rrk1/1pp2ppp/p7/3n4/8/P7/1P3PPP/R1BR2K1 w - - 0 1 =====> test not getting to mate!!!
-    // test hard end game -> 8/p3k3/Pp4p1/1P4P1/4K3/8/8/8 w - - 0 1
-    // medium endgame Some("8/p3k2p/Pp4p1/1Pn3P1/3RK3/8/8/8 w - - 0 1".to_string())
-    let turns = play_game(None, true, false, 0, Some(200));
-    let elapsed = now.elapsed();
-            
-    println!("avarage time per move: {:.2?}",  elapsed / (turns) as u32);
+    });
+    receiver
 }
 
 
 fn handle_uci(){
     let mut game = Game::new(); 
+    let mut draws = DrawTracker::new(&game.current_position(), 0);
+    let mut contempt: i32 = 0;
     let mut book_moves = 10;
-    let mut buffer=String::new();
+    let stop = Arc::new(AtomicBool::new(false));
+    let input = spawn_input_reader(stop.clone());
+    // the tables and the history are kept from one search to the next during a game
+    let mut state = SearchState::new();
+    // the king and pawn against king bitbase is built before the first search needs it
+    bitbase::init();
     let _=io::stdout().flush();
-    io::stdin().read_line(&mut buffer).expect("Did not enter a correct string");
-    if let Some('\n')=buffer.chars().next_back() {
-        buffer.pop();
-    }
-    if let Some('\r')=buffer.chars().next_back() {
-        buffer.pop();
-    }
-    while buffer != "" {
+    let mut buffer = input.recv().unwrap_or_default();
+    while !buffer.is_empty() {
         
         if buffer == "uci"{
             // print!("heyyyy");
-            io::stdout().write((format!("id name {} \n", constants::NAME)).as_bytes()).ok();
-            io::stdout().write((format!("id auther {} \n", constants::NAME)).as_bytes()).ok();
-            io::stdout().write(("uciok\n").as_bytes()).ok();
+            io::stdout().write_all((format!("id name {} \n", constants::NAME)).as_bytes()).ok();
+            io::stdout().write_all((format!("id auther {} \n", constants::NAME)).as_bytes()).ok();
+            io::stdout().write_all(("option name Contempt type spin default 0 min -200 max 200\n").as_bytes()).ok();
+            io::stdout().write_all(("option name EvalFile type string default <empty>\n").as_bytes()).ok();
+            io::stdout().write_all(("option name EvalNetwork type string default <empty>\n").as_bytes()).ok();
+            println!("option name SyzygyPath type string default <empty>");
+            io::stdout().write_all(("uciok\n").as_bytes()).ok();
 
             
         }else if buffer == "quit"{
-            io::stdout().write(("Bye Bye!\n").as_bytes()).ok();
+            io::stdout().write_all(("Bye Bye!\n").as_bytes()).ok();
             
         }else if buffer == "isready"{
-            io::stdout().write(("readyok\n").as_bytes()).ok();
+            io::stdout().write_all(("readyok\n").as_bytes()).ok();
         }else if buffer == "ucinewgame"{
             game = Game::new();
+            draws = DrawTracker::new(&game.current_position(), 0);
             book_moves = 10;
+            state = SearchState::new();
+        }else if buffer.starts_with("setoption "){
+            // setoption name <name> value <value>
+            let parts: Vec<&str> = buffer.split(" ").collect();
+            if parts.len() == 5 && parts[1] == "name" && parts[3] == "value" && parts[2].eq_ignore_ascii_case("contempt"){
+                match i32::from_str(parts[4]){
+                    Ok(value)=>contempt = value.clamp(-200, 200),
+                    Err(_)=>println!("invalid contempt {}", parts[4])
+                }
+            }else if parts.len() >= 5 && parts[1] == "name" && parts[3] == "value" && parts[2].eq_ignore_ascii_case("evalfile"){
+                // the path can contain spaces, an empty path goes back to the compiled in parameters
+                let path = parts[4..].join(" ");
+                if path.is_empty() || path == "<empty>"{
+                    params::set_current(EvalParams::default());
+                }else{
+                    match EvalParams::read(&path){
+                        Ok(loaded)=>{
+                            params::set_current(loaded);
+                            println!("info string evaluation parameters loaded from {}", path);
+                        },
+                        Err(e)=>println!("info string failed to load evaluation parameters from {}: {}", path, e)
+                    }
+                }
+            }else if parts.len() >= 5 && parts[1] == "name" && parts[3] == "value" && parts[2].eq_ignore_ascii_case("evalnetwork"){
+                // searches use the network instead of the handcrafted evaluation, an empty path turns it off
+                let path = parts[4..].join(" ");
+                if path.is_empty() || path == "<empty>"{
+                    nnue::set_current(None);
+                }else{
+                    match nnue::Network::load(&path){
+                        Ok(network)=>{
+                            nnue::set_current(Some(network));
+                            println!("info string network loaded from {}", path);
+                        },
+                        Err(e)=>println!("info string failed to load network from {}: {}", path, e)
+                    }
+                }
+            }else if parts.len() >= 5 && parts[1] == "name" && parts[3] == "value" && parts[2].eq_ignore_ascii_case("syzygypath"){
+                // one or more directories of tablebase files, an empty path turns the tablebases off
+                let path = parts[4..].join(" ");
+                if path.is_empty() || path == "<empty>"{
+                    syzygy::set_current(None);
+                }else{
+                    match syzygy::Tablebases::load(&path){
+                        Ok(tablebases)=>{
+                            println!("info string found {} tablebases with up to {} pieces in {}", tablebases.count(), tablebases.max_pieces(), path);
+                            syzygy::set_current(Some(tablebases));
+                        },
+                        Err(e)=>println!("info string failed to load tablebases from {}: {}", path, e)
+                    }
+                }
+            }
+            // the scores in the tables were made with the old options
+            state = SearchState::new();
+        }else if buffer == "eval"{
+            // the static evaluation of the current position, term by term
+            print!("{}", evaluation::trace_with_params(&game.current_position(), &params::current()).table());
+            let board = game.current_position();
+            if let Some(score) = endgame::evaluate(&board){
+                println!("known endgame    {}", score);
+            }
+            for color in [chess::Color::White, chess::Color::Black]{
+                let scale = endgame::scale_factor(&board, color);
+                if scale != endgame::SCALE_NORMAL{
+                    println!("scale for {:?}  {}/{}", color, scale, endgame::SCALE_NORMAL);
+                }
+            }
+            if let Some(network) = nnue::current(){
+                println!("network          {}", nnue::NnueStack::new(network).evaluate(&board));
+            }
+        }else if buffer == "dumpparams" || buffer.starts_with("dumpparams "){
+            // dumpparams [file]
+            dump_params(buffer.strip_prefix("dumpparams ").map(|x| x.trim()));
+        }else if buffer.starts_with("trace "){
+            // trace <depth> <file>
+            let parts: Vec<&str> = buffer.split(" ").collect();
+            if parts.len() == 3{
+                match u32::from_str(parts[1]){
+                    Ok(depth)=>trace_search(&game.current_position(), depth, parts[2]),
+                    Err(_)=>println!("usage: trace <depth> <file>")
+                }
+            }else{
+                println!("usage: trace <depth> <file>");
+            }
+        }else if buffer.starts_with("replay "){
+            // replay <file> [moves...]
+            let parts: Vec<&str> = buffer.split(" ").collect();
+            if let Err(e) = trace::replay(parts[1], &parts[2..], &mut io::stdout()){
+                println!("failed to replay {}: {}", parts[1], e);
+            }
         }
         if buffer.starts_with("position "){
             let parts: Vec<&str> = buffer.split(" ").collect(); 
             if parts[1] == "startpos"{
                 game = Game::new();
+                draws = DrawTracker::new(&game.current_position(), 0);
                 book_moves = 10;
                 for chess_move in &parts[2..]{
                     if chess_move != &"moves"{
-                    game.make_move(ChessMove::from_str(chess_move).ok().expect("illigal move"));
+                    let chess_move = ChessMove::from_str(chess_move).expect("illigal move");
+                    draws.push(&game.current_position(), chess_move, &game.current_position().make_move_new(chess_move));
+                    game.make_move(chess_move);
                     if book_moves > 0{ book_moves -= 1}
                     }
                 }
@@ -341,18 +405,21 @@ fn handle_uci(){
                 while parts.len() > idx && parts[idx] != "moves"{
                     if parts[idx] != "fen"{
                         if idx!=2{
-                            fen.push_str(" ");
+                            fen.push(' ');
                         }
                         fen.push_str(parts[idx]);
                     }
                     idx += 1;
                 }
                 
-                game = Game::new_with_board(Board::from_str(&fen).expect(&format!("failed to load pos {}", fen)[..]));
+                game = Game::new_with_board(Board::from_str(&fen).unwrap_or_else(|_| panic!("failed to load pos {}", fen)));
+                draws = DrawTracker::new(&game.current_position(), draw::halfmove_clock_from_fen(&fen));
                 
                 for chess_move in &parts[idx..]{
                     if chess_move != &"moves"{
-                    game.make_move(ChessMove::from_str(chess_move).ok().expect("illigal move"));
+                    let chess_move = ChessMove::from_str(chess_move).expect("illigal move");
+                    draws.push(&game.current_position(), chess_move, &game.current_position().make_move_new(chess_move));
+                    game.make_move(chess_move);
                     }
                 }
             }
@@ -364,6 +431,7 @@ fn handle_uci(){
             let mut wtime: u128 = 100000000000;
             let mut btime: u128 = 100000000000; 
             let mut max_depth: u32 = 0;
+            let mut mate: Option<u32> = None;
             while tokens.len() > idx{
                 current_token = tokens[idx];
                 if current_token == "wtime"{
@@ -375,69 +443,123 @@ fn handle_uci(){
                 }else if current_token == "depth"{
                     idx += 1;
                     max_depth = FromStr::from_str(tokens[idx]).unwrap();
+                }else if current_token == "mate"{
+                    idx += 1;
+                    mate = FromStr::from_str(tokens[idx]).ok();
                 }
                 idx += 1;
             }
             
 
             let board = game.current_position();
-            
-            let remaining_time:u128;
-            match board.side_to_move(){
-                chess::Color::White=>{
-                    remaining_time = wtime;
-                },
-                chess::Color::Black=>{
-                    remaining_time = btime;
+
+            if let Some(mate_in) = mate{
+                draws.contempt = contempt;
+                let (best_move, found, info) = search_mate(&board, mate_in, &stop, &draws, &mut state);
+                match found{
+                    Some(MateFound::Mate(moves))=>println!("info score mate {} nodes {}", moves, info.nodes_searched),
+                    Some(MateFound::Mated(moves))=>{
+                        println!("info score mate -{} nodes {}", moves, info.nodes_searched);
+                        println!("info string the side to move is mated in {}", moves);
+                    },
+                    None=>println!("info string no mate in {} found, {} nodes", mate_in, info.nodes_searched)
+                }
+                match best_move.or_else(|| chess::MoveGen::new_legal(&board).next()){
+                    Some(chess_move)=>println!("bestmove {}", chess_move),
+                    None=>println!("bestmove 0000")
                 }
+                let _=io::stdout().flush();
+                buffer = input.recv().unwrap_or_default();
+                continue;
             }
             
-            let chess_move = play_bot_move(board , max_depth, 10, remaining_time);
-            io::stdout().write(format!("bestmove {}\n", chess_move).as_bytes()).ok();
+            let remaining_time = match board.side_to_move(){
+                chess::Color::White=>wtime,
+                chess::Color::Black=>btime
+            };
+            
+            draws.contempt = contempt;
+            let chess_move = play_bot_move(board , max_depth, 10, remaining_time, &stop, &draws, &mut state);
+            io::stdout().write_all(format!("bestmove {}\n", chess_move).as_bytes()).ok();
+            draws.push(&board, chess_move, &board.make_move_new(chess_move));
             game.make_move(chess_move);
             if book_moves> 0 {book_moves -= 1;}
         }
-        buffer.clear();
         let _=io::stdout().flush();
-        io::stdin().read_line(&mut buffer).expect("Did not enter a correct string");
-        if let Some('\n')=buffer.chars().next_back() {
-            buffer.pop();
-        }
-        if let Some('\r')=buffer.chars().next_back() {
-            buffer.pop();
-        }
+        buffer = input.recv().unwrap_or_default();
     }
 }
 
 
-fn run_test_position(position: &str, remaining_time: u128){
-    let now = Instant::now();
-    let test = Board::from_str(position).ok().expect("invalid position");
-    let chess_move = choose_move(test, 10, remaining_time);
-    let elapsed = now.elapsed();
-    println!("{}, {:?}", chess_move.0.expect("msg"), chess_move.1);
-    println!("time to complete {:?}", elapsed);
+// prints the current evaluation parameters or writes them to a file, in the format --evalfile reads
+fn dump_params(path: Option<&str>){
+    let current = params::current();
+    match path{
+        None=>print!("{}", current.dump()),
+        Some(path)=>match current.write(path){
+            Ok(_)=>println!("evaluation parameters written to {}", path),
+            Err(e)=>println!("failed to write evaluation parameters to {}: {}", path, e)
+        }
+    }
 }
 
 
-
-fn check_eval(position: &str, pawn_table: &mut chess::CacheTable<i32>){
-    let board = Board::from_str(position).ok().expect("msg"); 
-    let now = Instant::now();
-    let eval = evaluation::evaluate(&board);
-    let elapsed = now.elapsed();
-    println!("{} - {:?}", eval, elapsed);
-    
-    let mut info = search::SearchInfo::new();
-    let now = Instant::now();
-    
-    let eval = evaluation::evaluate_rework(&board) + search::pawn_table_lookup(&board, pawn_table, &mut info);
-    let elapsed = now.elapsed();
-    println!("{} - {:?}", eval, elapsed);
+// searches the position to the given depth (without iterative deepening) and writes the searched tree to a file
+fn trace_search(board: &Board, depth: u32, path: &str){
+    let mut state = SearchState::new();
+    state.start(SearchLimits::none(), DrawTracker::new(board, 0));
+    state.info.tracer = Some(trace::Tracer::new(board, depth));
+    let result = search::search_depth(board, depth, &None, u128::MAX, (None, -9999), &mut state);
+    if let Some(mut tracer) = state.info.tracer{
+        tracer.finish(result.1);
+        match tracer.write(path){
+            Ok(_)=>println!("trace of {} nodes written to {}", tracer.nodes().len(), path),
+            Err(e)=>println!("failed to write trace to {}: {}", path, e)
+        }
+    }
 }
 
+
 fn main() {
     let debug = false;
+    let mut args: Vec<String> = std::env::args().collect();
+    // rust_chess --evalfile <file> ... starts with the evaluation parameters of the file
+    if let Some(index) = args.iter().position(|x| x == "--evalfile"){
+        if index + 1 >= args.len(){
+            println!("usage: --evalfile <file>");
+            return;
+        }
+        let path = args.remove(index + 1);
+        args.remove(index);
+        match EvalParams::read(&path){
+            Ok(loaded)=>params::set_current(loaded),
+            Err(e)=>{
+                println!("failed to load evaluation parameters from {}: {}", path, e);
+                return;
+            }
+        }
+    }
+    // rust_chess dumpparams [file]
+    if args.len() >= 2 && args[1] == "dumpparams"{
+        dump_params(args.get(2).map(|x| x.as_str()));
+        return;
+    }
+    // rust_chess replay <file> [moves...]
+    if args.len() >= 3 && args[1] == "replay"{
+        let move_path: Vec<&str> = args[3..].iter().map(|x| x.as_str()).collect();
+        if let Err(e) = trace::replay(&args[2], &move_path, &mut io::stdout()){
+            println!("failed to replay {}: {}", args[2], e);
+        }
+        return;
+    }
+    // rust_chess [--evalfile <file>] tune <dataset> <output> [passes]
+    if args.len() >= 4 && args[1] == "tune"{
+        let passes = args.get(4).and_then(|x| x.parse().ok()).unwrap_or(100);
+        if let Err(e) = tune::run(&args[2], &args[3], passes){
+            println!("failed to tune on {}: {}", args[2], e);
+        }
+        return;
+    }
     // //let board = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").ok().expect("invalid position");
     // //println!("{:x}",board.get_hash());
     // //let board = Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").ok().expect("invalid position");
@@ -507,33 +629,6 @@ fn main() {
 
 
 
-        // let mut s=String::new();
-        // while s != "exit" {
-        //     print!("Please enter some text: ");
-        //     let _=stdout().flush();
-        //     stdin().read_line(&mut s).expect("Did not enter a correct string");
-        //     if let Some('\n')=s.chars().next_back() {
-        //         s.pop();
-        //     }
-        //     if let Some('\r')=s.chars().next_back() {
-        //         s.pop();
-        //     }
-        //     run_test_position(s.as_str(), 10*60*1000)
-        // };
-        
-        // let mut pawn_table = chess::CacheTable::new(65536,  0);
-        // check_eval("rnbqkb1r/ppp1pppp/5n2/3p4/3P4/2N5/PPP1PPPP/R1BQKBNR w KQkq - 1 3", &mut pawn_table);
-        // check_eval("rnbqkbnr/ppp1pppp/8/3p4/3P4/2N5/PPP1PPPP/R1BQKBNR b KQkq - 0 2", &mut pawn_table);
-        // check_eval("rnbqkb1r/ppp1pppp/5n2/3p4/3P4/2N5/PPP1PPPP/R1BQKBNR w KQkq - 1 3", &mut pawn_table);
-        // check_eval("rnbqkbnr/ppp1pppp/8/3p4/3P4/2N5/PPP1PPPP/R1BQKBNR b KQkq - 0 2", &mut pawn_table);
-        // check_eval("rnbqkb1r/ppp1pppp/5n2/3p4/3P4/2N5/PPP1PPPP/R1BQKBNR w KQkq - 1 3", &mut pawn_table);
-
-
-        // run_test_position("rnbqkb1r/p3pppp/1p6/2ppP3/3N4/2P5/PPP1QPPP/R1B1KB1R w KQkq -", 10*60*1000);
-        // run_test_position("8/8/7p/3KNN1k/2p4p/8/3P2p1/8 w - -", 10*60*1000);
-        // run_test_position("6k1/1p3ppp/4b3/2p4q/3p4/P2PB1QP/2P3PK/8 b - - 0 26", 10*60*1000);
-        
-        // test_match()
     }else{
         handle_uci();
     }
@@ -543,6 +638,7 @@ fn main() {
 #[cfg(test)]
 mod test{
     use super::*;
+    use crate::{pawns::PawnEntry, search::SearchInfo};
     
     struct TestPositon{
         pos: String,
@@ -552,12 +648,16 @@ mod test{
     
     fn run_mate(test_pos: TestPositon){
         let now = Instant::now();
-
-            let count = play_game(Some(test_pos.pos.to_string()), false, test_pos.mating_side_white, 9, Some(10));
+            let board = Board::from_str(&test_pos.pos).expect("invalid position");
+            let (_, found, _) = search_mate(&board, test_pos.mate_in, &Arc::new(AtomicBool::new(false)), &DrawTracker::new(&board, 0), &mut SearchState::new());
             let elapsed = now.elapsed();
-            let turns = (count as f32/2 as f32).ceil();
-            println!("mate found in {} move, should be in {}, Elapsed time: {:.2?}", turns, test_pos.mate_in, elapsed);
-            assert_eq!(turns, test_pos.mate_in as f32);
+            // a mate against the side to move is reported on its own
+            let mut expected = MateFound::Mate(test_pos.mate_in);
+            if test_pos.mating_side_white != (board.side_to_move() == chess::Color::White){
+                expected = MateFound::Mated(test_pos.mate_in);
+            }
+            println!("mate found {:?}, should be {:?}, Elapsed time: {:.2?}", found, expected, elapsed);
+            assert_eq!(found, Some(expected));
             
 
     }
@@ -569,6 +669,51 @@ mod test{
         run_mate(TestPositon{ pos: "8/4K3/2b5/3kp3/8/8/1n6/b4r2 w - - 1 10".to_string(),mate_in: 2, mating_side_white: false})
     }
 
+    #[test]
+    fn test_mated_side_to_move(){
+        // white to move gets mated in 2, that is neither a mate in 1 for white nor one for the side to move at all
+        let board = Board::from_str("8/4K3/2b5/3kp3/8/8/1n6/b4r2 w - - 1 10").unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (_, found, _) = search_mate(&board, 1, &stop, &DrawTracker::new(&board, 1), &mut SearchState::new());
+        assert_eq!(found, None);
+        let (_, found, _) = search_mate(&board, 3, &stop, &DrawTracker::new(&board, 1), &mut SearchState::new());
+        assert_eq!(found, Some(MateFound::Mated(2)));
+    }
+
+    #[test]
+    fn test_mate_on_the_fiftieth_move(){
+        // the mate comes with the hundredth half move, it ends the game before the fifty-move rule can
+        let board = Board::from_str("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80").unwrap();
+        let (best_move, found, _) = search_mate(&board, 1, &Arc::new(AtomicBool::new(false)), &DrawTracker::new(&board, 99), &mut SearchState::new());
+        assert_eq!(found, Some(MateFound::Mate(1)));
+        assert_eq!(best_move, Some(ChessMove::from_str("a1a8").unwrap()));
+    }
+
+    #[test]
+    fn test_stopped_search(){
+        // stopped before the first root move is searched, the move ordered first comes back instead of none
+        let board = Board::default();
+        let draws = DrawTracker::new(&board, 0);
+        let mut state = SearchState::new();
+        state.start(SearchLimits::new(None, Arc::new(AtomicBool::new(true))), draws.clone());
+        let result = search::search_depth(&board, 6, &None, u128::MAX, (None, 0), &mut state);
+        assert!(result.4);
+        assert_eq!(result.0, chess::MoveGen::new_legal(&board).next());
+        // without time left the first iteration still runs. how far it gets depends on the clock, so only the move is checked
+        let stop = Arc::new(AtomicBool::new(false));
+        let (best_move, _) = iterative_deepening(&board, 1, 0, &stop, &draws, &mut SearchState::new());
+        assert!(best_move.is_some_and(|x| board.legal(x)));
+        let (best_move, _) = iterative_deepening(&board, 60000, 0, &Arc::new(AtomicBool::new(true)), &draws, &mut state);
+        assert!(best_move.is_some_and(|x| board.legal(x)));
+
+        // the next search with the same tables starts over with its own limits
+        let (best_move, counts) = iterative_deepening(&board, 0, 3, &stop, &draws, &mut state);
+        assert!(best_move.is_some_and(|x| board.legal(x)));
+        assert!(counts.nodes_searched > 0);
+        assert!(!state.info.stopped);
+        assert_eq!((state.info.ply, state.info.pawn_keys.len(), state.info.materials.len()), (0, 0, 0));
+    }
+
     #[test]
     fn test_mate_black_3(){
         run_mate(TestPositon{pos: "6k1/1p3pp1/p7/8/r2n4/8/3K4/7q b - - 0 1".to_string(), mate_in: 3, mating_side_white: false })
@@ -587,19 +732,567 @@ mod test{
         run_mate(TestPositon{pos: "6k1/1p3ppp/4b3/2p4q/8/P2Pp1QP/2P3PK/8 w - - 0 26 ".to_string(), mate_in: 2, mating_side_white: true})
     }
 
+    #[test]
+    fn test_game_phase(){
+        assert_eq!(evaluation::game_phase(&Board::default()), constants::PHASE_MAX);
+        assert_eq!(evaluation::game_phase(&Board::from_str("8/4k3/4p3/8/8/4P3/4K3/8 w - - 0 1").unwrap()), 0);
+        // trading a pair of knights only moves the phase a little
+        assert_eq!(evaluation::game_phase(&Board::from_str("r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap()), 235);
+    }
+
+    #[test]
+    fn test_eval_trace_matches_evaluation(){
+        let board = Board::from_str("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let mut pawn_table = chess::CacheTable::new(1024, PawnEntry::default());
+        let eval = evaluation::evaluate_rework(&board) + search::pawn_table_lookup(&board, &mut pawn_table, &mut SearchInfo::new());
+        assert_eq!(evaluation::trace(&board).value(), eval);
+    }
+
+    #[test]
+    fn test_rook_pairs_and_trapped_rook(){
+        use eval_trace::Term;
+        let params = EvalParams::default();
+        // the rooks on a1 and a3 are doubled and connected, that is one pair
+        let trace = evaluation::trace(&Board::from_str("6k1/8/8/8/8/R7/8/R5K1 w - - 0 1").unwrap());
+        let pair = (params.doubled_rooks.0 + params.connected_rooks.0, params.doubled_rooks.1 + params.connected_rooks.1);
+        assert_eq!(trace.get(Term::HeavyPieces, chess::Color::White), pair);
+        // the rook on h1 behind the king that can't castle anymore is trapped, and not blocked on top of it
+        let trace = evaluation::trace(&Board::from_str("6k1/8/8/8/8/8/5PPP/6KR w - - 0 1").unwrap());
+        assert_eq!(trace.get(Term::HeavyPieces, chess::Color::White), params.trapped_rook);
+        assert_eq!(trace.get(Term::BlockedPieces, chess::Color::White), (0, 0));
+    }
+
+    #[test]
+    fn test_mobility(){
+        use eval_trace::Term;
+        let params = EvalParams::default();
+        let mobility = |fen: &str, term: Term| evaluation::trace(&Board::from_str(fen).unwrap()).get(term, chess::Color::White);
+        // the bishop sees every square of its diagonals on the empty board
+        assert_eq!(mobility("4k3/8/8/8/3B4/8/8/4K3 w - - 0 1", Term::BishopMobility), params.bishop_mobility[13]);
+        // it stops at the pawn it attacks, and the square the pawn attacks is left out
+        assert_eq!(mobility("4k3/8/5p2/8/3B4/8/8/4K3 w - - 0 1", Term::BishopMobility), params.bishop_mobility[10]);
+        // behind our own pawn it is trapped
+        assert_eq!(mobility("4k3/8/8/8/8/8/1P6/B3K3 w - - 0 1", Term::BishopMobility), params.bishop_mobility[0]);
+        assert_eq!(mobility("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1", Term::KnightMobility), params.knight_mobility[8]);
+        // the squares of the cornered knight are attacked by the enemy pawns
+        assert_eq!(mobility("4k3/8/8/8/2p5/3p4/8/N3K3 w - - 0 1", Term::KnightMobility), params.knight_mobility[0]);
+        assert_eq!(mobility("4k3/8/8/8/3R4/8/8/6K1 w - - 0 1", Term::RookMobility), params.rook_mobility[14]);
+        assert_eq!(mobility("4k3/8/8/8/8/8/6PP/6KR w - - 0 1", Term::RookMobility), params.rook_mobility[0]);
+
+        // a free piece is worth more than a trapped one
+        let value = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap()).value();
+        assert!(value("4k3/8/8/8/3B4/8/1P6/4K3 w - - 0 1") > value("4k3/8/8/8/8/8/1P6/B3K3 w - - 0 1"));
+        assert!(value("4k3/8/8/8/2pN4/3p4/8/4K3 w - - 0 1") > value("4k3/8/8/8/2p5/3p4/8/N3K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_king_safety(){
+        use eval_trace::Term;
+        let safety = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap()).get(Term::KingSafety, chess::Color::White);
+        let sheltered = safety("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        // the queen and the rook attack the ring of the king that lost its pawns
+        let attacked = safety("6k1/5ppp/8/8/4q3/8/r7/6K1 w - - 0 1");
+        assert!(attacked.0 < 0 && attacked.1 < 0);
+        assert!(attacked.0 < sheltered.0);
+        // the same attack without the queen is less dangerous
+        assert!(safety("6k1/5ppp/8/8/4b3/8/r7/6K1 w - - 0 1").0 > attacked.0);
+    }
+
+    #[test]
+    fn test_threats(){
+        use eval_trace::Term;
+        let params = EvalParams::default();
+        // the pawn attacks the knight nobody defends, two threats for the side to move
+        let trace = evaluation::trace(&Board::from_str("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1").unwrap());
+        let index = chess::Piece::Knight.to_index();
+        let threats = trace.get(Term::Threats, chess::Color::White);
+        assert_eq!(threats.0, params.hanging_piece[index].0 + params.threat_by_lesser[index].0 + params.multiple_threats.0);
+        assert_eq!(threats.1, params.hanging_piece[index].1 + params.threat_by_lesser[index].1 + params.multiple_threats.1);
+        assert!(threats.0 > 0 && threats.1 > 0);
+        assert_eq!(trace.get(Term::Threats, chess::Color::Black), (0, 0));
+        // the pawn push would attack the knight
+        let trace = evaluation::trace(&Board::from_str("4k3/8/8/3n4/8/4P3/8/4K3 w - - 0 1").unwrap());
+        assert_eq!(trace.get(Term::Threats, chess::Color::White), params.pawn_push_threat);
+    }
+
+    #[test]
+    fn test_pawn_structure(){
+        use eval_trace::Term;
+        let structure = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap()).get(Term::PawnStructure, chess::Color::White);
+        // doubled and isolated pawns are weak, pawns side by side and defending each other are strong
+        let weak = structure("4k3/8/8/8/3P4/3P4/8/4K3 w - - 0 1");
+        assert!(weak.0 < 0 && weak.1 < 0);
+        let phalanx = structure("4k3/8/8/8/3PP3/8/8/4K3 w - - 0 1");
+        assert!(phalanx.0 > weak.0 && phalanx.1 > weak.1);
+        let chain = structure("4k3/8/8/8/4P3/3P4/8/4K3 w - - 0 1");
+        assert!(chain.0 > weak.0 && chain.1 > weak.1);
+    }
+
+    #[test]
+    fn test_passed_pawns(){
+        use eval_trace::Term;
+        let passed = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap()).get(Term::PassedPawns, chess::Color::White);
+        let advanced = passed("4k3/8/3P4/8/8/8/8/4K3 w - - 0 1");
+        assert!(advanced.0 > 0 && advanced.1 > 0);
+        assert!(advanced.1 > passed("4k3/8/8/8/8/3P4/8/4K3 w - - 0 1").1);
+        // the knight in front of the pawn stops it
+        assert!(passed("4k3/3n4/3P4/8/8/8/8/4K3 w - - 0 1").1 < advanced.1);
+        // a pawn the enemy pawn stands in front of isn't passed
+        assert_eq!(passed("4k3/3p4/8/8/3P4/8/8/4K3 w - - 0 1"), (0, 0));
+    }
+
+    #[test]
+    fn test_space_and_centre_control(){
+        use eval_trace::Term;
+        let trace = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap());
+        // the squares behind the pawn wall are worth something with knights to use them
+        let space = trace("4k3/8/8/8/2PPPP2/8/8/1N2K1N1 w - - 0 1").get(Term::Space, chess::Color::White);
+        assert!(space.0 > 0);
+        assert_eq!(space.1, 0);
+        assert_eq!(trace("4k3/8/8/8/2PPPP2/8/8/4K3 w - - 0 1").get(Term::Space, chess::Color::White), (0, 0));
+        // the knights attack the centre, the black king doesn't
+        let centre = trace("4k3/8/8/8/8/2N2N2/8/4K3 w - - 0 1");
+        let white = centre.get(Term::CentreControl, chess::Color::White);
+        assert!(white.0 > 0 && white.1 > 0);
+        assert_eq!(centre.get(Term::CentreControl, chess::Color::Black), (0, 0));
+        assert!(centre.blended(Term::CentreControl) > 0);
+    }
+
+    #[test]
+    fn test_minor_pieces(){
+        use eval_trace::Term;
+        let params = EvalParams::default();
+        let trace = |fen: &str| evaluation::trace(&Board::from_str(fen).unwrap());
+        let minor = |fen: &str| trace(fen).get(Term::MinorPieces, chess::Color::White);
+        // the fianchettoed bishop sees the centre along the long diagonal
+        let fianchetto = minor("4k3/8/8/8/8/6P1/6B1/6K1 w - - 0 1");
+        assert_eq!(fianchetto, (params.fianchetto.0 + params.long_diagonal_bishop.0, params.fianchetto.1 + params.long_diagonal_bishop.1));
+        assert!(fianchetto.0 > 0);
+        // the bishop that took on a7 is shut in by the pawn on b6
+        assert_eq!(minor("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1"), params.trapped_bishop);
+        assert!(params.trapped_bishop.0 < 0);
+        // both pawns stand on the color of the bishop
+        let bad = minor("4k3/8/8/8/3P4/4P3/8/2B1K3 w - - 0 1");
+        assert_eq!(bad, (2 * params.bad_bishop.0, 2 * params.bad_bishop.1));
+        assert!(bad.0 < 0 && bad.1 < 0);
+        let pair = trace("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").get(Term::BishopPair, chess::Color::White);
+        assert_eq!(pair, params.bishop_pair);
+        assert!(pair.0 > 0 && pair.1 > 0);
+        // the knight defended by the pawn can't be chased away by a black pawn
+        let outpost = params.knight_outpost[chess::Square::D5.to_index()];
+        assert!(outpost > 0);
+        assert_eq!(trace("4k3/8/8/3N4/4P3/8/8/4K3 w - - 0 1").get(Term::KnightOutpost, chess::Color::White), (outpost, outpost));
+        assert_eq!(trace("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1").get(Term::KnightOutpost, chess::Color::White), (0, 0));
+    }
+
+    #[test]
+    fn test_params_vector_round_trip(){
+        let params = params::EvalParams::default();
+        let mut vector = params.to_vector();
+        assert_eq!(vector.len(), params.vector_names().len());
+        let mut changed = params.clone();
+        vector[0] += 1;
+        changed.from_vector(&vector);
+        assert_eq!(changed.pawn_val, (params.pawn_val.0 + 1, params.pawn_val.1));
+        assert_eq!(changed.to_vector(), vector);
+    }
+
+    #[test]
+    fn test_params_file_round_trip(){
+        let mut params = EvalParams{queen_val: (1000, 1100), ..EvalParams::default()};
+        params.knight_pst[10] = 7;
+        assert_eq!(EvalParams::parse(&params.dump()), Ok(params));
+        let partial = EvalParams::parse("# only the rooks\nrook_val = 480 520\n").unwrap();
+        assert_eq!(partial.rook_val, (480, 520));
+        assert_eq!(partial.queen_val, EvalParams::default().queen_val);
+        assert!(EvalParams::parse("rook_val 480").is_err());
+        assert!(EvalParams::parse("no_such_weight 1").is_err());
+    }
+
+    #[test]
+    fn test_nnue_material_network(){
+        let stack = nnue::NnueStack::new(Arc::new(nnue::Network::load("nets/material.nnue").unwrap()));
+        // the test network only counts material
+        assert_eq!(stack.evaluate(&Board::default()), 0);
+        assert_eq!(stack.evaluate(&Board::from_str("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap()), 900);
+        assert_eq!(stack.evaluate(&Board::from_str("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap()), -900);
+    }
+
+    #[test]
+    fn test_nnue_incremental_matches_refresh(){
+        // random weights so a feature that is updated wrong changes the score
+        let hidden = 16;
+        let mut rng = rand::thread_rng();
+        let mut bytes = b"RCNN".to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend((hidden as u32).to_le_bytes());
+        for _ in 0..(nnue::INPUTS * hidden + 3 * hidden){
+            bytes.extend(rng.gen_range(-64i16..64).to_le_bytes());
+        }
+        bytes.extend(0i32.to_le_bytes());
+        let network = Arc::new(nnue::Network::from_bytes(&bytes).unwrap());
+
+        // castling, en passant, a promotion with a capture and king moves across the buckets, all of them legal
+        let mut stack = nnue::NnueStack::new(network.clone());
+        let mut board = Board::from_str("r3k2r/1P6/8/8/5p2/8/4P3/R3K2R w KQkq - 0 1").unwrap();
+        stack.push(&board);
+        for chess_move in ["e1g1", "e8g8", "e2e4", "f4e3", "b7a8q", "f8a8", "g1g2", "a8b8", "g2f3"]{
+            let chess_move = ChessMove::from_str(chess_move).unwrap();
+            assert!(board.legal(chess_move), "{}", chess_move);
+            board = board.make_move_new(chess_move);
+            stack.push(&board);
+            assert_eq!(stack.evaluate(&board), network.evaluate(&nnue::Accumulator::new(&network, &board), board.side_to_move()), "{}", chess_move);
+        }
+    }
+
+    // a network with the same weight for every feature, the same bias for every neuron and the given output weights
+    fn uniform_network(hidden: usize, weight: i16, bias: i16, our_weight: i16, their_weight: i16) -> Vec<u8>{
+        let mut bytes = b"RCNN".to_vec();
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend((hidden as u32).to_le_bytes());
+        for (value, count) in [(weight, nnue::INPUTS * hidden), (bias, hidden), (our_weight, hidden), (their_weight, hidden)]{
+            for _ in 0..count{
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        bytes.extend(0i32.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_nnue_overflow(){
+        // 32 pieces with a weight of 2000 each don't fit in an i16 accumulator
+        assert!(nnue::Network::from_bytes(&uniform_network(1, 2000, 0, 1, 1)).is_err());
+        assert!(nnue::Network::from_bytes(&uniform_network(1, 1000, 0, 1, 1)).is_ok());
+        // every neuron is at the clip with the largest output weight, far more than an i32 holds after scaling
+        let network = Arc::new(nnue::Network::from_bytes(&uniform_network(64, 0, 255, i16::MAX, 0)).unwrap());
+        let stack = nnue::NnueStack::new(network);
+        assert_eq!(stack.evaluate(&Board::default()), constants::TB_WIN_SCORE - 1);
+    }
+
+    #[test]
+    fn test_incremental_pawn_key_and_material(){
+        // captures by and of pawns, en passant, a promotion, castling and a capture by the king
+        let mut info = SearchInfo::new();
+        let mut board = Board::from_str("r3k2r/1P6/8/8/5p2/8/4P3/R3K2R w KQkq - 0 1").unwrap();
+        for chess_move in ["e2e4", "f4e3", "b7a8q", "e8f7", "a8b8", "f7e6", "e1g1", "h8h2", "g1h2"]{
+            let chess_move = ChessMove::from_str(chess_move).unwrap();
+            assert!(board.legal(chess_move), "{}", chess_move);
+            let new_board = board.make_move_new(chess_move);
+            info.push_move(&board, chess_move, &new_board);
+            board = new_board;
+            assert_eq!(info.pawn_key(&board), pawns::pawn_key(&board), "{}", chess_move);
+            assert_eq!(info.material(&board), material::MaterialPst::new(&board, &info.params), "{}", chess_move);
+        }
+        // castling on the queen side
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let chess_move = ChessMove::from_str("e8c8").unwrap();
+        let values = material::MaterialPst::new(&board, &info.params).update(&info.params, &board, chess_move);
+        assert_eq!(values, material::MaterialPst::new(&board.make_move_new(chess_move), &info.params));
+    }
+
+    #[test]
+    fn test_stalemate_evaluation(){
+        let mut pawn_table = chess::CacheTable::new(1024, PawnEntry::default());
+        // the bare king has no move, a known endgame win otherwise
+        let board = Board::from_str("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.status(), chess::BoardStatus::Stalemate);
+        assert!(endgame::evaluate(&board).unwrap() < -endgame::KNOWN_WIN);
+        assert_eq!(SearchInfo::new().evaluate(&board, 7, &mut pawn_table), 7);
+    }
+
+    #[test]
+    fn test_eval_cache(){
+        let cache = eval_cache::EvalCache::new(1024);
+        let board = Board::default();
+        assert_eq!(cache.get(board.get_hash()), None);
+        cache.add(board.get_hash(), -37);
+        assert_eq!(cache.get(board.get_hash()), Some(-37));
+        // a key that lands in the same entry doesn't match
+        assert_eq!(cache.get(board.get_hash() ^ (1 << 40)), None);
+    }
+
+    #[test]
+    fn test_trace_and_replay(){
+        let board = Board::from_str("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4").unwrap();
+        let mut state = SearchState::new();
+        state.start(SearchLimits::none(), DrawTracker::new(&board, 0));
+        state.info.tracer = Some(trace::Tracer::new(&board, 3));
+        let result = search::search_depth(&board, 3, &None, u128::MAX, (None, -9999), &mut state);
+        let mut tracer = state.info.tracer.take().unwrap();
+        tracer.finish(result.1);
+        let nodes = tracer.nodes();
+
+        // every node but the root is counted, except the zero window nodes at depth 0 that only call the quiescence search
+        let passed_to_quiescence = nodes.iter().filter(|x| x.kind == "zw" && x.depth == 0).count();
+        assert_eq!(nodes.len() - 1 - passed_to_quiescence, result.3.nodes_searched as usize);
+        // the nodes are in pre-order: a node is at most one level below the one before it
+        assert_eq!(nodes[0].kind, "root");
+        assert!(nodes.windows(2).all(|x| x[1].level >= 1 && x[1].level <= x[0].level + 1));
+        assert!(nodes.iter().all(|x| x.score.is_some()));
+        let reasons: Vec<&str> = nodes.iter().filter_map(|x| x.pruning).collect();
+        for reason in ["beta cutoff", "stand pat"]{
+            assert!(reasons.contains(&reason), "{}", reason);
+        }
+        assert!(reasons.iter().all(|x| ["stopped", "mate", "draw", "mate distance", "tablebase", "kpk draw", "null move", "razoring", "futility", "stalemate", "beta cutoff", "stand pat", "delta"].contains(x)));
+
+        // the replay of the first root move is the root, the move and the nodes right below it
+        let path = std::env::temp_dir().join(format!("rust_chess_trace_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        tracer.write(path).unwrap();
+        let first = result.2[0].0.to_string();
+        let mut expected = vec![(0, "-".to_string())];
+        let mut on_path = false;
+        for node in &nodes[1..]{
+            if node.level == 1{
+                on_path = node.label == first;
+            }
+            if on_path && node.level <= 2{
+                expected.push((node.level, node.label.clone()));
+            }
+        }
+        let mut out = Vec::new();
+        trace::replay(path, &[first.as_str()], &mut out).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(lines.next().unwrap(), format!("trace depth 3 fen {}", board));
+        let replayed: Vec<(usize, String)> = lines.map(|x| ((x.len() - x.trim_start().len()) / 2, x.trim_start().split(' ').next().unwrap().to_string())).collect();
+        assert!(expected.len() > 2);
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn test_tuning_samples(){
+        assert_eq!(tune::parse_result("[Event \"?\"] 1-0"), Some(1.));
+        assert_eq!(tune::parse_result("8/8/8/8/8/8/8/8 w - - c9 \"0-1\";"), Some(0.));
+        assert_eq!(tune::parse_result("8/8/8/8/8/8/8/8 w - - [0.5]"), Some(0.5));
+        assert_eq!(tune::parse_result("8/8/8/8/8/8/8/8 w - - 1/2-1/2"), Some(0.5));
+        assert_eq!(tune::parse_result("8/8/8/8/8/8/8/8 w - -"), None);
+
+        let sample = tune::parse_sample("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1 [1.0]").unwrap();
+        assert_eq!(sample.board, Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap());
+        assert_eq!(sample.result, 1.);
+        // the move counters of an epd line are made up
+        let sample = tune::parse_sample("r3k2r/ppp2ppp/8/8/8/8/PPP2PPP/R3K2R w KQkq - c9 \"1/2-1/2\";").unwrap();
+        assert_eq!(sample.board.get_hash(), Board::from_str("r3k2r/ppp2ppp/8/8/8/8/PPP2PPP/R3K2R w KQkq - 0 1").unwrap().get_hash());
+        assert_eq!(sample.result, 0.5);
+        // finished games, known endgames and scaled down endgames are left out
+        assert!(tune::parse_sample("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1 [1.0]").is_none());
+        assert!(tune::parse_sample("8/8/8/4k3/8/8/8/KBN5 w - - 0 1 [1.0]").is_none());
+        assert!(tune::parse_sample("4k3/8/8/2b5/8/3B4/4PP2/4K3 w - - 0 1 [0.5]").is_none());
+        assert!(tune::parse_sample("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").is_none());
+        assert!(tune::parse_sample("not a position [1.0]").is_none());
+
+        // results made from the evaluation with a known constant give back that constant
+        let params = params::current();
+        let fens = ["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1", "rnbqkbnr/pppppppp/8/8/8/8/PPP1PPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"];
+        let samples: Vec<tune::Sample> = fens.iter().map(|fen| {
+            let board = Board::from_str(fen).unwrap();
+            let score = evaluation::trace_with_params(&board, &params).value() as f64;
+            tune::Sample{board, result: 1. / (1. + 10f64.powf(-1.3 * score / 400.))}
+        }).collect();
+        assert!((tune::fit_k(&samples, &params) - 1.3).abs() < 0.01);
+        assert!(tune::error(&samples, &params, 1.3) < 1e-9);
+    }
+
+    #[test]
+    fn test_history_updates(){
+        let mut history = history::History::new();
+        let board = Board::default();
+        let chess_move = ChessMove::from_str("g1f3").unwrap();
+        // the gravity update brings the entry closer to the bonus the closer it is to the maximum
+        let bonus = history::bonus(20);
+        history.update_quiet(&board, chess_move, bonus);
+        assert_eq!(history.quiet_score(&board, chess_move), bonus);
+        history.update_quiet(&board, chess_move, bonus);
+        assert_eq!(history.quiet_score(&board, chess_move), 2 * bonus - bonus * bonus / history::HISTORY_MAX);
+        for _ in 0..1000{
+            history.update_quiet(&board, chess_move, bonus);
+        }
+        assert_eq!(history.quiet_score(&board, chess_move), history::HISTORY_MAX);
+        history.update_quiet(&board, chess_move, -bonus);
+        assert_eq!(history.quiet_score(&board, chess_move), history::HISTORY_MAX - 2 * bonus);
+
+        // the moves one and two plies back have their own continuation tables
+        let first = ChessMove::from_str("e2e4").unwrap();
+        let second = ChessMove::from_str("e7e5").unwrap();
+        let before = board.make_move_new(first).make_move_new(second);
+        let current = history::piece_to(&before, chess_move).unwrap();
+        let mut history = history::History::new();
+        history.push(history::piece_to(&board, first));
+        history.push(history::piece_to(&board.make_move_new(first), second));
+        history.update_quiet(&before, chess_move, bonus);
+        assert_eq!(history.continuation_score(current), 2 * bonus);
+        history.pop();
+        history.pop();
+        history.push(history::piece_to(&board.make_move_new(first), second));
+        history.push(history::piece_to(&board, first));
+        assert_eq!(history.continuation_score(current), 0);
+        history.pop();
+        assert_eq!(history.continuation_score(current), bonus);
+    }
+
+    #[test]
+    fn test_syzygy_single_value_table(){
+        // a KRvK table where every position with white to move is won and every position with black to move is lost:
+        // magic, both sides stored, the group order, the pieces (king, rook, king), padding and one value per side
+        let directory = std::env::temp_dir().join(format!("rust_chess_syzygy_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let table = [0x71, 0xE8, 0x23, 0x5D, 0x01, 0x00, 0x66, 0x44, 0xEE, 0x00, 0x80, 4, 0x80, 0];
+        std::fs::write(directory.join("KRvK.rtbw"), table).unwrap();
+        let tablebases = syzygy::Tablebases::load(directory.to_str().unwrap()).unwrap();
+        assert_eq!((tablebases.count(), tablebases.max_pieces()), (1, 3));
+
+        let probe = |fen: &str| tablebases.probe_wdl(&Board::from_str(fen).unwrap());
+        assert_eq!(probe("8/8/8/8/8/2k5/8/R3K3 w - - 0 1"), Some(syzygy::Wdl::Win));
+        assert_eq!(probe("8/8/8/8/8/2k5/8/R3K3 b - - 0 1"), Some(syzygy::Wdl::Loss));
+        // black to move takes the rook whatever the table says
+        assert_eq!(probe("8/8/8/8/8/8/1k6/R3K3 b - - 0 1"), Some(syzygy::Wdl::Draw));
+        // the same table with the colors swapped
+        assert_eq!(probe("r3k3/8/8/8/8/2K5/8/8 b - - 0 1"), Some(syzygy::Wdl::Win));
+        // no table for this material, and none with castling rights
+        assert_eq!(probe("8/8/8/8/8/3k4/8/Q3K3 w - - 0 1"), None);
+        assert_eq!(probe("8/8/8/8/8/2k5/8/R3K3 w Q - 0 1"), None);
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[test]
+    fn test_syzygy_tables(){
+        // the 3-piece tables and KQvKR, written by the generator of tbgen.rs
+        let tablebases = syzygy::Tablebases::load("test-data/syzygy").unwrap();
+        assert_eq!((tablebases.count(), tablebases.max_pieces()), (6, 4));
+        let board = |fen: &str| Board::from_str(fen).unwrap();
+        let wdl = |fen: &str| tablebases.probe_wdl(&board(fen));
+        let dtz = |fen: &str| tablebases.probe_dtz(&board(fen));
+        let root_move = |fen: &str, halfmove_clock: u32| tablebases.root_move(&board(fen), halfmove_clock).map(|(chess_move, dtz)| (chess_move.to_string(), dtz));
+
+        // KRvK: the mate in one, the mate, black walking into it and black taking the rook
+        assert_eq!(wdl("k7/8/1K6/8/8/8/8/7R w - - 0 1"), Some(syzygy::Wdl::Win));
+        assert_eq!(root_move("k7/8/1K6/8/8/8/8/7R w - - 0 1", 0), Some(("h1h8".to_string(), 1)));
+        assert_eq!(dtz("k6R/8/1K6/8/8/8/8/8 b - - 0 1"), Some(-1));
+        // the dtz table only stores white to move, black to move is found from the moves
+        assert_eq!(wdl("k7/8/1K6/8/8/8/8/7R b - - 0 1"), Some(syzygy::Wdl::Loss));
+        assert_eq!(dtz("k7/8/1K6/8/8/8/8/7R b - - 0 1"), Some(-2));
+        assert_eq!(wdl("8/8/8/8/8/8/1k6/R3K3 b - - 0 1"), Some(syzygy::Wdl::Draw));
+        assert_eq!(dtz("8/8/8/8/8/8/1k6/R3K3 b - - 0 1"), Some(0));
+        // the longest win is a mate in 16, also with the colors swapped
+        assert_eq!(dtz("8/8/8/8/8/2k5/1R6/K7 w - - 0 1"), Some(31));
+        assert_eq!(dtz("k7/1r6/2K5/8/8/8/8/8 b - - 0 1"), Some(31));
+
+        // KPvK: the promotion wins, the rook pawn and the stalemate are draws
+        assert_eq!(root_move("8/4P3/8/8/8/k7/8/K7 w - - 0 1", 0), Some(("e7e8q".to_string(), 1)));
+        assert_eq!(wdl("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(syzygy::Wdl::Draw));
+        assert_eq!(wdl("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(syzygy::Wdl::Draw));
+        assert_eq!(wdl("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(syzygy::Wdl::Loss));
+        assert_eq!(wdl("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"), Some(syzygy::Wdl::Loss));
+        assert_eq!(dtz("8/8/8/k7/8/8/K4P2/8 w - - 0 1"), Some(19));
+
+        // KQvKR: black takes the queen, white takes the rook
+        assert_eq!(root_move("3rk3/8/8/8/3Q4/8/8/7K b - - 0 1", 0), Some(("d8d4".to_string(), 1)));
+        assert_eq!(root_move("8/8/8/4k3/8/8/3r4/1Q2K3 w - - 0 1", 0), Some(("e1d2".to_string(), 1)));
+        // the longest win only converts before the fifty-move rule while the counter is low enough
+        let longest = "8/8/8/8/Q7/5k2/8/K3r3 w - - 0 1";
+        assert_eq!(wdl(longest), Some(syzygy::Wdl::Win));
+        assert_eq!(dtz(longest), Some(61));
+        assert_eq!(root_move(longest, 38).map(|x| x.1), Some(61));
+        assert_eq!(root_move(longest, 39), None);
+    }
+
+    // writes the tables of test-data/syzygy again, cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn test_generate_syzygy_tables(){
+        for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KQvKR"]{
+            tbgen::generate(name, "test-data/syzygy").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_kpk_bitbase(){
+        let probe = |fen: &str| bitbase::probe_kpk(&Board::from_str(fen).unwrap());
+        // our king in front of the pawn on the sixth rank wins whoever moves
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), Some(true));
+        assert_eq!(probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), Some(true));
+        // with the pawn on the seventh our king steps aside to win, on their move it is stalemate
+        assert_eq!(probe("4k3/4P3/4K3/8/8/8/8/8 w - - 0 1"), Some(true));
+        assert_eq!(probe("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(false));
+        // the opposition decides, and the king in the corner holds a rook pawn
+        assert_eq!(probe("8/8/4k3/8/4K3/4P3/8/8 w - - 0 1"), Some(false));
+        assert_eq!(probe("8/8/4k3/8/4K3/4P3/8/8 b - - 0 1"), Some(true));
+        assert_eq!(probe("k7/8/8/8/8/8/P7/7K w - - 0 1"), Some(false));
+        // the rule of the square, on the h-file for the mirrored half of the table
+        assert_eq!(probe("8/8/8/8/7P/8/8/K1k5 w - - 0 1"), Some(true));
+        assert_eq!(probe("8/8/5k2/8/7P/8/8/K7 w - - 0 1"), Some(false));
+        // black has the pawn
+        assert_eq!(probe("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"), Some(true));
+        assert_eq!(probe("8/8/8/8/8/4k3/4p3/4K3 w - - 0 1"), Some(false));
+        assert_eq!(probe("8/8/8/8/8/4k3/4p3/4KB2 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_endgame_registry(){
+        let score = |fen: &str| endgame::evaluate(&Board::from_str(fen).unwrap());
+        // the bare king is mated in the corner of the bishop's color
+        assert!(score("8/8/8/4k3/8/8/8/KBN5 w - - 0 1").unwrap() > endgame::KNOWN_WIN);
+        assert!(score("8/8/8/4k3/8/8/8/KBN5 b - - 0 1").unwrap() < -endgame::KNOWN_WIN);
+        assert!(score("8/8/8/4k3/8/8/8/KNN5 w - - 0 1").unwrap().abs() < 50);
+        // the black king is outside the square of the pawn, and the same position with black to move catches it
+        assert!(score("8/8/8/8/P4k2/8/8/7K w - - 0 1").unwrap() > endgame::KNOWN_WIN);
+        assert!(score("8/8/8/8/P4k2/8/8/7K b - - 0 1").unwrap().abs() < endgame::KNOWN_WIN);
+        // a bishop pawn on the seventh next to its king holds against the queen
+        assert!(score("8/8/8/8/8/1Q6/5pk1/3K4 w - - 0 1").unwrap() < 200);
+        assert!(score("8/8/8/8/8/1Q6/4p1k1/3K4 w - - 0 1").unwrap() > 500);
+        assert_eq!(score("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), None);
+        // bishops of opposite colors
+        assert!(endgame::scale_factor(&Board::from_str("4k3/8/8/2b5/8/3B4/4PP2/4K3 w - - 0 1").unwrap(), chess::Color::White) < endgame::SCALE_NORMAL);
+    }
+
+    #[test]
+    fn test_mirrored_evaluation(){
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "rnbqkb1r/ppp1pppp/3p1n2/8/8/3P4/PPP1PPPP/RNBQKBNR b KQkq - 1 3",
+            "rn1qkb1r/ppp1pppp/8/3p1bB1/3Pn3/3Q1N2/PPP1PPPP/RN2KB1R w KQkq - 4 5",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            "1kr5/1p3ppp/p1n5/3Np3/2P1n3/1P4P1/P3PPBP/3R2K1 b - - 3 22",
+            "8/p3k2p/Pp4p1/1Pn3P1/3RK3/8/8/8 w - - 0 1",
+            "6k1/1p3pp1/p7/8/r2n4/8/3K4/7q b - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "2kr3r/ppp2ppp/2n5/2b1p3/4P1b1/2NP1N2/PPP2PPP/R1B2RK1 w - e6 0 12",
+        ];
+        let mut pawn_table = chess::CacheTable::new(1024, PawnEntry::default());
+        let mut info = SearchInfo::new();
+        for position in positions{
+            let board = Board::from_str(position).unwrap();
+            let mirrored = utils::mirror_board(&board);
+            assert_eq!(utils::mirror_board(&mirrored), board);
+            let eval = evaluation::evaluate_rework(&board) + search::pawn_table_lookup(&board, &mut pawn_table, &mut info);
+            let mirrored_eval = evaluation::evaluate_rework(&mirrored) + search::pawn_table_lookup(&mirrored, &mut pawn_table, &mut info);
+            assert_eq!(eval, mirrored_eval, "{}", position);
+        }
+    }
+
+    // the old evaluation scores from white's side with its own terms, so the rework is checked against
+    // its own trace and the mirrored position instead
     fn test_better_evaluation(position: &str){
-        let board = Board::from_str(position).ok().expect("msg"); 
+        let board = Board::from_str(position).expect("msg"); 
+        let mirrored = utils::mirror_board(&board);
+        let mut pawn_table = chess::CacheTable::new(1024, PawnEntry::default());
+        let mut info = SearchInfo::new();
         let now = Instant::now();
-        let eval = evaluation::evaluate(&board);
+        let eval = evaluation::evaluate_rework(&board) + search::pawn_table_lookup(&board, &mut pawn_table, &mut info);
         let elapsed = now.elapsed();
         println!("{} - {:?}", eval, elapsed);
 
-        let now = Instant::now();
-        let eval_other = evaluation::evaluate_rework(&board);
-        let elapsed = now.elapsed();
-        println!("{} - {:?}", eval_other, elapsed);
-
-        assert_eq!(eval_other, eval);
+        // the trace is from white's point of view, the evaluation from the side to move's
+        let sign = if board.side_to_move() == chess::Color::White {1} else {-1};
+        assert_eq!(evaluation::trace(&board).value() * sign, eval);
+        let mirrored_eval = evaluation::evaluate_rework(&mirrored) + search::pawn_table_lookup(&mirrored, &mut pawn_table, &mut info);
+        assert_eq!(mirrored_eval, eval);
     }
 
 
@@ -0,0 +1,84 @@
+use chess::{Board, ChessMove, Color, File, Piece, Square, ALL_PIECES};
+use crate::constants::Score;
+use crate::params::EvalParams;
+use crate::utils::{get_piece_type, relative_square};
+
+// the material and the piece square tables of both sides, indexed by color. they only change for the pieces a
+// move touches, so the search keeps them up to date move by move instead of adding up every piece at every leaf
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MaterialPst{
+    pub material: [Score; 2],
+    pub pst: [Score; 2]
+}
+
+// the material and the piece square value of a piece of the color on the square
+fn piece_value(params: &EvalParams, piece: Piece, color: Color, square: Square) -> (Score, Score){
+    let index = relative_square(square, color).to_index();
+    let both = |value: i32| (value, value);
+    match piece{
+        Piece::Pawn=>(params.pawn_val, both(params.pawn_pst[index])),
+        Piece::Knight=>(params.knight_val, both(params.knight_pst[index])),
+        Piece::Bishop=>(params.bishop_val, both(params.bishop_pst[index])),
+        Piece::Rook=>(params.rook_val, both(params.rook_pst[index])),
+        Piece::Queen=>(params.queen_val, both(params.queen_pst[index])),
+        // the king hides behind its pawns in the opening and walks to the center in the endgame
+        Piece::King=>((0, 0), (params.king_pst[index], params.king_endgame_pst[index]))
+    }
+}
+
+impl MaterialPst{
+    pub fn new(board: &Board, params: &EvalParams) -> MaterialPst{
+        let mut values = MaterialPst::default();
+        for color in [Color::White, Color::Black]{
+            for piece in ALL_PIECES{
+                for square in get_piece_type(board, piece, color){
+                    values.add(params, piece, color, square, 1);
+                }
+            }
+        }
+        values
+    }
+
+    // the values after a move from the values before it, only the pieces that moved, were taken or promoted change them
+    pub fn update(&self, params: &EvalParams, board: &Board, chess_move: ChessMove) -> MaterialPst{
+        let mut values = *self;
+        let us = board.side_to_move();
+        let source = chess_move.get_source();
+        let dest = chess_move.get_dest();
+        let piece = match board.piece_on(source){
+            Some(piece)=>piece,
+            None=>return values
+        };
+        values.add(params, piece, us, source, -1);
+        values.add(params, chess_move.get_promotion().unwrap_or(piece), us, dest, 1);
+
+        if let Some(captured) = board.piece_on(dest){
+            values.add(params, captured, !us, dest, -1);
+        }else if piece == Piece::Pawn && source.get_file() != dest.get_file(){
+            // en passant, the pawn is taken on the square it moved to
+            values.add(params, Piece::Pawn, !us, Square::make_square(source.get_rank(), dest.get_file()), -1);
+        }
+
+        // castling moves the rook from the corner to the other side of the king
+        if piece == Piece::King && source.get_file().to_index().abs_diff(dest.get_file().to_index()) == 2{
+            let (from, to) = if dest.get_file() == File::G { (File::H, File::F) } else { (File::A, File::D) };
+            values.add(params, Piece::Rook, us, Square::make_square(source.get_rank(), from), -1);
+            values.add(params, Piece::Rook, us, Square::make_square(source.get_rank(), to), 1);
+        }
+        values
+    }
+
+    fn add(&mut self, params: &EvalParams, piece: Piece, color: Color, square: Square, sign: i32){
+        let (material, pst) = piece_value(params, piece, color, square);
+        let index = color.to_index();
+        self.material[index] = (self.material[index].0 + sign * material.0, self.material[index].1 + sign * material.1);
+        self.pst[index] = (self.pst[index].0 + sign * pst.0, self.pst[index].1 + sign * pst.1);
+    }
+
+    // the opening material of the side to move minus the material of the other side
+    pub fn material_balance(&self, board: &Board) -> i32{
+        let us = board.side_to_move().to_index();
+        let them = (!board.side_to_move()).to_index();
+        self.material[us].0 - self.material[them].0
+    }
+}
@@ -0,0 +1,249 @@
+use chess::{BitBoard, Board, Color, Piece, Square, ALL_PIECES};
+use std::fs;
+use std::io;
+use std::sync::{Arc, LazyLock, RwLock};
+use crate::{constants, utils::relative_square};
+
+// an efficiently updatable network: the pieces are the inputs of one hidden layer per side (the accumulator),
+// the two accumulators go through a clipped relu into a single output.
+// the inputs are king bucketed, every side sees the board from its own point of view (ranks flipped for black)
+// with the bucket of its own king: bucket * 768 + (own pieces ? 0 : 384) + piece * 64 + relative square
+//
+// file layout, little endian:
+//   "RCNN", u32 version, u32 hidden size
+//   i16 feature weights [INPUTS][hidden], i16 feature biases [hidden]
+//   i16 output weights [2 * hidden] (side to move first), i32 output bias
+const MAGIC: &[u8; 4] = b"RCNN";
+const VERSION: u32 = 1;
+
+pub const KING_BUCKETS: usize = 4;
+pub const INPUTS: usize = KING_BUCKETS * 768;
+
+// the hidden layer is clipped to 0..QA, the output weights are scaled by QB
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+// a position has at most this many pieces, so at most this many features are active in an accumulator
+const MAX_ACTIVE: usize = 32;
+
+#[derive(Debug)]
+pub struct Network{
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32
+}
+
+// the network new searches use, none when the handcrafted evaluation is used
+static CURRENT_NETWORK: LazyLock<RwLock<Option<Arc<Network>>>> = LazyLock::new(|| RwLock::new(None));
+
+pub fn current() -> Option<Arc<Network>>{
+    CURRENT_NETWORK.read().unwrap().clone()
+}
+
+pub fn set_current(network: Option<Network>){
+    *CURRENT_NETWORK.write().unwrap() = network.map(Arc::new);
+}
+
+fn read_i16s(bytes: &[u8], offset: &mut usize, count: usize) -> Vec<i16>{
+    let values = bytes[*offset..*offset + 2 * count].chunks_exact(2).map(|x| i16::from_le_bytes([x[0], x[1]])).collect();
+    *offset += 2 * count;
+    values
+}
+
+impl Network{
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, String>{
+        if bytes.len() < 12 || &bytes[..4] != MAGIC{
+            return Err("not a network file".to_string());
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != VERSION{
+            return Err(format!("unsupported network version {}", version));
+        }
+        let hidden = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        let expected = 12 + 2 * (INPUTS * hidden + hidden + 2 * hidden) + 4;
+        if hidden == 0 || bytes.len() != expected{
+            return Err(format!("expected {} bytes for a hidden layer of {}, got {}", expected, hidden, bytes.len()));
+        }
+        let mut offset = 12;
+        let feature_weights = read_i16s(bytes, &mut offset, INPUTS * hidden);
+        let feature_biases = read_i16s(bytes, &mut offset, hidden);
+        let output_weights = read_i16s(bytes, &mut offset, 2 * hidden);
+        let output_bias = i32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let network = Network{hidden, feature_weights, feature_biases, output_weights, output_bias};
+        if let Some(neuron) = network.overflowing_neuron(){
+            return Err(format!("hidden neuron {} can leave the i16 range of the accumulator", neuron));
+        }
+        Ok(network)
+    }
+
+    // the accumulator holds the bias and the weights of at most MAX_ACTIVE features, a neuron whose bias and
+    // largest weights in either direction don't fit in an i16 could overflow in some position
+    fn overflowing_neuron(&self) -> Option<usize>{
+        (0..self.hidden).find(|&neuron|{
+            let mut weights: Vec<i32> = (0..INPUTS).map(|feature| self.feature_weights[feature * self.hidden + neuron] as i32).collect();
+            weights.sort_unstable();
+            let bias = self.feature_biases[neuron] as i32;
+            let lowest = bias + weights.iter().take(MAX_ACTIVE).filter(|x| **x < 0).sum::<i32>();
+            let highest = bias + weights.iter().rev().take(MAX_ACTIVE).filter(|x| **x > 0).sum::<i32>();
+            lowest < i16::MIN as i32 || highest > i16::MAX as i32
+        })
+    }
+
+    pub fn load(path: &str) -> io::Result<Network>{
+        Network::from_bytes(&fs::read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn weights(&self, feature: usize) -> &[i16]{
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+
+    // the score of the side to move in centipawns
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> i32{
+        let us = &accumulator.values[side_to_move.to_index()];
+        let them = &accumulator.values[(!side_to_move).to_index()];
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        // every term is up to QA * 2^15, an i32 sum of 2 * hidden of them only holds small hidden layers
+        let mut output: i64 = 0;
+        for i in 0..self.hidden{
+            output += (us[i] as i64).clamp(0, QA as i64) * our_weights[i] as i64;
+            output += (them[i] as i64).clamp(0, QA as i64) * their_weights[i] as i64;
+        }
+        // the score stays below the tablebase wins and the mates
+        let score = (output + self.output_bias as i64) * SCALE as i64 / (QA * QB) as i64;
+        score.clamp(-(constants::TB_WIN_SCORE - 1) as i64, (constants::TB_WIN_SCORE - 1) as i64) as i32
+    }
+}
+
+fn king_bucket(relative_king: Square) -> usize{
+    (relative_king.get_rank().to_index() >= 2) as usize * 2 + (relative_king.get_file().to_index() >= 4) as usize
+}
+
+fn feature(perspective: Color, bucket: usize, color: Color, piece: Piece, square: Square) -> usize{
+    bucket * 768 + (color != perspective) as usize * 384 + piece.to_index() * 64 + relative_square(square, perspective).to_index()
+}
+
+fn bucket_of(board: &Board, perspective: Color) -> usize{
+    king_bucket(relative_square(board.king_square(perspective), perspective))
+}
+
+fn pieces(board: &Board, color: Color, piece: Piece) -> BitBoard{
+    *board.pieces(piece) & *board.color_combined(color)
+}
+
+// the hidden layer of both sides, indexed by color
+#[derive(Debug, Clone)]
+pub struct Accumulator{
+    values: [Vec<i16>; 2]
+}
+
+impl Accumulator{
+    pub fn new(network: &Network, board: &Board) -> Accumulator{
+        let mut accumulator = Accumulator{values: [network.feature_biases.clone(), network.feature_biases.clone()]};
+        accumulator.refresh(network, board, Color::White);
+        accumulator.refresh(network, board, Color::Black);
+        accumulator
+    }
+
+    fn refresh(&mut self, network: &Network, board: &Board, perspective: Color){
+        let values = &mut self.values[perspective.to_index()];
+        values.copy_from_slice(&network.feature_biases);
+        let bucket = bucket_of(board, perspective);
+        for color in [Color::White, Color::Black]{
+            for piece in ALL_PIECES{
+                for square in pieces(board, color, piece){
+                    add(values, network.weights(feature(perspective, bucket, color, piece, square)));
+                }
+            }
+        }
+    }
+
+    // the accumulator of after from the accumulator of before, only the pieces that changed are updated.
+    // a side whose king moved to another bucket is computed again from scratch
+    pub fn update_from(&mut self, network: &Network, previous: &Accumulator, before: &Board, after: &Board){
+        for perspective in [Color::White, Color::Black]{
+            let bucket = bucket_of(after, perspective);
+            if bucket != bucket_of(before, perspective){
+                self.refresh(network, after, perspective);
+                continue;
+            }
+            let values = &mut self.values[perspective.to_index()];
+            values.copy_from_slice(&previous.values[perspective.to_index()]);
+            for color in [Color::White, Color::Black]{
+                for piece in ALL_PIECES{
+                    let old = pieces(before, color, piece);
+                    let new = pieces(after, color, piece);
+                    for square in old & !new{
+                        sub(values, network.weights(feature(perspective, bucket, color, piece, square)));
+                    }
+                    for square in new & !old{
+                        add(values, network.weights(feature(perspective, bucket, color, piece, square)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// from_bytes only accepts networks whose accumulators can't overflow
+fn add(values: &mut [i16], weights: &[i16]){
+    for (value, weight) in values.iter_mut().zip(weights){
+        *value += *weight;
+    }
+}
+
+fn sub(values: &mut [i16], weights: &[i16]){
+    for (value, weight) in values.iter_mut().zip(weights){
+        *value -= *weight;
+    }
+}
+
+// the accumulators of the positions between the root and the current node. the search pushes every position it
+// makes a move to and pops it when it takes the move back, the buffers of popped positions are reused
+#[derive(Debug)]
+pub struct NnueStack{
+    network: Arc<Network>,
+    boards: Vec<Board>,
+    accumulators: Vec<Accumulator>
+}
+
+impl NnueStack{
+    pub fn new(network: Arc<Network>) -> NnueStack{
+        NnueStack{network, boards: Vec::new(), accumulators: Vec::new()}
+    }
+
+    pub fn push(&mut self, board: &Board){
+        let top = self.boards.len();
+        if top == 0{
+            let accumulator = Accumulator::new(&self.network, board);
+            if self.accumulators.is_empty(){
+                self.accumulators.push(accumulator);
+            }else{
+                self.accumulators[0] = accumulator;
+            }
+        }else{
+            if self.accumulators.len() == top{
+                let copy = self.accumulators[top - 1].clone();
+                self.accumulators.push(copy);
+            }
+            let (done, rest) = self.accumulators.split_at_mut(top);
+            rest[0].update_from(&self.network, &done[top - 1], &self.boards[top - 1], board);
+        }
+        self.boards.push(*board);
+    }
+
+    pub fn pop(&mut self){
+        self.boards.pop();
+    }
+
+    // the score of the side to move, positions that were not pushed are computed from scratch
+    pub fn evaluate(&self, board: &Board) -> i32{
+        match self.boards.last(){
+            Some(top) if top.get_hash() == board.get_hash()=>{
+                self.network.evaluate(&self.accumulators[self.boards.len() - 1], board.side_to_move())
+            },
+            _=>self.network.evaluate(&Accumulator::new(&self.network, board), board.side_to_move())
+        }
+    }
+}
@@ -6,8 +6,8 @@ use std::io::{self, Read};
 use std::collections::HashMap;
 use std::str::FromStr;
 
-pub const FILE_NAMES: &'static [char]  = &['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
-pub const RANK_NAMES: &'static [char]  = &['1', '2', '3', '4', '5', '6', '7', '8'];
+pub const FILE_NAMES: &[char]  = &['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+pub const RANK_NAMES: &[char]  = &['1', '2', '3', '4', '5', '6', '7', '8'];
 
 
 #[derive(Debug, Clone, Copy)]
@@ -22,7 +22,7 @@ pub enum PromotionPiece {
 pub struct Move(u16);
 
 #[derive(Debug, Clone, Copy)]
-#[repr(packed)]
+#[repr(Rust, packed)]
 pub struct PolyglotEntry {
     pub key: u64,
     pub move_: Move,
@@ -73,15 +73,14 @@ impl Move {
 }
 pub fn convert_str_to_move(s: &str) -> Move {
     let mut move_ = Move(0);
-    let mut i = 0;
-    for c in s.chars() {
+    for (i, c) in s.chars().enumerate() {
         match c {
             'a'..='h' => {
-                move_.0 |= ((c as u8 - 'a' as u8) as u16) << (i * 3);
+                move_.0 |= ((c as u8 - b'a') as u16) << (i * 3);
                 // println!("{:b}", (c as u8 - 'a' as u8) as u16);
                 },
             '1'..='8' => {
-                move_.0 |= ((c as u8 - '1' as u8) as u16) << (i * 3);
+                move_.0 |= ((c as u8 - b'1') as u16) << (i * 3);
                 // println!("{:b}", (c as u8 - '1' as u8) as u16);
             },
             'N' => move_.0 |= 0b0001_0000_0000_0000,
@@ -90,12 +89,13 @@ pub fn convert_str_to_move(s: &str) -> Move {
             'Q' => move_.0 |= 0b1000_0000_0000_0000,
             _ => panic!("invalid move"),
         }
-        i += 1;
         // println!("{:b}", move_.0);
     }
     move_
 }
 
+// the caller must make sure every byte of the value is initialized
+#[allow(clippy::missing_safety_doc)]
 pub unsafe fn any_as_u8_slice<T: Sized>(p: &T) -> &[u8] {
     ::std::slice::from_raw_parts(
         (p as *const T) as *const u8,
@@ -182,10 +182,11 @@ pub fn read_polyglot_book<R: Read>(mut reader: R) -> io::Result<HashMap<u64, Vec
                     learn: u32::from_be_bytes(buf[12..16].try_into().unwrap()),
                 };
                 // if the key is already in the map, append the entry to the vector
-                if let Some(entries) = entries.get_mut(&entry.key) {
+                let key = entry.key;
+                if let Some(entries) = entries.get_mut(&key) {
                     entries.push(entry);
                 } else {
-                    entries.insert(entry.key, vec![entry]);
+                    entries.insert(key, vec![entry]);
                 }
                 
             }
@@ -205,7 +206,8 @@ pub fn write_entry<W: std::io::Write>(mut writer: W, entry: &PolyglotEntry) -> i
     let bytes = &entry.key.to_be_bytes();
     writer.write_all(bytes).unwrap();
     
-    let bytes = &entry.move_.value().to_be_bytes();
+    let move_ = entry.move_;
+    let bytes = &move_.value().to_be_bytes();
     writer.write_all(bytes).unwrap();
     
     let bytes = &entry.weight.to_be_bytes();
@@ -226,7 +228,6 @@ pub fn write_entrys(){
     let _ = std::fs::File::create("book.bin").expect("Something went wrong opening the file");
     
     let file = std::fs::OpenOptions::new()
-    .write(true)
     .append(true)
     .open("book.bin")
     .unwrap();
@@ -0,0 +1,415 @@
+use std::fs;
+use std::io;
+use std::sync::{Arc, LazyLock, RwLock};
+use crate::constants::{self, Score};
+
+// every weight of the evaluation. the defaults are the hand picked values of constants.rs,
+// the tables that hold fractions there are kept in percent here so every weight is an integer
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalParams{
+    pub pawn_val: Score,
+    pub knight_val: Score,
+    pub bishop_val: Score,
+    pub rook_val: Score,
+    pub queen_val: Score,
+
+    pub bishop_pair: Score,
+    pub rook_on_open_file: Score,
+    pub rook_on_half_open_file: Score,
+    pub rook_attacking_open_king_zone: Score,
+    pub rook_attacking_half_open_king_zone: Score,
+
+    // indexed by the number of squares the piece can go to
+    pub knight_mobility: [Score; 9],
+    pub bishop_mobility: [Score; 14],
+    pub rook_mobility: [Score; 15],
+    pub queen_mobility: [Score; 28],
+
+    pub doubled_pawns: Score,
+    pub isolated_pawns: Score,
+    pub backward_pawns: Score,
+    pub unstoppable_pawn: Score,
+    pub passed_pawn: [Score; 8],
+    pub passed_their_king_distance: Score,
+    pub passed_our_king_distance: Score,
+    pub passed_free_path: Score,
+    pub passed_safe_path: Score,
+    pub passed_blockaded: Score,
+    pub rook_behind_passer: Score,
+    // indexed by the rank of the pawn from its own side
+    pub supported_pawn: [Score; 8],
+    pub phalanx_pawn: [Score; 8],
+    pub candidate_passed_pawn: [Score; 8],
+    pub pawn_lever: Score,
+    pub blocked_pawn: Score,
+    pub pawn_hole: Score,
+    pub pawn_island: Score,
+
+    pub bad_bishop: Score,
+    pub bishop_outpost: Score,
+    pub long_diagonal_bishop: Score,
+    pub fianchetto: Score,
+    pub trapped_bishop: Score,
+    pub knight_king_distance: Score,
+
+    pub rook_on_seventh: Score,
+    pub doubled_rooks: Score,
+    pub connected_rooks: Score,
+    pub trapped_rook: Score,
+    pub early_queen: Score,
+    pub queen_minor_threat: Score,
+
+    pub blocked_bishop: i32,
+    pub blocked_rook: i32,
+    pub queen_tropism: i32,
+
+    // king safety: the attack units of every piece for each square of the king ring it attacks,
+    // scaled by the number of attackers, and the units of a safe check by every piece
+    pub king_attack_weight: [i32; 6],
+    pub king_attackers_percent: [i32; 24],
+    pub safe_check: [i32; 6],
+    pub king_weak_square: i32,
+    pub king_semi_open_file: i32,
+    pub king_open_file: i32,
+    // the penalty for the number of attack units
+    pub king_danger: [i32; 100],
+
+    // threats, indexed by the threatened piece
+    pub hanging_piece: [Score; 6],
+    pub threat_by_lesser: [Score; 6],
+    pub pawn_push_threat: Score,
+    pub multiple_threats: Score,
+
+    // space in the opening, counted in safe squares times minor pieces out of 16, and the attacked squares of the centre
+    pub space_weight: i32,
+    pub centre_control: Score,
+    pub extended_centre_control: Score,
+
+    // opening only, indexed by the rank of the pawn from its own side
+    pub pawn_storm: [i32; 8],
+    pub pawn_shelter: [i32; 8],
+
+    // how much of the bonuses that depend on the position of a passed pawn it gets on every rank
+    pub passed_pawn_percent: [i32; 8],
+
+    pub pawn_pst: [i32; 64],
+    pub knight_pst: [i32; 64],
+    pub bishop_pst: [i32; 64],
+    pub rook_pst: [i32; 64],
+    pub queen_pst: [i32; 64],
+    pub king_pst: [i32; 64],
+    pub king_endgame_pst: [i32; 64],
+    pub knight_outpost: [i32; 64]
+}
+
+pub static DEFAULT_PARAMS: LazyLock<EvalParams> = LazyLock::new(EvalParams::default);
+
+// the parameters new searches are started with, replaced by the --evalfile flag and the EvalFile option
+static CURRENT_PARAMS: LazyLock<RwLock<Arc<EvalParams>>> = LazyLock::new(|| RwLock::new(Arc::new(EvalParams::default())));
+
+pub fn current() -> Arc<EvalParams>{
+    CURRENT_PARAMS.read().unwrap().clone()
+}
+
+pub fn set_current(params: EvalParams){
+    *CURRENT_PARAMS.write().unwrap() = Arc::new(params);
+}
+
+fn table<T: Copy + Default, const N: usize>(values: &[T]) -> [T; N]{
+    let mut table = [T::default(); N];
+    table.copy_from_slice(values);
+    table
+}
+
+fn percent<const N: usize>(values: &[f32]) -> [i32; N]{
+    let mut table = [0; N];
+    for (entry, value) in table.iter_mut().zip(values){
+        *entry = (value * 100.).round() as i32;
+    }
+    table
+}
+
+impl Default for EvalParams{
+    fn default() -> EvalParams{
+        EvalParams{
+            pawn_val: constants::PAWN_VAL,
+            knight_val: constants::KNIGHT_VAL,
+            bishop_val: constants::BISHOP_VAL,
+            rook_val: constants::ROOK_VAL,
+            queen_val: constants::QUEEN_VAL,
+
+            bishop_pair: constants::BISHOP_PAIR,
+            rook_on_open_file: constants::ROOK_ON_OPEN_FILE,
+            rook_on_half_open_file: constants::ROOK_ON_HALF_OPEN_FILE,
+            rook_attacking_open_king_zone: constants::ROOK_ATTACKING_OPEN_KING_ZONE,
+            rook_attacking_half_open_king_zone: constants::ROOK_ATTACKING_HALF_OPEN_KING_ZONE,
+
+            knight_mobility: table(constants::KNIGHT_MOBILITY),
+            bishop_mobility: table(constants::BISHOP_MOBILITY),
+            rook_mobility: table(constants::ROOK_MOBILITY),
+            queen_mobility: table(constants::QUEEN_MOBILITY),
+
+            doubled_pawns: constants::DOUBLED_PAWNS_DEBUFF,
+            isolated_pawns: constants::ISOLATED_PAWNS_DEBUFF,
+            backward_pawns: constants::BACKWARD_PAWNS_DEBUFF,
+            unstoppable_pawn: constants::UNSTOPPABLE_PAWN,
+            passed_pawn: table(constants::PASSED_PAWN),
+            passed_their_king_distance: constants::PASSED_THEIR_KING_DISTANCE,
+            passed_our_king_distance: constants::PASSED_OUR_KING_DISTANCE,
+            passed_free_path: constants::PASSED_FREE_PATH,
+            passed_safe_path: constants::PASSED_SAFE_PATH,
+            passed_blockaded: constants::PASSED_BLOCKADED,
+            rook_behind_passer: constants::ROOK_BEHIND_PASSER,
+            supported_pawn: table(constants::SUPPORTED_PAWN),
+            phalanx_pawn: table(constants::PHALANX_PAWN),
+            candidate_passed_pawn: table(constants::CANDIDATE_PASSED_PAWN),
+            pawn_lever: constants::PAWN_LEVER,
+            blocked_pawn: constants::BLOCKED_PAWN,
+            pawn_hole: constants::PAWN_HOLE,
+            pawn_island: constants::PAWN_ISLAND,
+
+            bad_bishop: constants::BAD_BISHOP,
+            bishop_outpost: constants::BISHOP_OUTPOST,
+            long_diagonal_bishop: constants::LONG_DIAGONAL_BISHOP,
+            fianchetto: constants::FIANCHETTO,
+            trapped_bishop: constants::TRAPPED_BISHOP,
+            knight_king_distance: constants::KNIGHT_KING_DISTANCE,
+
+            rook_on_seventh: constants::ROOK_ON_SEVENTH,
+            doubled_rooks: constants::DOUBLED_ROOKS,
+            connected_rooks: constants::CONNECTED_ROOKS,
+            trapped_rook: constants::TRAPPED_ROOK,
+            early_queen: constants::EARLY_QUEEN,
+            queen_minor_threat: constants::QUEEN_MINOR_THREAT,
+
+            blocked_bishop: -50,
+            blocked_rook: -50,
+            queen_tropism: 10,
+
+            king_attack_weight: [0, 2, 2, 3, 5, 0],
+            king_attackers_percent: percent(constants::PIECES_ATTACKING_KING),
+            safe_check: [0, 4, 3, 5, 6, 0],
+            king_weak_square: 2,
+            king_semi_open_file: 1,
+            king_open_file: 3,
+            king_danger: table(constants::KING_DANGER),
+
+            hanging_piece: table(constants::HANGING_PIECE),
+            threat_by_lesser: table(constants::THREAT_BY_LESSER),
+            pawn_push_threat: constants::PAWN_PUSH_THREAT,
+            multiple_threats: constants::MULTIPLE_THREATS,
+
+            space_weight: constants::SPACE_WEIGHT,
+            centre_control: constants::CENTRE_CONTROL,
+            extended_centre_control: constants::EXTENDED_CENTRE_CONTROL,
+
+            pawn_storm: [0, 0, 0, -10, -30, -60, 0, 0],
+            // 36 - (8 - rank)^2 for the pawns that left the second rank
+            pawn_shelter: [0, 0, 0, -11, -20, -27, -32, -35],
+
+            passed_pawn_percent: percent(constants::PASSED_PAWNS_WHITE_OPENING),
+
+            pawn_pst: table(constants::PAWN_SQUARES_TABLE),
+            knight_pst: table(constants::KNIGHT_SQUARES_TABLE),
+            bishop_pst: table(constants::BISHOP_SQUARES_TABLE),
+            rook_pst: table(constants::ROOK_SQUARES_TABLE),
+            queen_pst: table(constants::QUEEN_SQUARES_TABLE),
+            king_pst: table(constants::KING_SQUARES_TABLE),
+            king_endgame_pst: table(constants::KING_ENDGAME_TABLE),
+            knight_outpost: table(constants::KNIGHT_OUTPOST_TABLE_WHITE)
+        }
+    }
+}
+
+// calls the visitor with the opening and endgame values of the score
+fn visit_score(name: &'static str, score: &mut Score, visitor: &mut dyn FnMut(&'static str, &mut [i32])){
+    let mut values = [score.0, score.1];
+    visitor(name, &mut values);
+    *score = (values[0], values[1]);
+}
+
+// a table of scores is visited as one list, the opening and endgame value of every entry after each other
+fn visit_scores(name: &'static str, scores: &mut [Score], visitor: &mut dyn FnMut(&'static str, &mut [i32])){
+    let mut values: Vec<i32> = scores.iter().flat_map(|score| [score.0, score.1]).collect();
+    visitor(name, &mut values);
+    for (score, value) in scores.iter_mut().zip(values.chunks_exact(2)){
+        *score = (value[0], value[1]);
+    }
+}
+
+impl EvalParams{
+    // walks over every weight in a fixed order, one call for every named field.
+    // this order is the order of the parameter vector and of the lines of a parameter file
+    pub fn visit(&mut self, visitor: &mut dyn FnMut(&'static str, &mut [i32])){
+        visit_score("pawn_val", &mut self.pawn_val, visitor);
+        visit_score("knight_val", &mut self.knight_val, visitor);
+        visit_score("bishop_val", &mut self.bishop_val, visitor);
+        visit_score("rook_val", &mut self.rook_val, visitor);
+        visit_score("queen_val", &mut self.queen_val, visitor);
+
+        visit_score("bishop_pair", &mut self.bishop_pair, visitor);
+        visit_score("rook_on_open_file", &mut self.rook_on_open_file, visitor);
+        visit_score("rook_on_half_open_file", &mut self.rook_on_half_open_file, visitor);
+        visit_score("rook_attacking_open_king_zone", &mut self.rook_attacking_open_king_zone, visitor);
+        visit_score("rook_attacking_half_open_king_zone", &mut self.rook_attacking_half_open_king_zone, visitor);
+
+        visit_scores("knight_mobility", &mut self.knight_mobility, visitor);
+        visit_scores("bishop_mobility", &mut self.bishop_mobility, visitor);
+        visit_scores("rook_mobility", &mut self.rook_mobility, visitor);
+        visit_scores("queen_mobility", &mut self.queen_mobility, visitor);
+
+        visit_score("doubled_pawns", &mut self.doubled_pawns, visitor);
+        visit_score("isolated_pawns", &mut self.isolated_pawns, visitor);
+        visit_score("backward_pawns", &mut self.backward_pawns, visitor);
+        visit_score("unstoppable_pawn", &mut self.unstoppable_pawn, visitor);
+        visit_scores("passed_pawn", &mut self.passed_pawn, visitor);
+        visit_score("passed_their_king_distance", &mut self.passed_their_king_distance, visitor);
+        visit_score("passed_our_king_distance", &mut self.passed_our_king_distance, visitor);
+        visit_score("passed_free_path", &mut self.passed_free_path, visitor);
+        visit_score("passed_safe_path", &mut self.passed_safe_path, visitor);
+        visit_score("passed_blockaded", &mut self.passed_blockaded, visitor);
+        visit_score("rook_behind_passer", &mut self.rook_behind_passer, visitor);
+        visit_scores("supported_pawn", &mut self.supported_pawn, visitor);
+        visit_scores("phalanx_pawn", &mut self.phalanx_pawn, visitor);
+        visit_scores("candidate_passed_pawn", &mut self.candidate_passed_pawn, visitor);
+        visit_score("pawn_lever", &mut self.pawn_lever, visitor);
+        visit_score("blocked_pawn", &mut self.blocked_pawn, visitor);
+        visit_score("pawn_hole", &mut self.pawn_hole, visitor);
+        visit_score("pawn_island", &mut self.pawn_island, visitor);
+
+        visit_score("bad_bishop", &mut self.bad_bishop, visitor);
+        visit_score("bishop_outpost", &mut self.bishop_outpost, visitor);
+        visit_score("long_diagonal_bishop", &mut self.long_diagonal_bishop, visitor);
+        visit_score("fianchetto", &mut self.fianchetto, visitor);
+        visit_score("trapped_bishop", &mut self.trapped_bishop, visitor);
+        visit_score("knight_king_distance", &mut self.knight_king_distance, visitor);
+
+        visit_score("rook_on_seventh", &mut self.rook_on_seventh, visitor);
+        visit_score("doubled_rooks", &mut self.doubled_rooks, visitor);
+        visit_score("connected_rooks", &mut self.connected_rooks, visitor);
+        visit_score("trapped_rook", &mut self.trapped_rook, visitor);
+        visit_score("early_queen", &mut self.early_queen, visitor);
+        visit_score("queen_minor_threat", &mut self.queen_minor_threat, visitor);
+
+        visitor("blocked_bishop", std::slice::from_mut(&mut self.blocked_bishop));
+        visitor("blocked_rook", std::slice::from_mut(&mut self.blocked_rook));
+        visitor("queen_tropism", std::slice::from_mut(&mut self.queen_tropism));
+
+        visitor("king_attack_weight", &mut self.king_attack_weight);
+        visitor("king_attackers_percent", &mut self.king_attackers_percent);
+        visitor("safe_check", &mut self.safe_check);
+        visitor("king_weak_square", std::slice::from_mut(&mut self.king_weak_square));
+        visitor("king_semi_open_file", std::slice::from_mut(&mut self.king_semi_open_file));
+        visitor("king_open_file", std::slice::from_mut(&mut self.king_open_file));
+        visitor("king_danger", &mut self.king_danger);
+
+        visit_scores("hanging_piece", &mut self.hanging_piece, visitor);
+        visit_scores("threat_by_lesser", &mut self.threat_by_lesser, visitor);
+        visit_score("pawn_push_threat", &mut self.pawn_push_threat, visitor);
+        visit_score("multiple_threats", &mut self.multiple_threats, visitor);
+
+        visitor("space_weight", std::slice::from_mut(&mut self.space_weight));
+        visit_score("centre_control", &mut self.centre_control, visitor);
+        visit_score("extended_centre_control", &mut self.extended_centre_control, visitor);
+
+        visitor("pawn_storm", &mut self.pawn_storm);
+        visitor("pawn_shelter", &mut self.pawn_shelter);
+        visitor("passed_pawn_percent", &mut self.passed_pawn_percent);
+
+        visitor("pawn_pst", &mut self.pawn_pst);
+        visitor("knight_pst", &mut self.knight_pst);
+        visitor("bishop_pst", &mut self.bishop_pst);
+        visitor("rook_pst", &mut self.rook_pst);
+        visitor("queen_pst", &mut self.queen_pst);
+        visitor("king_pst", &mut self.king_pst);
+        visitor("king_endgame_pst", &mut self.king_endgame_pst);
+        visitor("knight_outpost", &mut self.knight_outpost);
+    }
+
+    // all the weights as one flat vector
+    pub fn to_vector(&self) -> Vec<i32>{
+        let mut vector = Vec::new();
+        self.clone().visit(&mut |_, values| vector.extend_from_slice(values));
+        vector
+    }
+
+    pub fn from_vector(&mut self, vector: &[i32]){
+        let mut index = 0;
+        self.visit(&mut |_, values| {
+            values.copy_from_slice(&vector[index..index + values.len()]);
+            index += values.len();
+        });
+    }
+
+    // the name of every entry of the vector, tables get the index of the entry
+    pub fn vector_names(&self) -> Vec<String>{
+        let mut names = Vec::new();
+        self.clone().visit(&mut |name, values| {
+            if values.len() <= 2{
+                names.extend(["mg", "eg"].iter().take(values.len()).map(|phase| format!("{} {}", name, phase)));
+            }else{
+                names.extend((0..values.len()).map(|index| format!("{}[{}]", name, index)));
+            }
+        });
+        names
+    }
+
+    // one line for every field: the name followed by its values
+    pub fn dump(&self) -> String{
+        let mut dump = String::new();
+        self.clone().visit(&mut |name, values| {
+            let values: Vec<String> = values.iter().map(|x| x.to_string()).collect();
+            dump.push_str(&format!("{} {}\n", name, values.join(" ")));
+        });
+        dump
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()>{
+        fs::write(path, self.dump())
+    }
+
+    // reads the format written by dump. the fields that are not in the text keep their default value,
+    // an optional '=' can follow the name and everything after a '#' is a comment
+    pub fn parse(text: &str) -> Result<EvalParams, String>{
+        let mut params = EvalParams::default();
+        for (number, line) in text.lines().enumerate(){
+            let line = line.split('#').next().unwrap_or("").replace('=', " ");
+            let mut tokens = line.split_whitespace();
+            let name = match tokens.next(){
+                None=>continue,
+                Some(name)=>name
+            };
+            let mut values = Vec::new();
+            for token in tokens{
+                match token.parse::<i32>(){
+                    Ok(value)=>values.push(value),
+                    Err(_)=>return Err(format!("line {}: invalid value {}", number + 1, token))
+                }
+            }
+            let mut found = false;
+            let mut error = None;
+            params.visit(&mut |field, entries| {
+                if field != name{
+                    return;
+                }
+                found = true;
+                if entries.len() == values.len(){
+                    entries.copy_from_slice(&values);
+                }else{
+                    error = Some(format!("line {}: {} takes {} values, got {}", number + 1, name, entries.len(), values.len()));
+                }
+            });
+            if let Some(error) = error{
+                return Err(error);
+            }
+            if !found{
+                return Err(format!("line {}: unknown parameter {}", number + 1, name));
+            }
+        }
+        Ok(params)
+    }
+
+    pub fn read(path: &str) -> io::Result<EvalParams>{
+        EvalParams::parse(&fs::read_to_string(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
@@ -0,0 +1,110 @@
+use chess::{BitBoard, Board, Color, File, Piece};
+use crate::bitboard_operators;
+use crate::constants::{Access, Score};
+use crate::evaluation;
+use crate::params::EvalParams;
+use crate::utils::{get_piece_type, relative_square};
+
+// a zobrist key of the pawns only, so positions with the same pawns share their pawn hash entry.
+// the random numbers come from a fixed xorshift sequence
+const fn pawn_zobrist() -> [[u64; 64]; 2]{
+    let mut keys = [[0; 64]; 2];
+    let mut state: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 128{
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        keys[i / 64][i % 64] = state;
+        i += 1;
+    }
+    keys
+}
+
+static PAWN_ZOBRIST: [[u64; 64]; 2] = pawn_zobrist();
+
+pub fn pawn_key(board: &Board) -> u64{
+    let mut key = 0;
+    for color in [Color::White, Color::Black]{
+        for square in get_piece_type(board, Piece::Pawn, color){
+            key ^= PAWN_ZOBRIST[color.to_index()][square.to_index()];
+        }
+    }
+    key
+}
+
+// the key after a move from the key before it, only the pawns that moved, were taken or promoted change it
+pub fn update_pawn_key(key: u64, before: &Board, after: &Board) -> u64{
+    let mut key = key;
+    for color in [Color::White, Color::Black]{
+        let changed = get_piece_type(before, Piece::Pawn, color) ^ get_piece_type(after, Piece::Pawn, color);
+        for square in changed{
+            key ^= PAWN_ZOBRIST[color.to_index()][square.to_index()];
+        }
+    }
+    key
+}
+
+// everything the evaluation knows about the pawns, the arrays are indexed by color
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct PawnEntry{
+    // the pawn structure score of both sides
+    pub structure: [Score; 2],
+    pub passed: [BitBoard; 2],
+    pub attacks: [BitBoard; 2],
+    // the squares the pawns can ever attack
+    pub attack_spans: [BitBoard; 2],
+    // the files without pawns of the side
+    pub semi_open_files: [BitBoard; 2],
+    // opening only: the shelter of the own pawns and the storm of the enemy pawns for a king on every file
+    pub shelter: [[i32; 8]; 2],
+    pub storm: [[i32; 8]; 2]
+}
+
+impl PawnEntry{
+    pub fn new(board: &Board, params: &EvalParams) -> PawnEntry{
+        let pawns = [get_piece_type(board, Piece::Pawn, Color::White), get_piece_type(board, Piece::Pawn, Color::Black)];
+        let (white_structure, black_structure) = evaluation::pawn_structure_sides(pawns[1], pawns[0], params);
+        let mut entry = PawnEntry{structure: [white_structure, black_structure], ..PawnEntry::default()};
+
+        for color in [Color::White, Color::Black]{
+            let us = color.to_index();
+            let them = (!color).to_index();
+            entry.passed[us] = bitboard_operators::passed_pawns(pawns[us], pawns[them], color);
+            entry.attacks[us] = bitboard_operators::pawn_any_attacks(pawns[us], color);
+            entry.attack_spans[us] = bitboard_operators::pawn_attack_spans(pawns[us], color);
+            entry.semi_open_files[us] = bitboard_operators::half_open_files(pawns[us]);
+
+            // pawns on the file of the king or next to it
+            for king_file in 0..8{
+                let files = king_files(king_file);
+                for pawn in pawns[us] & files{
+                    entry.shelter[us][king_file] += params.pawn_shelter[relative_square(pawn, color).get_rank().to_index()];
+                }
+                for pawn in pawns[them] & files{
+                    entry.storm[us][king_file] += params.pawn_storm[relative_square(pawn, !color).get_rank().to_index()];
+                }
+            }
+        }
+        entry
+    }
+
+    // the pawn structure from the side to move's point of view, blended with the phase of the position
+    pub fn score(&self, board: &Board) -> i32{
+        let white = self.structure[Color::White.to_index()];
+        let black = self.structure[Color::Black.to_index()];
+        let score = (white.0 - black.0, white.1 - black.1).taper(evaluation::game_phase(board));
+        match board.side_to_move(){
+            Color::White=>score,
+            Color::Black=>-score
+        }
+    }
+}
+
+fn king_files(king_file: usize) -> BitBoard{
+    let mut files = chess::EMPTY;
+    for file in king_file.saturating_sub(1)..=(king_file + 1).min(7){
+        files |= chess::get_file(File::from_index(file));
+    }
+    files
+}
@@ -1,7 +1,7 @@
-use chess::{self, Board, ChessMove, Piece, Square, BitBoard, Color};
-use crate::{evaluation, constants::{self, Access}, utils::get_piece_type};
-use std::{time::Instant, io::{self, Write}, hash::{Hash, Hasher}};
-use std::collections::hash_map::DefaultHasher;
+use chess::{self, Board, ChessMove, Piece, Square, Color};
+use crate::{evaluation, constants::{self, Access}, history::{self, History}, trace::Tracer, draw::DrawTracker, params::{self, EvalParams}, nnue::{self, NnueStack}, endgame, pawns::{self, PawnEntry}, material::MaterialPst, eval_cache::EvalCache, syzygy::{self, Tablebases, Wdl}, bitbase};
+use std::{time::Instant, io::{self, Write}};
+use std::sync::{Arc, atomic::{AtomicBool, Ordering as AtomicOrdering}};
 use std::cmp::Ordering;
 
 #[derive(Clone, Copy, PartialEq, PartialOrd)]
@@ -11,32 +11,355 @@ pub enum Nodetype {
     AllNode
 }
 
+// the limits are polled once every CHECK_NODES_MASK + 1 nodes
+const CHECK_NODES_MASK: u64 = 2047;
+
+#[derive(Debug, Clone)]
+pub struct SearchLimits{
+    pub deadline: Option<Instant>,
+    pub stop: Arc<AtomicBool>,
+    // looking for a mate in this many moves, the unsound pruning is turned off so a found mate is proven
+    pub mate: Option<u32>
+}
+
+impl SearchLimits{
+    pub fn new(deadline: Option<Instant>, stop: Arc<AtomicBool>) -> SearchLimits{
+        SearchLimits{deadline, stop, mate: None}
+    }
+
+    pub fn none() -> SearchLimits{
+        SearchLimits{deadline: None, stop: Arc::new(AtomicBool::new(false)), mate: None}
+    }
+
+    pub fn mate(moves: u32, stop: Arc<AtomicBool>) -> SearchLimits{
+        SearchLimits{deadline: None, stop, mate: Some(moves)}
+    }
+
+    pub fn reached(&self) -> bool{
+        if self.stop.load(AtomicOrdering::Relaxed){
+            return true;
+        }
+        match self.deadline{
+            None=>false,
+            Some(deadline)=>Instant::now() >= deadline
+        }
+    }
+}
+
+// what the searches of a game share: the tables and the history, kept from one search to the next, and the search
+// that is running with its limits, the positions played before the root and the tracer, which is only set when the
+// searched tree is written to a file
+pub struct SearchState{
+    pub cachetable: chess::CacheTable<Entry>,
+    pub pawn_table: chess::CacheTable<PawnEntry>,
+    pub eval_cache: Arc<EvalCache>,
+    pub history: History,
+    pub info: SearchInfo
+}
+
+impl Default for SearchState{
+    fn default() -> SearchState{
+        SearchState::new()
+    }
+}
+
+impl SearchState{
+    pub fn new() -> SearchState{
+        SearchState{
+            cachetable: chess::CacheTable::new(constants::HASH_ENTRIES, Entry{depth: 0, node_type: Nodetype::Pvnode, score: 0}),
+            pawn_table: chess::CacheTable::new(constants::HASH_ENTRIES, PawnEntry::default()),
+            eval_cache: Arc::new(EvalCache::new(constants::HASH_ENTRIES)),
+            history: History::new(),
+            info: SearchInfo::new()
+        }
+    }
+
+    // the weights, the network and the tablebases are taken once a search, when it starts
+    pub fn start(&mut self, limits: SearchLimits, draws: DrawTracker){
+        let mut info = SearchInfo::with_limits(limits);
+        info.draws = draws;
+        info.eval_cache = Some(self.eval_cache.clone());
+        self.info = info;
+    }
+}
+
+// what a search counted, over the root moves of an iteration or over the iterations
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchCounts{
+    pub nodes_searched: u64,
+    pub transpostions_used: u64,
+    pub transpostions_recorded: u64,
+    pub pawn_hash_table_used: u64,
+    pub pawn_hash_table_recorded: u64,
+    pub eval_hash_table_used: u64,
+    pub eval_hash_table_recorded: u64,
+    pub tb_hits: u64
+}
+
+impl SearchCounts{
+    pub fn add(&mut self, other: &SearchCounts){
+        self.nodes_searched += other.nodes_searched;
+        self.transpostions_used += other.transpostions_used;
+        self.transpostions_recorded += other.transpostions_recorded;
+        self.pawn_hash_table_used += other.pawn_hash_table_used;
+        self.pawn_hash_table_recorded += other.pawn_hash_table_recorded;
+        self.eval_hash_table_used += other.eval_hash_table_used;
+        self.eval_hash_table_recorded += other.eval_hash_table_recorded;
+        self.tb_hits += other.tb_hits;
+    }
+}
+
 #[derive(Debug)]
 pub struct SearchInfo{
     pub nodes_searched: u64,
     pub transpostions_used: u64,
     pub transpostions_recorded: u64,
     pub pawn_hash_table_used: u64,
-    pub pawn_hash_table_recorded: u64
+    pub pawn_hash_table_recorded: u64,
+    pub eval_hash_table_used: u64,
+    pub eval_hash_table_recorded: u64,
+    pub tb_hits: u64,
+    pub stopped: bool,
+    // the distance of the current node from the root
+    pub ply: u32,
+    pub limits: SearchLimits,
+    pub tracer: Option<Tracer>,
+    pub draws: DrawTracker,
+    // the evaluation weights of this search, taken when the search starts
+    pub params: Arc<EvalParams>,
+    // the accumulators of the network when the network evaluation is used
+    pub nnue: Option<NnueStack>,
+    // the hash and the pawn key of the positions between the root and the current node
+    pub pawn_keys: Vec<(u64, u64)>,
+    // the hash and the material and piece square values of the same positions
+    pub materials: Vec<(u64, MaterialPst)>,
+    // the static evaluations, shared by the searches of a game
+    pub eval_cache: Option<Arc<EvalCache>>,
+    // the endgame tablebases of this search, taken when the search starts
+    pub tablebases: Option<Arc<Tablebases>>
 } 
 
+impl Default for SearchInfo{
+    fn default() -> SearchInfo{
+        SearchInfo::new()
+    }
+}
+
 impl SearchInfo{
     pub fn new() -> SearchInfo{
+        SearchInfo::with_limits(SearchLimits::none())
+    }
+
+    pub fn with_limits(limits: SearchLimits) -> SearchInfo{
         SearchInfo{
             nodes_searched: 0,
             transpostions_used: 0,
             transpostions_recorded: 0,
             pawn_hash_table_used: 0,
-            pawn_hash_table_recorded: 0
+            pawn_hash_table_recorded: 0,
+            eval_hash_table_used: 0,
+            eval_hash_table_recorded: 0,
+            tb_hits: 0,
+            stopped: false,
+            ply: 0,
+            limits,
+            tracer: None,
+            draws: DrawTracker::new(&Board::default(), 0),
+            params: params::current(),
+            nnue: nnue::current().map(NnueStack::new),
+            pawn_keys: Vec::new(),
+            materials: Vec::new(),
+            eval_cache: None,
+            tablebases: syzygy::current()
+        }
+    }
+
+    // every position the search moves to goes through here so the draw detection and the network see it
+    pub fn push_move(&mut self, board: &Board, chess_move: ChessMove, new_board: &Board){
+        self.draws.push(board, chess_move, new_board);
+        if let Some(nnue) = self.nnue.as_mut(){
+            nnue.push(new_board);
+        }
+        self.push_pawn_key(board, new_board);
+        let material = self.material(board).update(&self.params, board, chess_move);
+        self.materials.push((new_board.get_hash(), material));
+    }
+
+    pub fn push_null(&mut self, new_board: &Board){
+        self.draws.push_null(new_board);
+        if let Some(nnue) = self.nnue.as_mut(){
+            nnue.push(new_board);
+        }
+        self.pawn_keys.push((new_board.get_hash(), self.pawn_key(new_board)));
+        self.materials.push((new_board.get_hash(), self.material(new_board)));
+    }
+
+    pub fn pop_move(&mut self){
+        self.draws.pop();
+        if let Some(nnue) = self.nnue.as_mut(){
+            nnue.pop();
         }
+        self.pawn_keys.pop();
+        self.materials.pop();
+    }
+
+    // the pawn key of the new position from the key of the position the move was made in
+    fn push_pawn_key(&mut self, board: &Board, new_board: &Board){
+        let key = pawns::update_pawn_key(self.pawn_key(board), board, new_board);
+        self.pawn_keys.push((new_board.get_hash(), key));
+    }
+
+    // the pawn key of the current node, a position that wasn't pushed gets its key computed from scratch
+    pub fn pawn_key(&self, board: &Board) -> u64{
+        match self.pawn_keys.last(){
+            Some((hash, key)) if *hash == board.get_hash()=>*key,
+            _=>pawns::pawn_key(board)
+        }
+    }
+
+    // the material and piece square values of the current node, computed from scratch for a position that wasn't pushed
+    pub fn material(&self, board: &Board) -> MaterialPst{
+        match self.materials.last(){
+            Some((hash, material)) if *hash == board.get_hash()=>*material,
+            _=>MaterialPst::new(board, &self.params)
+        }
+    }
+
+    // the static evaluation from the side to move's point of view, by the network when one is loaded
+    pub fn evaluate(&mut self, board: &Board, draw_score: i32, pawn_table: &mut chess::CacheTable<PawnEntry>) -> i32{
+        if let Some(cache) = self.eval_cache.clone(){
+            if let Some(score) = cache.get(board.get_hash()){
+                self.eval_hash_table_used += 1;
+                return score;
+            }
+            let score = self.evaluate_uncached(board, draw_score, pawn_table);
+            cache.add(board.get_hash(), score);
+            self.eval_hash_table_recorded += 1;
+            return score;
+        }
+        self.evaluate_uncached(board, draw_score, pawn_table)
+    }
+
+    fn evaluate_uncached(&mut self, board: &Board, draw_score: i32, pawn_table: &mut chess::CacheTable<PawnEntry>) -> i32{
+        // the known endgames and the network would score a stalemate by its material
+        if board.status() == chess::BoardStatus::Stalemate{
+            return draw_score;
+        }
+        match self.nnue.as_ref(){
+            Some(nnue)=>nnue.evaluate(board),
+            None=>{
+                // the endgames the general evaluation doesn't understand
+                if let Some(score) = endgame::evaluate(board){
+                    return score;
+                }
+                let entry = pawn_entry(board, pawn_table, self);
+                let score = evaluation::evaluate_with_pawns(board, draw_score, &self.params, &entry, &self.material(board)) + entry.score(board);
+                endgame::scale(board, score)
+            }
+        }
+    }
+
+    pub fn trace_enter(&mut self, kind: &'static str, alpha: i32, beta: i32, depth: u32){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.enter(kind, alpha, beta, depth);
+        }
+    }
+
+    pub fn trace_leave(&mut self, score: i32){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.leave(score);
+        }
+    }
+
+    pub fn trace_prune(&mut self, reason: &'static str){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.prune(reason);
+        }
+    }
+
+    pub fn trace_prune_child(&mut self, reason: &'static str){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.prune_last_child(reason);
+        }
+    }
+
+    pub fn trace_tt_hit(&mut self){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.tt_hit();
+        }
+    }
+
+    // the label is only formatted when tracing, so a move can be passed as is
+    pub fn trace_move<T: ToString>(&mut self, label: T, reduction: u32){
+        if let Some(tracer) = self.tracer.as_mut(){
+            tracer.name_last_child(label.to_string(), reduction);
+        }
+    }
+
+    // the tablebase score of the position: wins score below the mates and a longer way to the win is worse
+    pub fn probe_tablebases(&mut self, board: &Board) -> Option<i32>{
+        let wdl = self.tablebases.as_ref()?.probe_wdl(board)?;
+        self.tb_hits += 1;
+        Some(match wdl{
+            Wdl::Win=>constants::TB_WIN_SCORE - self.ply as i32,
+            Wdl::Loss=>-constants::TB_WIN_SCORE + self.ply as i32,
+            _=>self.draws.score(board)
+        })
+    }
+
+    pub fn counts(&self) -> SearchCounts{
+        SearchCounts{
+            nodes_searched: self.nodes_searched,
+            transpostions_used: self.transpostions_used,
+            transpostions_recorded: self.transpostions_recorded,
+            pawn_hash_table_used: self.pawn_hash_table_used,
+            pawn_hash_table_recorded: self.pawn_hash_table_recorded,
+            eval_hash_table_used: self.eval_hash_table_used,
+            eval_hash_table_recorded: self.eval_hash_table_recorded,
+            tb_hits: self.tb_hits
+        }
+    }
+
+    pub fn reset_counts(&mut self){
+        self.nodes_searched = 0;
+        self.transpostions_used = 0;
+        self.transpostions_recorded = 0;
+        self.pawn_hash_table_used = 0;
+        self.pawn_hash_table_recorded = 0;
+        self.eval_hash_table_used = 0;
+        self.eval_hash_table_recorded = 0;
+        self.tb_hits = 0;
+    }
+
+    // polls the deadline and the stop flag every few nodes, once the search is stopped it stays stopped
+    pub fn should_stop(&mut self) -> bool{
+        if !self.stopped && self.nodes_searched & CHECK_NODES_MASK == 0 && self.limits.reached(){
+            self.stopped = true;
+        }
+        self.stopped
+    }
+}
+
+// the score of the side to move when it is mated at the current node
+pub fn mated_score(ply: u32) -> i32{
+    -(constants::MATE_SCORE - ply as i32)
+}
+
+// the number of moves to the mate, negative when the side to move is the one getting mated
+pub fn mate_in(score: i32) -> Option<i32>{
+    if score >= constants::MATE_BOUND{
+        return Some((constants::MATE_SCORE - score + 1) / 2);
+    }else if score <= -constants::MATE_BOUND{
+        return Some(-(constants::MATE_SCORE + score) / 2);
     }
+    None
 }
 
-#[derive(Clone, Copy, PartialEq, PartialOrd, Hash, Eq)]
-pub struct PawnKey{
-    pub endgame: bool,
-    pub white_pawns: BitBoard,
-    pub black_pawns: BitBoard
+pub fn uci_score(score: i32) -> String{
+    match mate_in(score){
+        Some(moves)=>format!("mate {}", moves),
+        None=>format!("cp {}", score)
+    }
 }
 
 
@@ -49,10 +372,10 @@ pub struct Entry{
 
 
 
-pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessMove, i32)>>, max_time: u128, best_previous: ( Option<ChessMove>, i32), cachetable: &mut chess::CacheTable<Entry>, pawn_table: &mut chess::CacheTable<i32>) -> (Option<chess::ChessMove>, i32, Vec<(ChessMove, i32)>, SearchInfo, bool){
-    let movegen = chess::MoveGen::new_legal(&board);
-    let mut best_move:Option<chess::ChessMove> = None;
-    let mut best_score = -9999;  
+pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessMove, i32)>>, max_time: u128, best_previous: ( Option<ChessMove>, i32), state: &mut SearchState) -> (Option<chess::ChessMove>, i32, Vec<(ChessMove, i32)>, SearchCounts, bool){
+    let movegen = chess::MoveGen::new_legal(board);
+    // below any mate score so a move is picked even when every move gets mated
+    let mut best_score = -constants::MATE_SCORE;  
     let debug = false;
     let mut alpha = -1000000;
     let beta = 1000000;
@@ -83,11 +406,11 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
     else { 
         let mut new_moves: Vec<ChessMove> = Vec::new(); 
         // best move first
-        if best_previous.0.is_some(){
-            new_moves.push(best_previous.0.unwrap());
+        if let Some(best) = best_previous.0{
+            new_moves.push(best);
         }
         // remove the best move from moves list
-        moves.retain(|&x| x != best_previous.0.unwrap());
+        moves.retain(|&x| Some(x) != best_previous.0);
         
 
         // sort moves by score and then by capture and checks 
@@ -108,9 +431,7 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
                 else if !is_capture(board, a) && is_capture(board, b) {
                     return Ordering::Greater;
                 }
-                else if is_capture(board, a) && is_capture(board, b){
-                    return Ordering::Equal;
-                }else {
+                else {
                     return Ordering::Equal;
                 }
             }    
@@ -120,6 +441,8 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
         moves = new_moves;
         
     }
+    // a search stopped before the first move is done still plays the move ordered first, the best one so far
+    let mut best_move = moves.first().copied();
 
 
 
@@ -127,11 +450,8 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
 
     
     let mut time_spent = 0;
-    let mut total_nodes = 0;
-    let mut total_transpositions_recorded = 0;
-    let mut total_transpositions_used = 0;
-    let mut total_pawn_hash_table_used = 0;
-    let mut total_pawn_hash_table_recorded = 0;
+    // the counts of this iteration, every root move is searched with the same info
+    state.info.reset_counts();
     let mut checked_previous_best_move = false;
     let mut bad_last_move = false;
     let best_previous_move = best_previous.0;
@@ -140,24 +460,29 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
     for chess_move in moves{
         let now = Instant::now();
         let passed_board = board.make_move_new(chess_move); 
-        let mut search_info = SearchInfo::new();
-        let board_value = -pv_search(&passed_board, -beta, -alpha, depth, cachetable, &mut search_info, pawn_table, &mut line);
-        
+        state.info.push_move(board, chess_move, &passed_board);
+        state.history.push(history::piece_to(board, chess_move));
+        let board_value = -pv_search(&passed_board, -beta, -alpha, depth, &mut state.cachetable, &mut state.info, &mut state.pawn_table, &mut state.history, &mut line);
+        state.history.pop();
+        state.info.pop_move();
+        state.info.trace_move(chess_move, 0);
+
+        // the search of this move was cut short, its score can't be trusted.
+        // return the best move among the moves that were fully searched in this iteration (if any)
+        if state.info.stopped{
+            if debug{
+                println!("aborted in depth {} ", depth);
+            }
+            return (best_move, best_score, table, state.info.counts(), true);
+        }
         
-        if ! checked_previous_best_move && chess_move == best_previous_move.unwrap_or(ChessMove::default()){
+        if ! checked_previous_best_move && chess_move == best_previous_move.unwrap_or_default(){
             if best_previous_score > board_value + 100{
                 bad_last_move = true;
             }
             checked_previous_best_move = true;
         }
 
-
-        total_nodes += search_info.nodes_searched;
-        total_transpositions_recorded += search_info.transpostions_recorded;
-        total_transpositions_used += search_info.transpostions_used;
-        total_pawn_hash_table_used += search_info.pawn_hash_table_used;
-        total_pawn_hash_table_recorded += search_info.pawn_hash_table_recorded;
-
         if debug{
             println!("{} - {}",chess_move, board_value);
         }
@@ -181,9 +506,9 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
         }
         
 
-        if let Some(best_line) = best_move{
+        if best_move.is_some(){
             let joined = pvline.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(" ");
-            io::stdout().write((format!("info depth {} score cp {} pv {}\n",depth, best_score, joined)).as_bytes()).ok();
+            io::stdout().write_all((format!("info depth {} score {} pv {}\n",depth, uci_score(best_score), joined)).as_bytes()).ok();
 
         }
 
@@ -195,9 +520,9 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
             println!("found {}, last {}", best_score, best_previous_score);
             }
             if best_score >= best_previous_score + 100{
-                return (best_move, best_score, table, SearchInfo{nodes_searched: total_nodes, transpostions_recorded: total_transpositions_recorded, transpostions_used: total_transpositions_used, pawn_hash_table_recorded: total_pawn_hash_table_recorded, pawn_hash_table_used: total_pawn_hash_table_used}, true)
+                return (best_move, best_score, table, state.info.counts(), true)
             }
-            return (None, -9999, table, SearchInfo{nodes_searched: total_nodes, transpostions_recorded: total_transpositions_recorded, transpostions_used: total_transpositions_used, pawn_hash_table_recorded: total_pawn_hash_table_recorded, pawn_hash_table_used: total_pawn_hash_table_used}, true);
+            return (None, -9999, table, state.info.counts(), true);
         }
     }
     
@@ -205,7 +530,7 @@ pub fn search_depth(board: &Board, depth: u32, sorted_moves: &Option<Vec<(ChessM
 
     table.sort_by_key(|x| -x.1);
 
-    return (best_move, best_score, table, SearchInfo{nodes_searched: total_nodes, transpostions_recorded: total_transpositions_recorded, transpostions_used: total_transpositions_used, pawn_hash_table_recorded: total_pawn_hash_table_recorded, pawn_hash_table_used: total_pawn_hash_table_used}, false)
+    return (best_move, best_score, table, state.info.counts(), false)
 }
 
 fn is_interesting(board: &Board, chess_move: ChessMove) -> bool{
@@ -222,8 +547,10 @@ fn is_interesting(board: &Board, chess_move: ChessMove) -> bool{
         interesting
 }
 
-fn late_move_reduction(board: &Board, chess_move: ChessMove, depth: u32) -> u32{
-    let mut red = 0;
+// the reduction grows with the depth and the number of moves already searched,
+// and is then corrected by the history of the move: moves that caused cutoffs before are reduced less
+fn late_move_reduction(board: &Board, chess_move: ChessMove, depth: u32, moves_searched: u32, history_score: i32) -> u32{
+    let mut red: i32 = 0;
     if depth >= 3{
         let interesting = is_interesting(board, chess_move);
         
@@ -231,26 +558,103 @@ fn late_move_reduction(board: &Board, chess_move: ChessMove, depth: u32) -> u32{
 
             red = 1;
             if depth >= 5{
-                red = depth / 4; //TODO: maybe to much?
+                red = depth as i32 / 4;
             }
-            
+            if moves_searched >= constants::LMR_LATE_MOVES{
+                red += 1;
+            }
+            red -= history_score / constants::LMR_HISTORY_DIVISOR;
+            // never drop directly into the quiescence search
+            red = red.clamp(0, depth as i32 - 2);
         }
         
     }
-    return red;
+    return red as u32;
 }
 
 
+// captures and checks first, captures ordered by their capture history, then the quiet moves ordered by history.
+// the flag tells if the move is a capture
+fn order_moves(board: &Board, history: &History) -> Vec<(ChessMove, bool)>{
+    let mut noisy: Vec<(ChessMove, bool, i32)> = Vec::new();
+    let mut quiet: Vec<(ChessMove, bool, i32)> = Vec::new();
+    let movegen = chess::MoveGen::new_legal(board);
+    for chess_move in movegen{
+        if is_capture(board, &chess_move){
+            noisy.push((chess_move, true, history.capture_score(board, chess_move)));
+        }else if is_check(board, &chess_move){
+            noisy.push((chess_move, false, 0));
+        }else{
+            quiet.push((chess_move, false, history.quiet_score(board, chess_move)));
+        }
+    }
+    noisy.sort_by_key(|x| -x.2);
+    quiet.sort_by_key(|x| -x.2);
+    noisy.iter().chain(quiet.iter()).map(|x| (x.0, x.1)).collect()
+}
+
 
 
-pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut chess::CacheTable<Entry>, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<i32>, pvline: &mut Vec<ChessMove>) -> i32{
+
+#[allow(clippy::too_many_arguments)]
+pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut chess::CacheTable<Entry>, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<PawnEntry>, history: &mut History, pvline: &mut Vec<ChessMove>) -> i32{
+    info.ply += 1;
+    info.trace_enter("pv", alpha, beta, depth);
+    let score = pv_search_inner(board, alpha, beta, depth, cache, info, pawn_table, history, pvline);
+    info.trace_leave(score);
+    info.ply -= 1;
+    score
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn pv_search_inner(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut chess::CacheTable<Entry>, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<PawnEntry>, history: &mut History, pvline: &mut Vec<ChessMove>) -> i32{
     let mut line:Vec<ChessMove> = Vec::new();
-    let late_move_reduction_enabled = true;
-    let null_pruning = true;
+    // when proving a mate every move has to be searched to the full depth
+    let proving_mate = info.limits.mate.is_some();
+    let late_move_reduction_enabled = !proving_mate;
+    let null_pruning = !proving_mate;
     let using_cache = false;
     info.nodes_searched += 1;
+    if info.should_stop(){
+        info.trace_prune("stopped");
+        return 0;
+    }
+    // a mate given with the move that completes the fifty moves still wins, so it is checked before the draws
+    if board.checkers().popcnt() > 0 && board.status() == chess::BoardStatus::Checkmate{
+        info.trace_prune("mate");
+        return mated_score(info.ply);
+    }
+    if info.draws.is_draw(board){
+        info.trace_prune("draw");
+        return info.draws.score(board);
+    }
     let mut alpha = alpha;
-    let beta = beta;
+    let mut beta = beta;
+
+    // mate distance pruning. even mating right here can't beat a shorter mate that was already found,
+    // and getting mated on the next move is no worse than a mate that was already found against us
+    alpha = alpha.max(mated_score(info.ply));
+    beta = beta.min(-mated_score(info.ply + 1));
+    if alpha >= beta{
+        info.trace_prune("mate distance");
+        return alpha;
+    }
+
+    // right after a capture or a pawn move the tablebase result is exact, later the fifty-move rule could change it
+    if info.draws.halfmove_clock() == 0{
+        if let Some(score) = info.probe_tablebases(board){
+            info.trace_prune("tablebase");
+            return score;
+        }
+    }
+
+    // a drawn king and pawn against king needs no search. the won ones are left to the search and the evaluation,
+    // which scores them by how far the pawn is, so the pawn keeps moving up
+    if bitbase::probe_kpk(board) == Some(false){
+        info.trace_prune("kpk draw");
+        return info.draws.score(board);
+    }
 
     // look for the position in the cache
     if using_cache{
@@ -263,6 +667,7 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
                     Nodetype::AllNode=>{
                         alpha = entry.score;
                         info.transpostions_used += 1;
+                        info.trace_tt_hit();
                     }
                     Nodetype::CutNode=>{
                         // if we have a lower enough value to set the upper-bound
@@ -274,6 +679,7 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
                         if entry.depth >= depth{
                             // println!("used cache!!");
                             info.transpostions_used += 1;
+                            info.trace_tt_hit();
                             return entry.score
                         }
                     }
@@ -284,7 +690,7 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
     }
     
     // if we reached the max depth then we'll return the score.
-    if depth <= 0 { 
+    if depth == 0 { 
         pvline.clear();
         return quiesce(board, alpha, beta, 6, info, pawn_table);
     }
@@ -295,8 +701,17 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
     // null move pruning 
     if null_pruning && depth >= 3 && !in_check{
         if let Some(passed_board) = board.null_move(){
-            let score =  -pv_search(&passed_board,-beta, -beta + 1, depth - 2 - 1, cache, info, pawn_table, &mut line);
+            history.push(None);
+            info.push_null(&passed_board);
+            let score =  -pv_search(&passed_board,-beta, -beta + 1, depth - 2 - 1, cache, info, pawn_table, history, &mut line);
+            info.pop_move();
+            history.pop();
+            info.trace_move("null", 2);
+            if info.stopped{
+                return 0;
+            }
             if score >= beta{
+                info.trace_prune("null move");
                 return beta;
             }
         }
@@ -309,18 +724,23 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
     
 
     /* decide about limited razoring at the pre-pre-frontier nodes */
-    let board_balance = evaluation::material_balance(board);
+    let board_balance = info.material(board).material_balance(board);
     let mut fscore = board_balance + constants::RAZORING_MARGIN;
-    if !in_check && extend != 0 && depth == 3 && fscore <= alpha
+    if !proving_mate && !in_check && extend != 0 && depth == 3 && fscore <= alpha
         { fprune = true;  fmax = fscore; razoring = true; }
     /* decide about extended futility pruning at pre-frontier nodes */
     fscore = board_balance + constants::EXTENDED_FUTILITY_MARGIN;
-    if !in_check && extend != 0 && depth == 2 && fscore <= alpha
+    if !proving_mate && !in_check && extend != 0 && depth == 2 && fscore <= alpha
         { fprune = true; fmax = fscore; }
     /* decide about selective futility pruning at frontier nodes */
     fscore = board_balance + constants::FUTILITY_MARGIN;
-    if !in_check && depth == 1 && fscore <= alpha
+    if !proving_mate && !in_check && depth == 1 && fscore <= alpha
         { fprune = true; fmax = fscore; }
+    if razoring{
+        info.trace_prune("razoring");
+    }else if fprune{
+        info.trace_prune("futility");
+    }
  
 
     
@@ -364,20 +784,11 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
     // if this positon has no moves then its mate or a stalemate
     if count.count() == 0{
         if board.status() == chess::BoardStatus::Stalemate{
-            return 0;
-        }
-        match board.side_to_move(){
-            chess::Color::White=>{
-                // print!("white mated");
-                return -9999 - depth as i32
-
-            },
-            chess::Color::Black=>{
-                // print!("black mated");
-                return -9999 - depth as i32
-
-            }
+            info.trace_prune("stalemate");
+            return info.draws.score(board);
         }
+        info.trace_prune("mate");
+        return mated_score(info.ply);
         
     }
     
@@ -407,25 +818,14 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
     // //}
 
 
-    let movegen = chess::MoveGen::new_legal(board);
-    
     // sort the moves by checks and captures
-    let mut moves: Vec<ChessMove> = Vec::new();
-    for chess_move in movegen{
-        if is_capture(board, &chess_move) || is_check(board, &chess_move){
-            moves.push(chess_move);
-        }
-    }
-    let movegen = chess::MoveGen::new_legal(board);
-    for chess_move in movegen{
-        if !is_capture(board, &chess_move) && !is_check(board, &chess_move){
-            moves.push(chess_move);
-        }
-    }
+    let moves = order_moves(board, history);
+    let mut tried_quiets: Vec<ChessMove> = Vec::new();
+    let mut tried_captures: Vec<ChessMove> = Vec::new();
 
     // let futility_pruning = false;
     // let margin = -100;
-    for chess_move in moves  {
+    for (chess_move, capture) in moves  {
 
         // // futility pruning in child nodes
         // if futility_pruning && depth == 1 && board.checkers().popcnt() == 0{
@@ -439,39 +839,65 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
         let passed_board = board.make_move_new(chess_move);
         let mut score;
         // late move reduction
-        let moves_to_reduce;
-        if late_move_reduction_enabled{
-            moves_to_reduce = late_move_reduction(board, chess_move, depth);
+        let moves_to_reduce = if late_move_reduction_enabled{
+            let history_score = if capture{
+                history.capture_score(board, chess_move)
+            }else{
+                history.quiet_score(board, chess_move)
+            };
+            late_move_reduction(board, chess_move, depth, (tried_quiets.len() + tried_captures.len()) as u32, history_score)
         }else{
-            moves_to_reduce = 0;
-        }
+            0
+        };
+
+        history.push(history::piece_to(board, chess_move));
+        info.push_move(board, chess_move, &passed_board);
+
+        // the reduction of the last search of this move, only used by the tracer
+        let mut last_reduction = 0;
 
         if first_search_pv{
-            score = -pv_search(&passed_board,-beta, -alpha, depth - 1, cache, info, pawn_table, &mut line);
+            score = -pv_search(&passed_board,-beta, -alpha, depth - 1, cache, info, pawn_table, history, &mut line);
         } else {
-            if !fprune || is_check(board, &chess_move) || fmax + gain(&board, &chess_move) > alpha{
-                score = -zero_window_search(&passed_board, -alpha, depth - 1 - moves_to_reduce, info, cache, pawn_table);
+            if !fprune || is_check(board, &chess_move) || fmax + gain(board, &chess_move) > alpha{
+                score = -zero_window_search(&passed_board, -alpha, depth - 1 - moves_to_reduce, info, cache, pawn_table, history);
+                info.trace_move(chess_move, moves_to_reduce);
+                last_reduction = moves_to_reduce;
                 // in fail-soft ... && score < beta ) is common
                 if  score > alpha {
-                    score = -pv_search(&passed_board, -beta, -alpha, depth - 1, cache, info, pawn_table, &mut line); // re-search
+                    score = -pv_search(&passed_board, -beta, -alpha, depth - 1, cache, info, pawn_table, history, &mut line); // re-search
+                    last_reduction = 0;
                 }
 
             }else{
                 if razoring && is_interesting(board, chess_move){
-                    score = -zero_window_search(&passed_board, -alpha, depth - 1 - moves_to_reduce, info, cache, pawn_table);
+                    score = -zero_window_search(&passed_board, -alpha, depth - 1 - moves_to_reduce, info, cache, pawn_table, history);
+                    info.trace_move(chess_move, moves_to_reduce);
+                    last_reduction = moves_to_reduce;
                     // in fail-soft ... && score < beta ) is common
                     if  score > alpha {
-                        score = -pv_search(&passed_board, -beta, -alpha, depth - 1, cache, info, pawn_table, &mut line); // re-search
+                        score = -pv_search(&passed_board, -beta, -alpha, depth - 1, cache, info, pawn_table, history, &mut line); // re-search
+                        last_reduction = 0;
                     }
                 }else{
                     score = quiesce(board, -beta, alpha, 6, info, pawn_table);
+                    info.trace_prune_child(if razoring {"razoring"} else {"futility"});
                 }
                     
                 
             }
         }
+        info.pop_move();
+        history.pop();
+        info.trace_move(chess_move, last_reduction);
+        // the search was aborted, unwind without touching the cache
+        if info.stopped{
+            return 0;
+        }
         // the move is un-made because we created a copy of the board.
         if score >= beta {
+            history.update_cutoff(board, chess_move, capture, &tried_quiets, &tried_captures, depth);
+            info.trace_prune("beta cutoff");
             cache.add(board.get_hash(), Entry{ depth, node_type: Nodetype::AllNode, score: alpha });
             info.transpostions_recorded += 1;
                 return beta;   // fail-hard beta-cutoff
@@ -489,6 +915,11 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
             info.transpostions_recorded += 1;
             first_search_pv = false;   // *1)
         }
+        if capture{
+            tried_captures.push(chess_move);
+        }else{
+            tried_quiets.push(chess_move);
+        }
     }
     cache.add(board.get_hash(), Entry{ depth, node_type: Nodetype::CutNode, score: alpha });
     info.transpostions_recorded += 1;
@@ -497,11 +928,35 @@ pub fn pv_search(board: &Board ,alpha: i32, beta:i32, depth:u32, cache: &mut che
  }
  
  // fail-hard zero window search, returns either beta-1 or beta
-fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo, cache: &mut chess::CacheTable<Entry>, pawn_table: &mut chess::CacheTable<i32>) -> i32 {
+fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo, cache: &mut chess::CacheTable<Entry>, pawn_table: &mut chess::CacheTable<PawnEntry>, history: &mut History) -> i32 {
+    info.ply += 1;
+    info.trace_enter("zw", beta - 1, beta, depth);
+    let score = zero_window_search_inner(board, beta, depth, info, cache, pawn_table, history);
+    info.trace_leave(score);
+    info.ply -= 1;
+    score
+}
+
+
+fn zero_window_search_inner(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo, cache: &mut chess::CacheTable<Entry>, pawn_table: &mut chess::CacheTable<PawnEntry>, history: &mut History) -> i32 {
     // alpha == beta - 1
     // this is either a cut- or all-node
     let using_cache = false;
-    if depth <= 0 { return quiesce(board, beta-1, beta, 6, info, pawn_table);}
+    if depth == 0 { return quiesce(board, beta-1, beta, 6, info, pawn_table);}
+    info.nodes_searched += 1;
+    if info.should_stop(){
+        info.trace_prune("stopped");
+        return 0;
+    }
+    // a mate given with the move that completes the fifty moves still wins, so it is checked before the draws
+    if board.checkers().popcnt() > 0 && board.status() == chess::BoardStatus::Checkmate{
+        info.trace_prune("mate");
+        return mated_score(info.ply);
+    }
+    if info.draws.is_draw(board){
+        info.trace_prune("draw");
+        return info.draws.score(board);
+    }
 
     let mut _beta = beta;
     if using_cache{
@@ -516,11 +971,13 @@ fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo
                     Nodetype::CutNode=>{
                         _beta = entry.score;
                         info.transpostions_used += 1;
+                        info.trace_tt_hit();
                     }
                     Nodetype::Pvnode=>{
                         if entry.depth >= depth{
                             // println!("used cache!!");
                             info.transpostions_used += 1;
+                            info.trace_tt_hit();
                             return entry.score
                         }
                     }
@@ -529,30 +986,44 @@ fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo
             }
         }
     }
-    let movegen = chess::MoveGen::new_legal(board);
     // sort the moves by checks and captures
-    let mut moves: Vec<ChessMove> = Vec::new();
-    for chess_move in movegen{
-        if is_capture(board, &chess_move) || is_check(board, &chess_move){
-            moves.push(chess_move);
-        }
-    }
-    let movegen = chess::MoveGen::new_legal(board);
-    for chess_move in movegen{
-        if !is_capture(board, &chess_move) && !is_check(board, &chess_move){
-            moves.push(chess_move);
+    let moves = order_moves(board, history);
+    let mut tried_quiets: Vec<ChessMove> = Vec::new();
+    let mut tried_captures: Vec<ChessMove> = Vec::new();
+
+    if moves.is_empty(){
+        if board.checkers().popcnt() > 0{
+            info.trace_prune("mate");
+            return mated_score(info.ply);
         }
+        info.trace_prune("stalemate");
+        return info.draws.score(board);
     }
 
-    for chess_move in moves {
+    for (chess_move, capture) in moves {
         let passed_board = board.make_move_new(chess_move);
-        let score = -zero_window_search(&passed_board, 1-_beta, depth - 1, info, cache, pawn_table);
+        history.push(history::piece_to(board, chess_move));
+        info.push_move(board, chess_move, &passed_board);
+        let score = -zero_window_search(&passed_board, 1-_beta, depth - 1, info, cache, pawn_table, history);
+        info.pop_move();
+        history.pop();
+        info.trace_move(chess_move, 0);
+        if info.stopped{
+            return 0;
+        }
 
         if score >= _beta {
+            history.update_cutoff(board, chess_move, capture, &tried_quiets, &tried_captures, depth);
+            info.trace_prune("beta cutoff");
             cache.add(board.get_hash(), Entry{ depth, node_type: Nodetype::AllNode, score: _beta });
             info.transpostions_recorded += 1;
             return _beta;   // fail-hard beta-cutoff
         }
+        if capture{
+            tried_captures.push(chess_move);
+        }else{
+            tried_quiets.push(chess_move);
+        }
     }
     cache.add(board.get_hash(), Entry{ depth, node_type: Nodetype::CutNode, score: _beta-1 });
     info.transpostions_recorded += 1;
@@ -560,17 +1031,41 @@ fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo
  }
 
 
- fn quiesce(board: &Board, alpha: i32, beta:i32, depth:u32, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<i32>) -> i32{
+ fn quiesce(board: &Board, alpha: i32, beta:i32, depth:u32, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<PawnEntry>) -> i32{
+    info.trace_enter("qs", alpha, beta, depth);
+    let score = quiesce_inner(board, alpha, beta, depth, info, pawn_table);
+    info.trace_leave(score);
+    score
+}
+
+
+fn quiesce_inner(board: &Board, alpha: i32, beta:i32, depth:u32, info: &mut SearchInfo, pawn_table: &mut chess::CacheTable<PawnEntry>) -> i32{
     info.nodes_searched += 1;
-    let stand_pat = evaluation::evaluate_rework(board) + pawn_table_lookup(board, pawn_table, info);
+    if info.should_stop(){
+        info.trace_prune("stopped");
+        return 0;
+    }
+    // the evaluation doesn't know how far from the root the mate is, and a mate beats the fifty-move rule
+    if board.checkers().popcnt() > 0 && board.status() == chess::BoardStatus::Checkmate{
+        info.trace_prune("mate");
+        return mated_score(info.ply);
+    }
+    let draw_score = info.draws.score(board);
+    if info.draws.is_draw(board){
+        info.trace_prune("draw");
+        return draw_score;
+    }
+    let stand_pat = info.evaluate(board, draw_score, pawn_table);
 
     let mut _alpha = alpha;
     if stand_pat >= beta{
+        info.trace_prune("stand pat");
         return beta;
     }
 
     let big_delta = 975; // queen value
     if stand_pat < alpha - big_delta {
+        info.trace_prune("delta");
         return alpha;
     }
 
@@ -581,15 +1076,25 @@ fn zero_window_search(board: &Board, beta:i32, depth: u32, info: &mut SearchInfo
     if depth == 0{
         return stand_pat
     }
-    let movegen = chess::MoveGen::new_legal(&board);
+    let movegen = chess::MoveGen::new_legal(board);
 
     for chess_move in movegen{
 
-        if is_capture(&board, &chess_move) || is_check(&board, &chess_move){
-            let board = board.make_move_new(chess_move);
-            let score = -quiesce(&board, -beta, -_alpha, depth - 1, info, pawn_table);
+        if is_capture(board, &chess_move) || is_check(board, &chess_move){
+            let passed_board = board.make_move_new(chess_move);
+            info.push_move(board, chess_move, &passed_board);
+            // the quiescence search is entered on the same position as its caller, so the ply only grows here
+            info.ply += 1;
+            let score = -quiesce(&passed_board, -beta, -_alpha, depth - 1, info, pawn_table);
+            info.ply -= 1;
+            info.pop_move();
+            info.trace_move(chess_move, 0);
+            if info.stopped{
+                return 0;
+            }
 
             if score >= beta{
+                info.trace_prune("beta cutoff");
                 return beta
             }
             if score > _alpha{
@@ -644,44 +1149,25 @@ fn gain(board: &Board, chess_move: &ChessMove) -> i32{
 }
 
 
-pub fn pawn_table_lookup(board: &Board, pawn_table: &mut chess::CacheTable<i32>, info: &mut SearchInfo) -> i32{
-    let mut score = 0;
-    let black_pawns = get_piece_type(board, Piece::Pawn,chess::Color::Black);
-    let white_pawns = get_piece_type(board, Piece::Pawn,chess::Color::White);
-    
-    // get endgame score
-    let pieces_total_count = board.pieces(chess::Piece::Bishop).popcnt() + board.pieces(chess::Piece::Knight).popcnt() + board.pieces(chess::Piece::Rook).popcnt();
-    let endgame = board.pieces(chess::Piece::Queen).popcnt() == 0 && pieces_total_count <= 2;
-    
-    // get the pawn hash key
-    let mut hasher = DefaultHasher::new();
-    PawnKey{endgame, black_pawns, white_pawns}.hash(&mut hasher);
-    let key = hasher.finish();
-    
-    // get pawn scores for both sides
+// the pawn hash entry of the position, computed and saved when the pawns are new to the table
+pub fn pawn_entry(board: &Board, pawn_table: &mut chess::CacheTable<PawnEntry>, info: &mut SearchInfo) -> PawnEntry{
+    let key = info.pawn_key(board);
     match pawn_table.get(key) {
         None =>{
-            
-
-            let structure_score = evaluation::evaluate_pawn_structure(black_pawns, white_pawns, endgame);
-            // save to pawn table
-            pawn_table.add(key, structure_score);
-            score += structure_score;
+            let entry = PawnEntry::new(board, &info.params);
+            pawn_table.add(key, entry);
             info.pawn_hash_table_recorded += 1;
-
+            entry
         },
-        Some(desc) =>{
-            score += desc;
+        Some(entry) =>{
             info.pawn_hash_table_used += 1;
+            entry
         }
     }
-    match board.side_to_move(){
-        chess::Color::White=>{
-            return score
-        },
-        chess::Color::Black=>{
-            return -score
-        }
-    }
-    
+}
+
+
+// the pawn structure score from the side to move's point of view
+pub fn pawn_table_lookup(board: &Board, pawn_table: &mut chess::CacheTable<PawnEntry>, info: &mut SearchInfo) -> i32{
+    pawn_entry(board, pawn_table, info).score(board)
 }
@@ -0,0 +1,1392 @@
+use chess::{BitBoard, Board, BoardStatus, CastleRights, ChessMove, Color, MoveGen, Piece, ALL_PIECES, ALL_SQUARES};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, OnceLock, RwLock};
+
+// a prober for the syzygy endgame tablebases. a table is named after its material with the stronger side first
+// (KRvK), the .rtbw file stores win/draw/loss for both sides to move, the .rtbz file the distance to the next
+// capture or pawn move (dtz) for one side to move. the positions of a table are numbered by the squares of
+// their pieces and the values are compressed in blocks of huffman coded symbols that expand into pairs of values
+//
+// file layout, little endian unless said otherwise:
+//   magic, u8 flags (1 = both sides stored apart, 2 = pawns)
+//   per file of the leading pawn (a-d, just one without pawns): the order of the groups and the pieces per side
+//   per file and side: the sizes of the compression, the symbols and their pairs
+//   the dtz value maps, the sparse index, the block lengths and the blocks (big endian bits, 64 byte aligned)
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+const MAX_PIECES: usize = 7;
+
+// the flags of the data of a side, all but the last are only used by the dtz tables
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+// the results in the tables, from the point of view of the side to move. a cursed win is a win that takes more
+// than fifty moves without a capture or a pawn move and a blessed loss the other side of it, both are draws
+const WDL_LOSS: i32 = -2;
+const WDL_BLESSED_LOSS: i32 = -1;
+const WDL_DRAW: i32 = 0;
+const WDL_CURSED_WIN: i32 = 1;
+const WDL_WIN: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wdl{
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win
+}
+
+impl Wdl{
+    fn from_value(value: i32) -> Wdl{
+        match value{
+            WDL_LOSS=>Wdl::Loss,
+            WDL_BLESSED_LOSS=>Wdl::BlessedLoss,
+            WDL_CURSED_WIN=>Wdl::CursedWin,
+            WDL_WIN=>Wdl::Win,
+            _=>Wdl::Draw
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind{
+    Wdl,
+    Dtz
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ProbeError{
+    // no table for the material or the file couldn't be read
+    Missing,
+    // the dtz table only stores the other side to move
+    ChangeStm
+}
+
+// the ways to number the squares of the leading pieces and pawns, the same for every table
+struct Indices{
+    binomial: [[u64; 64]; MAX_PIECES],
+    map_pawns: [usize; 64],
+    lead_pawn_idx: [[u64; 64]; MAX_PIECES],
+    lead_pawns_size: [[u64; 4]; MAX_PIECES],
+    map_b1h1h7: [u64; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[u64; 64]; 10]
+}
+
+fn file_of(square: usize) -> usize{
+    square & 7
+}
+
+fn rank_of(square: usize) -> usize{
+    square >> 3
+}
+
+// negative below the a1-h8 diagonal, zero on it and positive above it
+fn off_a1h8(square: usize) -> i32{
+    rank_of(square) as i32 - file_of(square) as i32
+}
+
+static INDICES: LazyLock<Indices> = LazyLock::new(|| {
+    let mut indices = Indices{
+        binomial: [[0; 64]; MAX_PIECES],
+        map_pawns: [0; 64],
+        lead_pawn_idx: [[0; 64]; MAX_PIECES],
+        lead_pawns_size: [[0; 4]; MAX_PIECES],
+        map_b1h1h7: [0; 64],
+        map_a1d1d4: [0; 64],
+        map_kk: [[0; 64]; 10]
+    };
+
+    // the squares below the a1-h8 diagonal are numbered 0..27
+    let mut code = 0;
+    for square in 0..64{
+        if off_a1h8(square) < 0{
+            indices.map_b1h1h7[square] = code;
+            code += 1;
+        }
+    }
+
+    // the squares of the a1-d1-d4 triangle are numbered 0..9, the ones on the diagonal last
+    let mut diagonal = Vec::new();
+    let mut code = 0;
+    for square in 0..28{
+        if off_a1h8(square) < 0 && file_of(square) <= 3{
+            indices.map_a1d1d4[square] = code;
+            code += 1;
+        }else if off_a1h8(square) == 0 && file_of(square) <= 3{
+            diagonal.push(square);
+        }
+    }
+    for square in diagonal{
+        indices.map_a1d1d4[square] = code;
+        code += 1;
+    }
+
+    // the 462 legal placements of two kings with the first one in the triangle. when the first king is on
+    // the diagonal the second one can't be above it, the placements with both on the diagonal come last
+    let mut both_on_diagonal = Vec::new();
+    let mut code = 0;
+    for index in 0..10{
+        for (first, &first_square) in ALL_SQUARES.iter().enumerate().take(28){
+            if indices.map_a1d1d4[first] != index || (index == 0 && first != 1){
+                continue;
+            }
+            let touching = chess::get_king_moves(first_square) | BitBoard::from_square(first_square);
+            for (second, &second_square) in ALL_SQUARES.iter().enumerate(){
+                if touching & BitBoard::from_square(second_square) != chess::EMPTY{
+                    continue;
+                }
+                if off_a1h8(first) == 0 && off_a1h8(second) > 0{
+                    continue;
+                }
+                if off_a1h8(first) == 0 && off_a1h8(second) == 0{
+                    both_on_diagonal.push((index, second));
+                }else{
+                    indices.map_kk[index][second] = code;
+                    code += 1;
+                }
+            }
+        }
+    }
+    for (index, second) in both_on_diagonal{
+        indices.map_kk[index][second] = code;
+        code += 1;
+    }
+
+    // the ways to choose k of n squares
+    indices.binomial[0][0] = 1;
+    for n in 1..64{
+        for k in 0..MAX_PIECES.min(n + 1){
+            indices.binomial[k][n] = if k > 0 { indices.binomial[k - 1][n - 1] } else { 0 } + if k < n { indices.binomial[k][n - 1] } else { 0 };
+        }
+    }
+
+    // the leading pawn is the one nearest to the edge and then the lowest. map_pawns numbers a2-h7 so it has the
+    // highest number, the number is also how many squares are left for the other pawns of the group
+    let mut available = 47;
+    for lead_pawns in 1..MAX_PIECES - 1{
+        for file in 0..4{
+            let mut index = 0;
+            for rank in 1..7{
+                let square = rank * 8 + file;
+                if lead_pawns == 1{
+                    indices.map_pawns[square] = available;
+                    indices.map_pawns[square ^ 7] = available - 1;
+                    available = available.saturating_sub(2);
+                }
+                indices.lead_pawn_idx[lead_pawns][square] = index;
+                index += indices.binomial[lead_pawns - 1][indices.map_pawns[square]];
+            }
+            indices.lead_pawns_size[lead_pawns][file] = index;
+        }
+    }
+    indices
+});
+
+fn read_u16(bytes: &[u8], pos: usize) -> usize{
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]]) as usize
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> usize{
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap()) as usize
+}
+
+// the compressed bits are big endian, a block can end right at the end of the file
+fn read_u32_be(bytes: &[u8], pos: usize) -> u64{
+    match bytes.get(pos..pos + 4){
+        Some(x)=>u32::from_be_bytes(x.try_into().unwrap()) as u64,
+        None=>0
+    }
+}
+
+fn need(bytes: &[u8], end: usize) -> Result<(), String>{
+    if end > bytes.len(){
+        return Err("the table is truncated".to_string());
+    }
+    Ok(())
+}
+
+// the compressed values of a side to move (and a file of the leading pawn)
+#[derive(Debug, Clone, Default)]
+struct PairsData{
+    flags: u8,
+    block_size: usize,
+    // there is a sparse index entry about every span values
+    span: u64,
+    num_blocks: usize,
+    min_sym_len: usize,
+    // offsets in the file
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: usize,
+    sparse_index: usize,
+    sparse_index_size: usize,
+    data: usize,
+    // base64[l] is the lowest symbol of length l + min_sym_len padded to 64 bits
+    base64: Vec<u64>,
+    // how many values a symbol expands into, minus one
+    symlen: Vec<u32>,
+    // the pieces in the order the position is numbered, in groups of same pieces
+    pieces: [u8; MAX_PIECES],
+    group_idx: [u64; MAX_PIECES + 1],
+    group_len: [usize; MAX_PIECES + 1],
+    // where the value maps of a dtz table start for a win, a loss, a cursed win and a blessed loss
+    map_idx: [usize; 4]
+}
+
+// the left and the right symbol a symbol expands into, 12 bits each
+fn btree_children(bytes: &[u8], btree: usize, symbol: usize) -> (usize, usize){
+    let lr = &bytes[btree + 3 * symbol..btree + 3 * symbol + 3];
+    let left = ((lr[1] as usize & 0xF) << 8) | lr[0] as usize;
+    let right = ((lr[2] as usize) << 4) | (lr[1] as usize >> 4);
+    (left, right)
+}
+
+impl PairsData{
+    // the pieces are numbered group after group in the order the table gives, the leading group is the kings and
+    // another piece (or just the kings when no piece is alone) or the leading pawns
+    fn set_groups(&mut self, info: &TableInfo, order: [u8; 2], file: usize){
+        let indices = &*INDICES;
+        let mut n = 0;
+        let mut first_len: i32 = if info.has_pawns { 0 } else if info.has_unique_pieces { 3 } else { 2 };
+        self.group_len[n] = 1;
+        for i in 1..info.piece_count{
+            first_len -= 1;
+            if first_len > 0 || self.pieces[i] == self.pieces[i - 1]{
+                self.group_len[n] += 1;
+            }else{
+                n += 1;
+                self.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        self.group_len[n] = 0;
+
+        let pawns_on_both_sides = info.has_pawns && info.pawn_count[1] > 0;
+        let mut next = if pawns_on_both_sides { 2 } else { 1 };
+        let mut free_squares = 64 - self.group_len[0] - if pawns_on_both_sides { self.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize{
+            if k == order[0] as usize{
+                self.group_idx[0] = idx;
+                idx *= if info.has_pawns{
+                    indices.lead_pawns_size[self.group_len[0]][file]
+                }else if info.has_unique_pieces{
+                    31332
+                }else{
+                    462
+                };
+            }else if k == order[1] as usize{
+                self.group_idx[1] = idx;
+                idx *= indices.binomial[self.group_len[1]][48 - self.group_len[0]];
+            }else{
+                self.group_idx[next] = idx;
+                idx *= indices.binomial[self.group_len[next]][free_squares];
+                free_squares -= self.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        self.group_idx[n] = idx;
+    }
+
+    // the number of indices, positions that can't happen included
+    fn size(&self) -> u64{
+        let groups = self.group_len.iter().position(|&x| x == 0).unwrap_or(MAX_PIECES);
+        self.group_idx[groups]
+    }
+
+    // reads the sizes of the compression and the symbols, returns where the next side starts
+    fn set_sizes(&mut self, bytes: &[u8], pos: usize) -> Result<usize, String>{
+        need(bytes, pos + 2)?;
+        self.flags = bytes[pos];
+        if self.flags & FLAG_SINGLE_VALUE != 0{
+            // every position has the same value, kept in min_sym_len
+            self.min_sym_len = bytes[pos + 1] as usize;
+            return Ok(pos + 2);
+        }
+        need(bytes, pos + 10)?;
+        let size = self.size();
+        self.block_size = 1 << bytes[pos + 1];
+        self.span = 1 << bytes[pos + 2];
+        self.sparse_index_size = size.div_ceil(self.span) as usize;
+        let padding = bytes[pos + 3] as usize;
+        self.num_blocks = read_u32(bytes, pos + 4);
+        self.block_length_size = self.num_blocks + padding;
+        let max_sym_len = bytes[pos + 8] as usize;
+        self.min_sym_len = bytes[pos + 9] as usize;
+        if max_sym_len < self.min_sym_len || self.min_sym_len == 0{
+            return Err("bad symbol lengths".to_string());
+        }
+        self.lowest_sym = pos + 10;
+
+        // the canonical huffman code gives the longer symbols the lower values, so the lowest symbol of every
+        // length padded to 64 bits decreases with the length and finds the length of the next symbol
+        let lengths = max_sym_len - self.min_sym_len + 1;
+        need(bytes, self.lowest_sym + 2 * lengths + 2)?;
+        let lowest_sym = self.lowest_sym;
+        let lowest = |i: usize| read_u16(bytes, lowest_sym + 2 * i) as u64;
+        self.base64 = vec![0; lengths];
+        for i in (0..lengths - 1).rev(){
+            self.base64[i] = self.base64[i + 1].wrapping_add(lowest(i)).wrapping_sub(lowest(i + 1)) / 2;
+        }
+        for i in 0..lengths{
+            self.base64[i] = self.base64[i].checked_shl((64 - i - self.min_sym_len) as u32).unwrap_or(0);
+        }
+
+        let mut pos = self.lowest_sym + 2 * lengths;
+        let symbols = read_u16(bytes, pos);
+        pos += 2;
+        self.btree = pos;
+        need(bytes, self.btree + 3 * symbols)?;
+        self.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for symbol in 0..symbols{
+            if !visited[symbol]{
+                self.symlen[symbol] = self.set_symlen(bytes, symbol, &mut visited)?;
+            }
+        }
+        Ok(pos + 3 * symbols + (symbols & 1))
+    }
+
+    // a symbol expands into its pair recursively, a leaf stands for a single value
+    fn set_symlen(&mut self, bytes: &[u8], symbol: usize, visited: &mut Vec<bool>) -> Result<u32, String>{
+        visited[symbol] = true;
+        let (left, right) = btree_children(bytes, self.btree, symbol);
+        if right == 0xFFF{
+            return Ok(0);
+        }
+        if left >= self.symlen.len() || right >= self.symlen.len(){
+            return Err("bad symbol pair".to_string());
+        }
+        if !visited[left]{
+            self.symlen[left] = self.set_symlen(bytes, left, visited)?;
+        }
+        if !visited[right]{
+            self.symlen[right] = self.set_symlen(bytes, right, visited)?;
+        }
+        Ok(self.symlen[left] + self.symlen[right] + 1)
+    }
+
+    fn block_length(&self, bytes: &[u8], block: usize) -> i64{
+        read_u16(bytes, self.block_length + 2 * block) as i64
+    }
+
+    // the value of the position with the index
+    fn decompress(&self, bytes: &[u8], idx: u64) -> i32{
+        if self.flags & FLAG_SINGLE_VALUE != 0{
+            return self.min_sym_len as i32;
+        }
+
+        // the sparse index gives the block and the offset of the value in the middle of the span of idx,
+        // from there walk back or forward over the blocks
+        let k = (idx / self.span) as usize;
+        let entry = self.sparse_index + 6 * k;
+        let mut block = read_u32(bytes, entry);
+        let mut offset = read_u16(bytes, entry + 4) as i64;
+        offset += (idx % self.span) as i64 - (self.span / 2) as i64;
+        while offset < 0{
+            block -= 1;
+            offset += self.block_length(bytes, block) + 1;
+        }
+        while offset > self.block_length(bytes, block){
+            offset -= self.block_length(bytes, block) + 1;
+            block += 1;
+        }
+
+        // read the symbols of the block until the one that covers the offset
+        let mut pos = self.data + block * self.block_size;
+        let mut buffer = (read_u32_be(bytes, pos) << 32) | read_u32_be(bytes, pos + 4);
+        pos += 8;
+        let mut buffer_size = 64;
+        let mut symbol;
+        loop{
+            let mut len = 0;
+            while buffer < self.base64[len]{
+                len += 1;
+            }
+            symbol = ((buffer - self.base64[len]) >> (64 - len - self.min_sym_len)) as usize;
+            symbol += read_u16(bytes, self.lowest_sym + 2 * len);
+            if offset < self.symlen[symbol] as i64 + 1{
+                break;
+            }
+            offset -= self.symlen[symbol] as i64 + 1;
+            len += self.min_sym_len;
+            buffer <<= len;
+            buffer_size -= len;
+            if buffer_size <= 32{
+                buffer_size += 32;
+                buffer |= read_u32_be(bytes, pos) << (64 - buffer_size);
+                pos += 4;
+            }
+        }
+
+        // the symbol stands for several values, go down its pairs to the one at the offset
+        while self.symlen[symbol] != 0{
+            let (left, right) = btree_children(bytes, self.btree, symbol);
+            if offset < self.symlen[left] as i64 + 1{
+                symbol = left;
+            }else{
+                offset -= self.symlen[left] as i64 + 1;
+                symbol = right;
+            }
+        }
+        btree_children(bytes, self.btree, symbol).0 as i32
+    }
+}
+
+// the material of a table. key is the material with the stronger side as white, key2 with the colors swapped
+#[derive(Debug, Clone)]
+struct TableInfo{
+    key: u64,
+    key2: u64,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // the pawns of the leading color first: the side with fewer pawns when both have them
+    pawn_count: [usize; 2]
+}
+
+// the pieces of a color as counted by material_key, kings included
+fn counts_key(counts: &[[usize; 6]; 2]) -> u64{
+    let mut key = 0;
+    for (color, counts) in counts.iter().enumerate(){
+        for (piece, &count) in counts.iter().enumerate(){
+            key |= (count as u64) << (4 * (color * 6 + piece));
+        }
+    }
+    key
+}
+
+fn material_key(board: &Board) -> u64{
+    let mut counts = [[0; 6]; 2];
+    for color in [Color::White, Color::Black]{
+        for piece in ALL_PIECES{
+            counts[color.to_index()][piece.to_index()] = (board.pieces(piece) & board.color_combined(color)).popcnt() as usize;
+        }
+    }
+    counts_key(&counts)
+}
+
+// the pieces of each side of a name like KRPvKR, kings included
+fn name_counts(name: &str) -> Option<[[usize; 6]; 2]>{
+    let sides: Vec<&str> = name.split('v').collect();
+    if sides.len() != 2{
+        return None;
+    }
+    let mut counts = [[0; 6]; 2];
+    for (color, side) in sides.iter().enumerate(){
+        for c in side.chars(){
+            let piece = match c{
+                'P'=>Piece::Pawn,
+                'N'=>Piece::Knight,
+                'B'=>Piece::Bishop,
+                'R'=>Piece::Rook,
+                'Q'=>Piece::Queen,
+                'K'=>Piece::King,
+                _=>return None
+            };
+            counts[color][piece.to_index()] += 1;
+        }
+    }
+    Some(counts)
+}
+
+impl TableInfo{
+    // from a name like KRPvKR
+    fn from_name(name: &str) -> Option<TableInfo>{
+        let counts = name_counts(name)?;
+        let piece_count: usize = counts.iter().flatten().sum();
+        if counts[0][5] != 1 || counts[1][5] != 1 || piece_count > MAX_PIECES{
+            return None;
+        }
+        let white_pawns = counts[0][0];
+        let black_pawns = counts[1][0];
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        Some(TableInfo{
+            key: counts_key(&counts),
+            key2: counts_key(&[counts[1], counts[0]]),
+            piece_count,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: (0..5).any(|piece| counts[0][piece] == 1 || counts[1][piece] == 1),
+            pawn_count: if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] }
+        })
+    }
+}
+
+// the piece as the tables write it: pawn 1 to king 6, black pieces + 8
+fn table_piece(piece: Piece, color: Color) -> u8{
+    piece.to_index() as u8 + 1 + if color == Color::Black { 8 } else { 0 }
+}
+
+#[derive(Debug)]
+struct Table{
+    kind: Kind,
+    bytes: Vec<u8>,
+    // by the file of the leading pawn and the side to move
+    pairs: Vec<Vec<PairsData>>
+}
+
+// the piece order and the groups of every file and side, and where the sizes of the compression start
+fn read_header(bytes: &[u8], info: &TableInfo, kind: Kind) -> Result<(Vec<Vec<PairsData>>, usize), String>{
+    let magic = if kind == Kind::Wdl { WDL_MAGIC } else { DTZ_MAGIC };
+    if bytes.len() < 5 || bytes[..4] != magic{
+        return Err("not a tablebase file".to_string());
+    }
+    let flags = bytes[4];
+    if (flags & 2 != 0) != info.has_pawns || (flags & 1 != 0) != (info.key != info.key2){
+        return Err("the table doesn't match its name".to_string());
+    }
+    let mut pos = 5;
+    let sides = if kind == Kind::Wdl && info.key != info.key2 { 2 } else { 1 };
+    let files = if info.has_pawns { 4 } else { 1 };
+    let pawns_on_both_sides = info.has_pawns && info.pawn_count[1] > 0;
+    let mut pairs = vec![vec![PairsData::default(); sides]; files];
+
+    for (file, file_pairs) in pairs.iter_mut().enumerate(){
+        need(bytes, pos + 1 + pawns_on_both_sides as usize + info.piece_count)?;
+        let second = if pawns_on_both_sides { bytes[pos + 1] } else { 0xFF };
+        let orders = [[bytes[pos] & 0xF, second & 0xF], [bytes[pos] >> 4, second >> 4]];
+        pos += 1 + pawns_on_both_sides as usize;
+        for k in 0..info.piece_count{
+            for (side, d) in file_pairs.iter_mut().enumerate(){
+                d.pieces[k] = if side == 0 { bytes[pos] & 0xF } else { bytes[pos] >> 4 };
+            }
+            pos += 1;
+        }
+        for (d, &order) in file_pairs.iter_mut().zip(&orders){
+            d.set_groups(info, order, file);
+        }
+    }
+    pos += pos & 1;
+    Ok((pairs, pos))
+}
+
+impl Table{
+    fn from_bytes(bytes: Vec<u8>, info: &TableInfo, kind: Kind) -> Result<Table, String>{
+        let (mut pairs, mut pos) = read_header(&bytes, info, kind)?;
+
+        for d in pairs.iter_mut().flatten(){
+            pos = d.set_sizes(&bytes, pos)?;
+        }
+
+        // a dtz table can map its values through a short list per result, of bytes or of words
+        if kind == Kind::Dtz{
+            for file_pairs in pairs.iter_mut(){
+                let d = &mut file_pairs[0];
+                if d.flags & FLAG_MAPPED == 0{
+                    continue;
+                }
+                if d.flags & FLAG_WIDE != 0{
+                    pos += pos & 1;
+                    for i in 0..4{
+                        need(&bytes, pos + 2)?;
+                        d.map_idx[i] = pos + 2;
+                        pos += 2 * read_u16(&bytes, pos) + 2;
+                    }
+                }else{
+                    for i in 0..4{
+                        need(&bytes, pos + 1)?;
+                        d.map_idx[i] = pos + 1;
+                        pos += bytes[pos] as usize + 1;
+                    }
+                }
+            }
+            pos += pos & 1;
+        }
+
+        for d in pairs.iter_mut().flatten(){
+            d.sparse_index = pos;
+            pos += 6 * d.sparse_index_size;
+        }
+        for d in pairs.iter_mut().flatten(){
+            d.block_length = pos;
+            pos += 2 * d.block_length_size;
+        }
+        need(&bytes, pos)?;
+        for d in pairs.iter_mut().flatten(){
+            if d.flags & FLAG_SINGLE_VALUE != 0{
+                continue;
+            }
+            pos = (pos + 0x3F) & !0x3F;
+            d.data = pos;
+            pos += d.num_blocks * d.block_size;
+            need(&bytes, pos)?;
+        }
+        Ok(Table{kind, bytes, pairs})
+    }
+
+    fn pairs_data(&self, side: usize, file: usize) -> &PairsData{
+        let file = file.min(self.pairs.len() - 1);
+        &self.pairs[file][side % self.pairs[file].len()]
+    }
+
+    fn probe(&self, board: &Board, info: &TableInfo, wdl: i32) -> Result<i32, ProbeError>{
+        let (table_file, side, idx) = self.index(board, info)?;
+        let value = self.pairs_data(side, table_file).decompress(&self.bytes, idx);
+        Ok(self.map_score(table_file, value, wdl))
+    }
+
+    // the file of the leading pawn, the side and the index the position is stored at. the tables are computed for
+    // the stronger side as white and with the first piece in a corner of the board, the position is flipped and
+    // mirrored until it matches
+    fn index(&self, board: &Board, info: &TableInfo) -> Result<(usize, usize, u64), ProbeError>{
+        let indices = &*INDICES;
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_count = 0;
+        let mut lead_pawns = chess::EMPTY;
+        let mut table_file = 0;
+
+        // symmetric tables only store white to move
+        let black_to_move = board.side_to_move() == Color::Black;
+        let flip = (info.key == info.key2 && black_to_move) || material_key(board) != info.key;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 0o70 } else { 0 };
+        let side = (flip ^ black_to_move) as usize;
+
+        // tables with pawns are split by the file of the leading pawn
+        if info.has_pawns{
+            let piece = self.pairs[0][0].pieces[0] ^ flip_color;
+            let color = if piece & 8 != 0 { Color::Black } else { Color::White };
+            lead_pawns = board.pieces(Piece::Pawn) & board.color_combined(color);
+            for square in lead_pawns{
+                squares[size] = square.to_index() ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns_count = size;
+            let mut lead = 0;
+            for i in 1..lead_pawns_count{
+                if indices.map_pawns[squares[i]] > indices.map_pawns[squares[lead]]{
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            table_file = file_of(squares[0]).min(file_of(squares[0] ^ 7));
+        }
+
+        if self.kind == Kind::Dtz{
+            let flags = self.pairs_data(0, table_file).flags;
+            if (flags & FLAG_STM) as usize != side && (info.key != info.key2 || info.has_pawns){
+                return Err(ProbeError::ChangeStm);
+            }
+        }
+
+        for square in *board.combined() & !lead_pawns{
+            squares[size] = square.to_index() ^ flip_squares;
+            pieces[size] = table_piece(board.piece_on(square).unwrap(), board.color_on(square).unwrap()) ^ flip_color;
+            size += 1;
+        }
+
+        // put the pieces in the order of the table
+        let d = self.pairs_data(side, table_file);
+        for i in lead_pawns_count..size - 1{
+            for j in i..size{
+                if d.pieces[i] == pieces[j]{
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece goes to the a-d files
+        if file_of(squares[0]) > 3{
+            for square in squares[..size].iter_mut(){
+                *square ^= 7;
+            }
+        }
+
+        let mut idx;
+        if info.has_pawns{
+            idx = indices.lead_pawn_idx[lead_pawns_count][squares[0]];
+            squares[1..lead_pawns_count].sort_by_key(|&x| indices.map_pawns[x]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns_count).skip(1){
+                idx += indices.binomial[i][indices.map_pawns[square]];
+            }
+        }else{
+            // without pawns the leading piece also goes below the fifth rank and below the a1-h8 diagonal
+            if rank_of(squares[0]) > 3{
+                for square in squares[..size].iter_mut(){
+                    *square ^= 0o70;
+                }
+            }
+            for i in 0..d.group_len[0]{
+                let off = off_a1h8(squares[i]);
+                if off == 0{
+                    continue;
+                }
+                if off > 0{
+                    for square in squares[i..size].iter_mut(){
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+
+            if info.has_unique_pieces{
+                // the kings and a piece together, placements with pieces on the diagonal after the others
+                let adjust1 = (squares[1] > squares[0]) as u64;
+                let adjust2 = (squares[2] > squares[0]) as u64 + (squares[2] > squares[1]) as u64;
+                let (s1, s2) = (squares[1] as u64, squares[2] as u64);
+                let (r0, r1, r2) = (rank_of(squares[0]) as u64, rank_of(squares[1]) as u64, rank_of(squares[2]) as u64);
+                idx = if off_a1h8(squares[0]) != 0{
+                    (indices.map_a1d1d4[squares[0]] as u64 * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+                }else if off_a1h8(squares[1]) != 0{
+                    (6 * 63 + r0 * 28 + indices.map_b1h1h7[squares[1]]) * 62 + s2 - adjust2
+                }else if off_a1h8(squares[2]) != 0{
+                    6 * 63 * 62 + 4 * 28 * 62 + r0 * 7 * 28 + (r1 - adjust1) * 28 + indices.map_b1h1h7[squares[2]]
+                }else{
+                    6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + r0 * 7 * 6 + (r1 - adjust1) * 6 + (r2 - adjust2)
+                };
+            }else{
+                idx = indices.map_kk[indices.map_a1d1d4[squares[0]]][squares[1]];
+            }
+        }
+
+        // the other groups by their squares, skipping the squares of the groups before them
+        idx *= d.group_idx[0];
+        let mut start = d.group_len[0];
+        let mut remaining_pawns = info.has_pawns && info.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0{
+            let len = d.group_len[next];
+            squares[start..start + len].sort();
+            let mut n = 0;
+            for i in 0..len{
+                let square = squares[start + i];
+                let adjust = squares[..start].iter().filter(|&&x| square > x).count();
+                n += indices.binomial[i + 1][square - adjust - if remaining_pawns { 8 } else { 0 }];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            start += len;
+            next += 1;
+        }
+
+        Ok((table_file, side, idx))
+    }
+
+    // the result for the wdl tables, the distance to zero in plies for the dtz tables
+    fn map_score(&self, file: usize, value: i32, wdl: i32) -> i32{
+        if self.kind == Kind::Wdl{
+            return value - 2;
+        }
+        const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+        let d = self.pairs_data(0, file);
+        let mut value = value as usize;
+        if d.flags & FLAG_MAPPED != 0{
+            let map = d.map_idx[WDL_MAP[(wdl + 2) as usize]];
+            value = if d.flags & FLAG_WIDE != 0 { read_u16(&self.bytes, map + 2 * value) } else { self.bytes[map + value] as usize };
+        }
+        let mut value = value as i32;
+        if (wdl == WDL_WIN && d.flags & FLAG_WIN_PLIES == 0) || (wdl == WDL_LOSS && d.flags & FLAG_LOSS_PLIES == 0) || wdl == WDL_CURSED_WIN || wdl == WDL_BLESSED_LOSS{
+            value *= 2;
+        }
+        value + 1
+    }
+}
+
+// the files of a material, read the first time they are probed
+#[derive(Debug)]
+struct TableFiles{
+    info: TableInfo,
+    wdl_path: PathBuf,
+    dtz_path: PathBuf,
+    wdl: OnceLock<Option<Table>>,
+    dtz: OnceLock<Option<Table>>
+}
+
+impl TableFiles{
+    fn table(&self, kind: Kind) -> Option<&Table>{
+        let (cell, path) = match kind{
+            Kind::Wdl=>(&self.wdl, &self.wdl_path),
+            Kind::Dtz=>(&self.dtz, &self.dtz_path)
+        };
+        cell.get_or_init(|| {
+            let table = fs::read(path).map_err(|e| e.to_string()).and_then(|bytes| Table::from_bytes(bytes, &self.info, kind));
+            match table{
+                Ok(table)=>Some(table),
+                Err(e)=>{
+                    if kind == Kind::Wdl || path.exists(){
+                        println!("info string failed to load {}: {}", path.display(), e);
+                    }
+                    None
+                }
+            }
+        }).as_ref()
+    }
+}
+
+fn is_capture(board: &Board, chess_move: ChessMove) -> bool{
+    board.piece_on(chess_move.get_dest()).is_some()
+        || (board.piece_on(chess_move.get_source()) == Some(Piece::Pawn) && chess_move.get_source().get_file() != chess_move.get_dest().get_file())
+}
+
+// a capture or a pawn move resets the fifty-move counter
+fn is_zeroing(board: &Board, chess_move: ChessMove) -> bool{
+    is_capture(board, chess_move) || board.piece_on(chess_move.get_source()) == Some(Piece::Pawn)
+}
+
+// the dtz of a position whose best move is a capture or a pawn move
+fn dtz_before_zeroing(wdl: i32) -> i32{
+    match wdl{
+        WDL_WIN=>1,
+        WDL_CURSED_WIN=>101,
+        WDL_BLESSED_LOSS=>-101,
+        WDL_LOSS=>-1,
+        _=>0
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Tablebases{
+    // both material keys of a table lead to it
+    tables: HashMap<u64, Arc<TableFiles>>,
+    count: usize,
+    max_pieces: u32
+}
+
+// the tablebases new searches use, none when no path is set
+static CURRENT_TABLEBASES: LazyLock<RwLock<Option<Arc<Tablebases>>>> = LazyLock::new(|| RwLock::new(None));
+
+pub fn current() -> Option<Arc<Tablebases>>{
+    CURRENT_TABLEBASES.read().unwrap().clone()
+}
+
+pub fn set_current(tablebases: Option<Tablebases>){
+    *CURRENT_TABLEBASES.write().unwrap() = tablebases.map(Arc::new);
+}
+
+impl Tablebases{
+    // the path holds one or more directories separated like the PATH variable. every .rtbw file is a table,
+    // the .rtbz file next to it is optional
+    pub fn load(path: &str) -> io::Result<Tablebases>{
+        let mut tablebases = Tablebases::default();
+        for directory in env::split_paths(path){
+            for entry in fs::read_dir(&directory)?{
+                let wdl_path = entry?.path();
+                if wdl_path.extension().and_then(|x| x.to_str()) != Some("rtbw"){
+                    continue;
+                }
+                let info = match wdl_path.file_stem().and_then(|x| x.to_str()).and_then(TableInfo::from_name){
+                    Some(info)=>info,
+                    None=>continue
+                };
+                if tablebases.tables.contains_key(&info.key){
+                    continue;
+                }
+                tablebases.max_pieces = tablebases.max_pieces.max(info.piece_count as u32);
+                tablebases.count += 1;
+                let files = Arc::new(TableFiles{
+                    info: info.clone(),
+                    dtz_path: wdl_path.with_extension("rtbz"),
+                    wdl_path,
+                    wdl: OnceLock::new(),
+                    dtz: OnceLock::new()
+                });
+                tablebases.tables.insert(info.key, files.clone());
+                tablebases.tables.insert(info.key2, files);
+            }
+        }
+        Ok(tablebases)
+    }
+
+    // the number of tables found, a table and its color flipped twin count once
+    pub fn count(&self) -> usize{
+        self.count
+    }
+
+    pub fn max_pieces(&self) -> u32{
+        self.max_pieces
+    }
+
+    // the tables have no castling and the search only probes with few enough pieces
+    pub fn covers(&self, board: &Board) -> bool{
+        board.combined().popcnt() <= self.max_pieces
+            && board.castle_rights(Color::White) == CastleRights::NoRights
+            && board.castle_rights(Color::Black) == CastleRights::NoRights
+    }
+
+    fn probe_table(&self, board: &Board, kind: Kind, wdl: i32) -> Result<i32, ProbeError>{
+        // two bare kings have no table
+        if board.combined().popcnt() == 2{
+            return Ok(WDL_DRAW);
+        }
+        let files = self.tables.get(&material_key(board)).ok_or(ProbeError::Missing)?;
+        let table = files.table(kind).ok_or(ProbeError::Missing)?;
+        table.probe(board, &files.info, wdl)
+    }
+
+    // the tables don't know about en passant and may store anything where a capture is the best move, so the
+    // captures (and the pawn moves for dtz) are searched first. the flag tells that the best move is one of them
+    fn search(&self, board: &Board, zeroing_moves: bool) -> Option<(i32, bool)>{
+        let moves: Vec<ChessMove> = MoveGen::new_legal(board).collect();
+        let mut best = WDL_LOSS;
+        let mut searched = 0;
+        for &chess_move in &moves{
+            if !is_capture(board, chess_move) && (!zeroing_moves || board.piece_on(chess_move.get_source()) != Some(Piece::Pawn)){
+                continue;
+            }
+            searched += 1;
+            let value = -self.search(&board.make_move_new(chess_move), false)?.0;
+            if value > best{
+                best = value;
+                if value >= WDL_WIN{
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // when every move was searched the table isn't needed (and its value could be wrong)
+        let no_more_moves = searched > 0 && searched == moves.len();
+        let value = if no_more_moves { best } else { self.probe_table(board, Kind::Wdl, WDL_DRAW).ok()? };
+        if best >= value{
+            return Some((best, best > WDL_DRAW || no_more_moves));
+        }
+        Some((value, false))
+    }
+
+    pub fn probe_wdl(&self, board: &Board) -> Option<Wdl>{
+        if !self.covers(board){
+            return None;
+        }
+        self.search(board, false).map(|x| Wdl::from_value(x.0))
+    }
+
+    // the plies to the next capture or pawn move with best play, positive when winning and negative when losing.
+    // cursed wins and blessed losses count 100 more, draws are 0
+    pub fn probe_dtz(&self, board: &Board) -> Option<i32>{
+        if !self.covers(board){
+            return None;
+        }
+        self.dtz(board)
+    }
+
+    fn dtz(&self, board: &Board) -> Option<i32>{
+        let (wdl, zeroing_best) = self.search(board, true)?;
+        if wdl == WDL_DRAW{
+            return Some(0);
+        }
+        if zeroing_best{
+            return Some(dtz_before_zeroing(wdl));
+        }
+        match self.probe_table(board, Kind::Dtz, wdl){
+            Ok(dtz)=>{
+                let cursed = if wdl == WDL_CURSED_WIN || wdl == WDL_BLESSED_LOSS { 100 } else { 0 };
+                return Some((dtz + cursed) * wdl.signum());
+            },
+            Err(ProbeError::Missing)=>return None,
+            Err(ProbeError::ChangeStm)=>{}
+        }
+
+        // the table stores the other side to move, take the best dtz after every move
+        let mut min_dtz = 0xFFFF;
+        for chess_move in MoveGen::new_legal(board){
+            let zeroing = is_zeroing(board, chess_move);
+            let new_board = board.make_move_new(chess_move);
+            let mut dtz = if zeroing{
+                -dtz_before_zeroing(self.search(&new_board, false)?.0)
+            }else{
+                -self.dtz(&new_board)?
+            };
+            if dtz == 1 && new_board.status() == BoardStatus::Checkmate{
+                min_dtz = 1;
+            }
+            if !zeroing{
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum(){
+                min_dtz = dtz;
+            }
+        }
+        Some(if min_dtz == 0xFFFF { -1 } else { min_dtz })
+    }
+
+    // in a won position the move that wins with the shortest distance to zero, as long as the win comes before the
+    // fifty-move rule. playing these moves converts the win, the dtz of the move is returned with it
+    pub fn root_move(&self, board: &Board, halfmove_clock: u32) -> Option<(ChessMove, i32)>{
+        if !self.covers(board){
+            return None;
+        }
+        let mut best: Option<(ChessMove, i32)> = None;
+        for chess_move in MoveGen::new_legal(board){
+            let new_board = board.make_move_new(chess_move);
+            let mut dtz = if is_zeroing(board, chess_move){
+                dtz_before_zeroing(-self.search(&new_board, false)?.0)
+            }else{
+                let dtz = -self.dtz(&new_board)?;
+                dtz + dtz.signum()
+            };
+            if new_board.status() == BoardStatus::Checkmate{
+                dtz = 1;
+            }
+            if dtz > 0 && dtz + halfmove_clock as i32 <= 99 && best.is_none_or(|x| dtz < x.1){
+                best = Some((chess_move, dtz));
+            }
+        }
+        best
+    }
+}
+
+// the writer, the inverse of the reader for the tables the tests generate. the values of the indices no
+// position has are free and repeat the value before them, then the values are replaced by pairs of symbols while
+// a pair is frequent enough and the symbols get a canonical huffman code
+#[cfg(test)]
+pub mod writer{
+    use super::*;
+
+    const WRITE_BLOCK_BITS: u8 = 6;
+    const WRITE_SPAN_BITS: u8 = 8;
+    // the symbol ids have 12 bits and 0xFFF marks a value
+    const WRITE_MAX_SYMBOLS: usize = 0xFFF;
+    const WRITE_MIN_PAIR_COUNT: u32 = 8;
+    const WRITE_MAX_SYMBOL_VALUES: u32 = 4096;
+    const WRITE_MAX_BLOCK_VALUES: usize = 32768;
+    const WRITE_MAX_CODE_LEN: usize = 32;
+
+    // the result and the dtz of a position at an index of the table being written
+    type Slot = Option<(i32, u16)>;
+
+    // the bytes of the compressed values of a side, in the four places the file keeps them
+    #[derive(Debug, Default)]
+    struct Compressed{
+        sizes: Vec<u8>,
+        sparse_index: Vec<u8>,
+        block_lengths: Vec<u8>,
+        data: Vec<u8>
+    }
+
+    // the lengths of the huffman code of the symbols, 0 for the symbols that aren't used
+    fn code_lengths(frequencies: &[u64]) -> Vec<usize>{
+        let used: Vec<usize> = (0..frequencies.len()).filter(|&x| frequencies[x] > 0).collect();
+        let mut lengths = vec![0; frequencies.len()];
+        if used.len() == 1{
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+        let mut frequencies = frequencies.to_vec();
+        loop{
+            // the nodes after the symbols are the merged ones, the heap pops the rarest first
+            let mut parent = vec![usize::MAX; frequencies.len()];
+            let mut heap: std::collections::BinaryHeap<std::cmp::Reverse<(u64, usize)>> = used.iter().map(|&x| std::cmp::Reverse((frequencies[x], x))).collect();
+            while heap.len() > 1{
+                let std::cmp::Reverse((first, a)) = heap.pop().unwrap();
+                let std::cmp::Reverse((second, b)) = heap.pop().unwrap();
+                parent.push(usize::MAX);
+                let node = parent.len() - 1;
+                parent[a] = node;
+                parent[b] = node;
+                heap.push(std::cmp::Reverse((first + second, node)));
+            }
+            for &symbol in &used{
+                let mut node = symbol;
+                let mut len = 0;
+                while parent[node] != usize::MAX{
+                    node = parent[node];
+                    len += 1;
+                }
+                lengths[symbol] = len;
+            }
+            if used.iter().all(|&x| lengths[x] <= WRITE_MAX_CODE_LEN){
+                return lengths;
+            }
+            // flatter frequencies give shorter codes to the rare symbols
+            for &symbol in &used{
+                frequencies[symbol] = frequencies[symbol] / 2 + 1;
+            }
+        }
+    }
+
+    fn compress(values: &[u16], flags: u8) -> Result<Compressed, String>{
+        let mut leaves = values.to_vec();
+        leaves.sort();
+        leaves.dedup();
+        if leaves.len() == 1 && leaves[0] <= 0xFF{
+            return Ok(Compressed{sizes: vec![flags | FLAG_SINGLE_VALUE, leaves[0] as u8], ..Default::default()});
+        }
+        if leaves.iter().any(|&x| x >= 0xFFF){
+            return Err("a value is too large to compress".to_string());
+        }
+
+        // the symbols are a value (right is 0xFFF) or a pair of symbols
+        let mut pairs: Vec<(usize, usize)> = leaves.iter().map(|&x| (x as usize, 0xFFF)).collect();
+        let mut symbol_values: Vec<u32> = vec![1; leaves.len()];
+        let mut sequence: Vec<usize> = values.iter().map(|x| leaves.binary_search(x).unwrap()).collect();
+        while pairs.len() < WRITE_MAX_SYMBOLS{
+            let mut counts: HashMap<(usize, usize), u32> = HashMap::new();
+            let mut i = 0;
+            while i + 1 < sequence.len(){
+                let pair = (sequence[i], sequence[i + 1]);
+                if symbol_values[pair.0] + symbol_values[pair.1] <= WRITE_MAX_SYMBOL_VALUES{
+                    *counts.entry(pair).or_insert(0) += 1;
+                }
+                // a run of the same symbol holds half as many pairs as it has neighbours
+                i += if pair.0 == pair.1 && sequence.get(i + 2) == Some(&pair.0) { 2 } else { 1 };
+            }
+            let best = counts.into_iter().max_by_key(|&(pair, count)| (count, std::cmp::Reverse(pair)));
+            let pair = match best{
+                Some((pair, count)) if count >= WRITE_MIN_PAIR_COUNT=>pair,
+                _=>break
+            };
+            let symbol = pairs.len();
+            pairs.push(pair);
+            symbol_values.push(symbol_values[pair.0] + symbol_values[pair.1]);
+            let mut merged = Vec::with_capacity(sequence.len());
+            let mut i = 0;
+            while i < sequence.len(){
+                if i + 1 < sequence.len() && (sequence[i], sequence[i + 1]) == pair{
+                    merged.push(symbol);
+                    i += 2;
+                }else{
+                    merged.push(sequence[i]);
+                    i += 1;
+                }
+            }
+            sequence = merged;
+        }
+
+        // the canonical code numbers the symbols from the longest code to the shortest, the unused ones come last
+        let mut frequencies = vec![0u64; pairs.len()];
+        for &symbol in &sequence{
+            frequencies[symbol] += 1;
+        }
+        let lengths = code_lengths(&frequencies);
+        let mut order: Vec<usize> = (0..pairs.len()).collect();
+        order.sort_by_key(|&x| (lengths[x] == 0, std::cmp::Reverse(lengths[x]), x));
+        let mut renumber = vec![0; pairs.len()];
+        for (id, &symbol) in order.iter().enumerate(){
+            renumber[symbol] = id;
+        }
+        let max_len = *lengths.iter().max().unwrap();
+        let min_len = *lengths.iter().filter(|&&x| x > 0).min().unwrap();
+        let count = |len: usize| lengths.iter().filter(|&&x| x == len).count() as u64;
+        let mut lowest = vec![0u64; max_len - min_len + 1];
+        let mut base = vec![0u64; max_len - min_len + 1];
+        for i in (0..max_len - min_len).rev(){
+            lowest[i] = lowest[i + 1] + count(min_len + i + 1);
+            base[i] = (base[i + 1] + count(min_len + i + 1)) / 2;
+        }
+
+        let mut sizes = vec![flags, WRITE_BLOCK_BITS, WRITE_SPAN_BITS, 0];
+        let mut btree = Vec::new();
+        for &symbol in &order{
+            let (left, right) = pairs[symbol];
+            let (left, right) = if right == 0xFFF { (left, right) } else { (renumber[left], renumber[right]) };
+            btree.extend([left as u8, (left >> 8) as u8 | ((right & 0xF) << 4) as u8, (right >> 4) as u8]);
+        }
+        if pairs.len() & 1 != 0{
+            btree.push(0);
+        }
+
+        // the blocks are filled with whole symbols, the length of a block is the number of values in it minus one
+        let block_size = 1usize << WRITE_BLOCK_BITS;
+        let mut data = Vec::new();
+        let mut block_starts = vec![0usize];
+        let mut bits = 0;
+        let mut block_values = 0;
+        data.resize(block_size, 0);
+        for &symbol in &sequence{
+            let len = lengths[symbol];
+            let values = symbol_values[symbol] as usize;
+            if bits + len > 8 * block_size || block_values + values > WRITE_MAX_BLOCK_VALUES{
+                block_starts.push(block_starts.last().unwrap() + block_values);
+                data.resize(data.len() + block_size, 0);
+                bits = 0;
+                block_values = 0;
+            }
+            let i = len - min_len;
+            let code = base[i] + renumber[symbol] as u64 - lowest[i];
+            let start = data.len() - block_size;
+            for bit in 0..len{
+                if code >> (len - 1 - bit) & 1 != 0{
+                    data[start + (bits + bit) / 8] |= 0x80 >> ((bits + bit) % 8);
+                }
+            }
+            bits += len;
+            block_values += values;
+        }
+        block_starts.push(values.len());
+        let num_blocks = block_starts.len() - 1;
+        sizes.extend((num_blocks as u32).to_le_bytes());
+        sizes.extend([max_len as u8, min_len as u8]);
+        for x in lowest{
+            sizes.extend((x as u16).to_le_bytes());
+        }
+        sizes.extend((pairs.len() as u16).to_le_bytes());
+        sizes.extend(btree);
+
+        let mut block_lengths = Vec::new();
+        for block in 0..num_blocks{
+            block_lengths.extend(((block_starts[block + 1] - block_starts[block] - 1) as u16).to_le_bytes());
+        }
+
+        // every span values an entry points at the value in the middle of the span, past the end from the last block
+        let span = 1usize << WRITE_SPAN_BITS;
+        let mut sparse_index = Vec::new();
+        for k in 0..values.len().div_ceil(span){
+            let target = k * span + span / 2;
+            let block = block_starts[1..].partition_point(|&x| x <= target).min(num_blocks - 1);
+            sparse_index.extend((block as u32).to_le_bytes());
+            sparse_index.extend(((target - block_starts[block]) as u16).to_le_bytes());
+        }
+        Ok(Compressed{sizes, sparse_index, block_lengths, data})
+    }
+
+    // the pieces in the order the written table numbers them: the pawns, or the kings and a piece that is alone
+    // first, then the pieces of each kind together
+    fn written_pieces(counts: &[[usize; 6]; 2], info: &TableInfo) -> Vec<u8>{
+        let colors = [Color::White, Color::Black];
+        let mut counts = *counts;
+        let mut pieces = Vec::new();
+        for color in 0..2{
+            if counts[color][0] > 0{
+                pieces.extend(vec![table_piece(Piece::Pawn, colors[color]); counts[color][0]]);
+                counts[color][0] = 0;
+            }
+        }
+        pieces.push(table_piece(Piece::King, Color::White));
+        pieces.push(table_piece(Piece::King, Color::Black));
+        counts[0][5] = 0;
+        counts[1][5] = 0;
+        if !info.has_pawns && info.has_unique_pieces{
+            let (color, piece) = (0..2).flat_map(|color| (1..5).rev().map(move |piece| (color, piece))).find(|&(color, piece)| counts[color][piece] == 1).unwrap();
+            pieces.push(table_piece(ALL_PIECES[piece], colors[color]));
+            counts[color][piece] = 0;
+        }
+        for color in 0..2{
+            for piece in (1..5).rev(){
+                pieces.extend(vec![table_piece(ALL_PIECES[piece], colors[color]); counts[color][piece]]);
+            }
+        }
+        pieces
+    }
+
+    // a table from the value of every position of its material, with the results from the point of view of the side
+    // to move and the dtz like probe_dtz gives it. the dtz table stores the stronger side to move
+    fn write_table(name: &str, kind: Kind, positions: &[(Board, Wdl, i32)]) -> Result<Vec<u8>, String>{
+        let info = TableInfo::from_name(name).ok_or("not a table name")?;
+        let counts = name_counts(name).unwrap();
+        if info.pawn_count[1] > 0{
+            return Err("tables with pawns on both sides can't be written".to_string());
+        }
+        let mut bytes = if kind == Kind::Wdl { WDL_MAGIC } else { DTZ_MAGIC }.to_vec();
+        bytes.push((info.key != info.key2) as u8 | (info.has_pawns as u8) << 1);
+        let pieces = written_pieces(&counts, &info);
+        let files = if info.has_pawns { 4 } else { 1 };
+        for _ in 0..files{
+            bytes.push(0);
+            bytes.extend(pieces.iter().map(|&x| x | x << 4));
+        }
+        if bytes.len() & 1 != 0{
+            bytes.push(0);
+        }
+        let (pairs, _) = read_header(&bytes, &info, kind)?;
+        let sides = pairs[0].len();
+        let table = Table{kind, bytes: bytes.clone(), pairs};
+
+        // the result, or the dtz of a win or a loss, at every index
+        let mut slots: Vec<Vec<Vec<Slot>>> = (0..files).map(|file| (0..sides).map(|side| vec![None; table.pairs[file][side].size() as usize]).collect()).collect();
+        for (board, wdl, dtz) in positions{
+            let wdl = match wdl{
+                Wdl::Win=>WDL_WIN,
+                Wdl::Draw=>WDL_DRAW,
+                Wdl::Loss=>WDL_LOSS,
+                _=>return Err("cursed wins and blessed losses can't be written".to_string())
+            };
+            let (file, side, idx) = match table.index(board, &info){
+                Ok(x)=>x,
+                Err(_)=>continue
+            };
+            let value = match kind{
+                Kind::Wdl=>(wdl + 2, 0),
+                Kind::Dtz if wdl != WDL_DRAW=>(wdl, (dtz.abs() - 1) as u16),
+                Kind::Dtz=>continue
+            };
+            let slot = &mut slots[file.min(files - 1)][side % sides][idx as usize];
+            if slot.is_some_and(|x| x != value){
+                return Err(format!("two values for the index of {}", board));
+            }
+            *slot = Some(value);
+        }
+
+        let flags = if kind == Kind::Wdl { 0 } else { FLAG_MAPPED | FLAG_WIN_PLIES | FLAG_LOSS_PLIES };
+        let mut compressed = Vec::new();
+        let mut maps = Vec::new();
+        for file_slots in &slots{
+            // a dtz is stored as its place in the list of the dtz of the wins or of the losses
+            let mut lists = [Vec::new(), Vec::new()];
+            for &(wdl, dtz) in file_slots.iter().flatten().flatten(){
+                if kind == Kind::Dtz{
+                    lists[(wdl < 0) as usize].push(dtz);
+                }
+            }
+            for list in lists.iter_mut(){
+                list.sort();
+                list.dedup();
+                if list.last().is_some_and(|&x| x > 0xFF){
+                    return Err("a dtz is too large to write".to_string());
+                }
+                maps.push(list.len() as u8);
+                maps.extend(list.iter().map(|&x| x as u8));
+            }
+            if kind == Kind::Dtz{
+                maps.extend([0, 0]);
+            }
+            let value = |(wdl, dtz): (i32, u16)| if kind == Kind::Wdl { wdl as u16 } else { lists[(wdl < 0) as usize].binary_search(&dtz).unwrap() as u16 };
+            for side_slots in file_slots{
+                let mut previous = side_slots.iter().flatten().next().map_or(0, |&x| value(x));
+                let values: Vec<u16> = side_slots.iter().map(|slot| {
+                    if let Some(x) = *slot{
+                        previous = value(x);
+                    }
+                    previous
+                }).collect();
+                compressed.push(compress(&values, flags)?);
+            }
+        }
+
+        for side in &compressed{
+            bytes.extend(&side.sizes);
+        }
+        if kind == Kind::Dtz{
+            bytes.extend(maps);
+            if bytes.len() & 1 != 0{
+                bytes.push(0);
+            }
+        }
+        for side in &compressed{
+            bytes.extend(&side.sparse_index);
+        }
+        for side in &compressed{
+            bytes.extend(&side.block_lengths);
+        }
+        for side in &compressed{
+            if !side.data.is_empty(){
+                bytes.resize((bytes.len() + 0x3F) & !0x3F, 0);
+                bytes.extend(&side.data);
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub fn write_wdl(name: &str, positions: &[(Board, Wdl, i32)]) -> Result<Vec<u8>, String>{
+        write_table(name, Kind::Wdl, positions)
+    }
+
+    pub fn write_dtz(name: &str, positions: &[(Board, Wdl, i32)]) -> Result<Vec<u8>, String>{
+        write_table(name, Kind::Dtz, positions)
+    }
+}
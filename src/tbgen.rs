@@ -0,0 +1,306 @@
+use chess::{Board, BoardBuilder, BoardStatus, Color, MoveGen, Piece, ALL_SQUARES};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::syzygy::{self, Wdl};
+
+// a generator of syzygy tables for small materials, so the prober can be tested on tables whose every value is
+// known. a position is numbered by the square of every piece in the order of the material and the side to move, the
+// pieces of a kind on ascending squares. the results are found backwards from the checkmates: a position is won
+// when a move reaches a lost one and lost when every move reaches a won one, the rest are draws. the distance to
+// zero is counted the same way over the moves that aren't captures or pawn moves. captures and promotions lead to
+// smaller materials that are solved before. the tables know nothing about en passant and the fifty-move rule, so
+// materials with pawns on both sides and cursed wins are refused
+const INVALID: i8 = i8::MIN;
+const UNKNOWN: i8 = 1;
+const LOSS: i8 = -2;
+const DRAW: i8 = 0;
+const WIN: i8 = 2;
+
+// a move in the list of a position: the index of the next position or, for another material, its result
+const ZEROING: u32 = 1 << 31;
+const OTHER_MATERIAL: u32 = 1 << 30;
+
+const PIECE_ORDER: [Piece; 6] = [Piece::King, Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight, Piece::Pawn];
+
+#[derive(Debug)]
+struct Solution{
+    pieces: Vec<(Piece, Color)>,
+    // per position: the result for the side to move and the distance to zero of a win or a loss
+    wdl: Vec<i8>,
+    dtz: Vec<u8>
+}
+
+fn piece_char(piece: Piece) -> char{
+    match piece{
+        Piece::Pawn=>'P',
+        Piece::Knight=>'N',
+        Piece::Bishop=>'B',
+        Piece::Rook=>'R',
+        Piece::Queen=>'Q',
+        Piece::King=>'K'
+    }
+}
+
+// the name of the material with white first, like KRvK
+fn material_name(board: &Board) -> String{
+    let mut name = String::new();
+    for color in [Color::White, Color::Black]{
+        if color == Color::Black{
+            name.push('v');
+        }
+        for piece in PIECE_ORDER{
+            for _ in 0..(board.pieces(piece) & board.color_combined(color)).popcnt(){
+                name.push(piece_char(piece));
+            }
+        }
+    }
+    name
+}
+
+// the pieces of a name with white first, the pieces of a kind together
+fn material_pieces(name: &str) -> Option<Vec<(Piece, Color)>>{
+    let sides: Vec<&str> = name.split('v').collect();
+    if sides.len() != 2{
+        return None;
+    }
+    let mut pieces = Vec::new();
+    for (side, color) in sides.iter().zip([Color::White, Color::Black]){
+        for piece in PIECE_ORDER{
+            let count = side.chars().filter(|&c| c == piece_char(piece)).count();
+            pieces.extend(vec![(piece, color); count]);
+        }
+        if side.chars().any(|c| !"KQRBNP".contains(c)) || side.chars().filter(|&c| c == 'K').count() != 1{
+            return None;
+        }
+    }
+    Some(pieces)
+}
+
+// without pawns the board is turned until the white king is in the a1-d1-d4 triangle, its square numbered 0..9
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+fn has_symmetries(pieces: &[(Piece, Color)]) -> bool{
+    pieces.iter().all(|x| x.0 != Piece::Pawn)
+}
+
+// the number of squares of the first piece, the white king
+fn first_squares(pieces: &[(Piece, Color)]) -> usize{
+    if has_symmetries(pieces) { TRIANGLE.len() } else { 64 }
+}
+
+fn size(pieces: &[(Piece, Color)]) -> usize{
+    (2 * first_squares(pieces)) << (6 * (pieces.len() - 1))
+}
+
+fn index(pieces: &[(Piece, Color)], board: &Board) -> usize{
+    let mut squares = Vec::with_capacity(pieces.len());
+    let mut i = 0;
+    while i < pieces.len(){
+        let (piece, color) = pieces[i];
+        for square in board.pieces(piece) & board.color_combined(color){
+            squares.push(square.to_index());
+            i += 1;
+        }
+    }
+    let mut first = squares[0];
+    if has_symmetries(pieces){
+        let flip = if first & 7 > 3 { 7 } else { 0 } ^ if first >> 3 > 3 { 0o70 } else { 0 };
+        let transpose = (first ^ flip) >> 3 > (first ^ flip) & 7;
+        for square in squares.iter_mut(){
+            *square ^= flip;
+            if transpose{
+                *square = ((*square >> 3) | (*square << 3)) & 63;
+            }
+        }
+        // the pieces of a kind stay on ascending squares
+        let mut start = 0;
+        for i in 1..=pieces.len(){
+            if i == pieces.len() || pieces[i] != pieces[start]{
+                squares[start..i].sort();
+                start = i;
+            }
+        }
+        first = TRIANGLE.iter().position(|&x| x == squares[0]).unwrap();
+    }
+    let rest = squares[1..].iter().rev().fold(0, |rest, &square| (rest << 6) | square);
+    (rest * first_squares(pieces) + first) * 2 + board.side_to_move().to_index()
+}
+
+// the position of an index, none when the pieces can't stand like that
+fn position(pieces: &[(Piece, Color)], index: usize) -> Option<Board>{
+    let first = (index >> 1) % first_squares(pieces);
+    let rest = (index >> 1) / first_squares(pieces);
+    let mut squares = vec![if has_symmetries(pieces) { TRIANGLE[first] } else { first }];
+    squares.extend((1..pieces.len()).map(|i| (rest >> (6 * (i - 1))) & 63));
+    let mut builder = BoardBuilder::new();
+    for (i, &(piece, color)) in pieces.iter().enumerate(){
+        if squares[..i].contains(&squares[i]) || (i > 0 && pieces[i - 1] == pieces[i] && squares[i - 1] > squares[i]){
+            return None;
+        }
+        if piece == Piece::Pawn && (squares[i] < 8 || squares[i] >= 56){
+            return None;
+        }
+        builder.piece(ALL_SQUARES[squares[i]], piece, color);
+    }
+    let kings: Vec<usize> = (0..pieces.len()).filter(|&i| pieces[i].0 == Piece::King).map(|i| squares[i]).collect();
+    if chess::get_king_moves(ALL_SQUARES[kings[0]]) & chess::BitBoard::from_square(ALL_SQUARES[kings[1]]) != chess::EMPTY{
+        return None;
+    }
+    builder.side_to_move(if index & 1 == 0 { Color::White } else { Color::Black });
+    Board::try_from(&builder).ok()
+}
+
+// solves the material and the materials its captures and promotions lead to
+fn solve(name: &str, solutions: &mut HashMap<String, Solution>) -> Result<(), String>{
+    if solutions.contains_key(name){
+        return Ok(());
+    }
+    let pieces = material_pieces(name).ok_or(format!("{} is not a material", name))?;
+    let has_pawns = |color| pieces.contains(&(Piece::Pawn, color));
+    if has_pawns(Color::White) && has_pawns(Color::Black){
+        return Err("materials with pawns on both sides can't be generated".to_string());
+    }
+    let size = size(&pieces);
+
+    // the moves of every position, and the results that are known right away
+    let mut wdl = vec![INVALID; size];
+    let mut moves = Vec::new();
+    let mut first_move = vec![0u32; size + 1];
+    for i in 0..size{
+        first_move[i] = moves.len() as u32;
+        let board = match position(&pieces, i){
+            Some(board)=>board,
+            None=>continue
+        };
+        wdl[i] = match board.status(){
+            BoardStatus::Checkmate=>LOSS,
+            BoardStatus::Stalemate=>DRAW,
+            BoardStatus::Ongoing=>UNKNOWN
+        };
+        for chess_move in MoveGen::new_legal(&board){
+            let zeroing = board.piece_on(chess_move.get_dest()).is_some() || board.piece_on(chess_move.get_source()) == Some(Piece::Pawn);
+            let new_board = board.make_move_new(chess_move);
+            let next = if new_board.combined().popcnt() == board.combined().popcnt() && chess_move.get_promotion().is_none(){
+                index(&pieces, &new_board) as u32
+            }else if new_board.combined().popcnt() == 2{
+                OTHER_MATERIAL | (DRAW + 2) as u32
+            }else{
+                let new_name = material_name(&new_board);
+                solve(&new_name, solutions)?;
+                let other = &solutions[&new_name];
+                OTHER_MATERIAL | (other.wdl[index(&other.pieces, &new_board)] + 2) as u32
+            };
+            moves.push(next | if zeroing { ZEROING } else { 0 });
+        }
+    }
+    first_move[size] = moves.len() as u32;
+    let moves_of = |i: usize| &moves[first_move[i] as usize..first_move[i + 1] as usize];
+    let result = |wdl: &[i8], next: u32| if next & OTHER_MATERIAL != 0 { (next & 0xFF) as i8 - 2 } else { wdl[(next & !ZEROING) as usize] };
+
+    let mut changed = true;
+    while changed{
+        changed = false;
+        for i in 0..size{
+            if wdl[i] != UNKNOWN{
+                continue;
+            }
+            let mut best = LOSS;
+            let mut unknown = false;
+            for &next in moves_of(i){
+                match result(&wdl, next){
+                    UNKNOWN=>unknown = true,
+                    x=>best = best.max(-x)
+                }
+            }
+            if best == WIN || !unknown{
+                wdl[i] = best;
+                changed = true;
+            }
+        }
+    }
+    for x in wdl.iter_mut(){
+        if *x == UNKNOWN{
+            *x = DRAW;
+        }
+    }
+
+    // the distance to zero grows by one ply a pass: a win takes the shortest way to a lost position, a loss the
+    // longest way to a won one. a checkmate and a capture or a pawn move that keeps the result are one ply
+    let mated = |i: usize| wdl[i] == LOSS && moves_of(i).is_empty();
+    let mut dtz = vec![0u8; size];
+    let mut ply = 1;
+    loop{
+        let mut found = Vec::new();
+        for i in 0..size{
+            if dtz[i] != 0 || (wdl[i] != WIN && wdl[i] != LOSS){
+                continue;
+            }
+            if wdl[i] == WIN{
+                let wins = moves_of(i).iter().any(|&next| {
+                    if next & (ZEROING | OTHER_MATERIAL) != 0{
+                        return ply == 1 && result(&wdl, next) == LOSS;
+                    }
+                    let next = next as usize;
+                    wdl[next] == LOSS && if mated(next) { ply == 1 } else { ply > 1 && dtz[next] as usize == ply - 1 }
+                });
+                if wins{
+                    found.push((i, ply));
+                }
+            }else{
+                let mut longest = 0;
+                let mut unknown = false;
+                for &next in moves_of(i){
+                    if next & (ZEROING | OTHER_MATERIAL) == 0{
+                        unknown |= dtz[next as usize] == 0;
+                        longest = longest.max(dtz[next as usize] as usize);
+                    }
+                }
+                if !unknown{
+                    found.push((i, longest + 1));
+                }
+            }
+        }
+        if found.is_empty(){
+            break;
+        }
+        for (i, x) in found{
+            if x > 100{
+                return Err(format!("{} has cursed wins", name));
+            }
+            dtz[i] = x as u8;
+        }
+        ply += 1;
+    }
+    if (0..size).any(|i| (wdl[i] == WIN || wdl[i] == LOSS) && dtz[i] == 0){
+        return Err(format!("the distance to zero of {} doesn't converge", name));
+    }
+    solutions.insert(name.to_string(), Solution{pieces, wdl, dtz});
+    Ok(())
+}
+
+// writes NAME.rtbw and NAME.rtbz to the directory
+pub fn generate(name: &str, directory: &str) -> io::Result<()>{
+    let error = |e: String| io::Error::new(io::ErrorKind::InvalidInput, e);
+    let mut solutions = HashMap::new();
+    solve(name, &mut solutions).map_err(error)?;
+    let solution = &solutions[name];
+    let mut positions = Vec::new();
+    for i in 0..solution.wdl.len(){
+        if solution.wdl[i] == INVALID{
+            continue;
+        }
+        let board = position(&solution.pieces, i).unwrap();
+        let (wdl, sign) = match solution.wdl[i]{
+            WIN=>(Wdl::Win, 1),
+            LOSS=>(Wdl::Loss, -1),
+            _=>(Wdl::Draw, 0)
+        };
+        positions.push((board, wdl, sign * solution.dtz[i] as i32));
+    }
+    let path = Path::new(directory).join(name);
+    fs::write(path.with_extension("rtbw"), syzygy::writer::write_wdl(name, &positions).map_err(error)?)?;
+    fs::write(path.with_extension("rtbz"), syzygy::writer::write_dtz(name, &positions).map_err(error)?)?;
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use chess::Board;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+// one searched node. nodes are stored in the order they were entered so the list is a pre-order walk of the tree
+#[derive(Debug, Clone)]
+pub struct TraceNode{
+    pub level: usize,
+    pub kind: &'static str,
+    pub label: String,
+    pub alpha: i32,
+    pub beta: i32,
+    pub depth: u32,
+    pub reduction: u32,
+    pub tt_hit: bool,
+    pub pruning: Option<&'static str>,
+    pub score: Option<i32>
+}
+
+#[derive(Debug)]
+pub struct Tracer{
+    header: String,
+    nodes: Vec<TraceNode>,
+    stack: Vec<usize>,
+    last_left: Option<usize>
+}
+
+impl Tracer{
+    pub fn new(board: &Board, depth: u32) -> Tracer{
+        let mut tracer = Tracer{header: format!("trace depth {} fen {}", depth, board), nodes: Vec::new(), stack: Vec::new(), last_left: None};
+        tracer.enter("root", -1000000, 1000000, depth);
+        tracer
+    }
+
+    pub fn enter(&mut self, kind: &'static str, alpha: i32, beta: i32, depth: u32){
+        self.nodes.push(TraceNode{level: self.stack.len(), kind, label: "-".to_string(), alpha, beta, depth, reduction: 0, tt_hit: false, pruning: None, score: None});
+        self.stack.push(self.nodes.len() - 1);
+    }
+
+    pub fn leave(&mut self, score: i32){
+        if let Some(index) = self.stack.pop(){
+            self.nodes[index].score = Some(score);
+            self.last_left = Some(index);
+        }
+    }
+
+    pub fn tt_hit(&mut self){
+        if let Some(&index) = self.stack.last(){
+            self.nodes[index].tt_hit = true;
+        }
+    }
+
+    pub fn prune(&mut self, reason: &'static str){
+        if let Some(&index) = self.stack.last(){
+            self.nodes[index].pruning = Some(reason);
+        }
+    }
+
+    // the node doesn't know which move led to it, so the parent names the child after it returns
+    pub fn name_last_child(&mut self, label: String, reduction: u32){
+        if let Some(index) = self.last_left{
+            self.nodes[index].label = label;
+            self.nodes[index].reduction = reduction;
+        }
+    }
+
+    // a child that was not searched normally (the razoring quiescence search) carries the reason on its own node
+    pub fn prune_last_child(&mut self, reason: &'static str){
+        if let Some(index) = self.last_left{
+            self.nodes[index].pruning = Some(reason);
+        }
+    }
+
+    pub fn finish(&mut self, score: i32){
+        while !self.stack.is_empty(){
+            self.leave(score);
+        }
+    }
+
+    pub fn nodes(&self) -> &Vec<TraceNode>{
+        &self.nodes
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()>{
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", self.header)?;
+        for node in &self.nodes{
+            writeln!(writer, "{}", format_node(node))?;
+        }
+        writer.flush()
+    }
+}
+
+
+fn format_node(node: &TraceNode) -> String{
+    let score = match node.score{
+        None=>"-".to_string(),
+        Some(score)=>score.to_string()
+    };
+    format!("{}{} {} depth {} window [{}, {}] red {} tt {} prune {} score {}",
+        "  ".repeat(node.level), node.label, node.kind, node.depth, node.alpha, node.beta, node.reduction,
+        if node.tt_hit {"hit"} else {"-"}, node.pruning.unwrap_or("-"), score)
+}
+
+
+// prints the lines of a trace file that lie on the given move path, followed by the children of the last node of the path.
+// a move may show up more than once under the same parent (zero window search and re-search), all of them are followed.
+pub fn replay<W: Write>(path: &str, move_path: &[&str], out: &mut W) -> io::Result<()>{
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+    if let Some(header) = lines.next(){
+        writeln!(out, "{}", header?)?;
+    }
+    // matched[level] is true when the current node at that level is on the path
+    let mut matched: Vec<bool> = Vec::new();
+    for line in lines{
+        let line = line?;
+        let trimmed = line.trim_start();
+        let level = (line.len() - trimmed.len()) / 2;
+        let label = trimmed.split(' ').next().unwrap_or("-");
+        matched.truncate(level);
+
+        // the root (level 0) is always on the path, level n is the n-th move of the path
+        let parent_on_path = matched.iter().all(|x| *x);
+        let on_path = parent_on_path && (level == 0 || (level <= move_path.len() && move_path[level - 1] == label));
+        if parent_on_path && level <= move_path.len() + 1 && (on_path || level == move_path.len() + 1){
+            writeln!(out, "{}", line)?;
+        }
+        matched.push(on_path);
+    }
+    Ok(())
+}
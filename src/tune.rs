@@ -0,0 +1,163 @@
+use chess::{Board, BoardStatus, Color};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
+use crate::{evaluation, endgame, params::{self, EvalParams}};
+
+// a position of the dataset with the result of the game it was played in, 1 for a white win, 0.5 for a draw and 0 for a black win
+pub struct Sample{
+    pub board: Board,
+    pub result: f64
+}
+
+// the result can be a pgn result ("1-0", "0-1", "1/2-1/2") anywhere in the line or a bracketed number ("[1.0]", "[0.5]", "[0]")
+pub fn parse_result(line: &str) -> Option<f64>{
+    if line.contains("1/2-1/2") || line.contains("[0.5]"){
+        return Some(0.5);
+    }else if line.contains("1-0") || line.contains("[1.0]") || line.contains("[1]"){
+        return Some(1.);
+    }else if line.contains("0-1") || line.contains("[0.0]") || line.contains("[0]"){
+        return Some(0.);
+    }
+    None
+}
+
+// an epd line only has the first four fields of a fen, the move counters are added when they are missing
+pub fn parse_sample(line: &str) -> Option<Sample>{
+    let result = parse_result(line)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 4{
+        return None;
+    }
+    let mut fen = fields[..4].join(" ");
+    if fields.len() >= 6 && fields[4].parse::<u32>().is_ok() && fields[5].trim_end_matches(';').parse::<u32>().is_ok(){
+        fen = format!("{} {} {}", fen, fields[4], fields[5].trim_end_matches(';'));
+    }else{
+        fen.push_str(" 0 1");
+    }
+    let board = Board::from_str(&fen).ok()?;
+    // finished games don't tell anything about the evaluation
+    if board.status() != BoardStatus::Ongoing{
+        return None;
+    }
+    // neither are the known endgames and the scaled down endgames, the weights only make the unscaled evaluation
+    if endgame::evaluate(&board).is_some() || [Color::White, Color::Black].iter().any(|&x| endgame::scale_factor(&board, x) != endgame::SCALE_NORMAL){
+        return None;
+    }
+    Some(Sample{board, result})
+}
+
+pub fn load_dataset(path: &str) -> io::Result<Vec<Sample>>{
+    let reader = BufReader::new(File::open(path)?);
+    let mut samples = Vec::new();
+    for line in reader.lines(){
+        if let Some(sample) = parse_sample(&line?){
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+// the expected result of a position from its score (white's point of view)
+fn sigmoid(score: i32, k: f64) -> f64{
+    1. / (1. + 10f64.powf(-k * score as f64 / 400.))
+}
+
+// the mean squared difference between the game results and the expected results. the dataset is split between threads
+pub fn error(samples: &[Sample], params: &EvalParams, k: f64) -> f64{
+    if samples.is_empty(){
+        return 0.;
+    }
+    let threads = thread::available_parallelism().map(|x| x.get()).unwrap_or(1);
+    let chunk_size = samples.len().div_ceil(threads);
+    let total: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = samples.chunks(chunk_size).map(|chunk| scope.spawn(move || {
+            chunk.iter().map(|sample| {
+                let expected = sigmoid(evaluation::trace_with_params(&sample.board, params).value(), k);
+                (sample.result - expected) * (sample.result - expected)
+            }).sum::<f64>()
+        })).collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).sum()
+    });
+    total / samples.len() as f64
+}
+
+// the scaling constant that fits the current evaluation best, found by narrowing down the range around the best value
+pub fn fit_k(samples: &[Sample], params: &EvalParams) -> f64{
+    let mut best_k = 1.;
+    let mut best_error = error(samples, params, best_k);
+    let mut step = 0.5;
+    while step > 0.001{
+        let mut improved = true;
+        while improved{
+            improved = false;
+            for k in [best_k - step, best_k + step]{
+                if k <= 0.{
+                    continue;
+                }
+                let k_error = error(samples, params, k);
+                if k_error < best_error{
+                    best_error = k_error;
+                    best_k = k;
+                    improved = true;
+                }
+            }
+        }
+        step /= 10.;
+    }
+    best_k
+}
+
+// local search: every weight is moved by one in both directions and the change is kept when it lowers the error.
+// the passes go on until a pass doesn't change anything, the parameters are written after every pass
+pub fn tune(samples: &[Sample], params: &EvalParams, k: f64, passes: u32, output: &str) -> io::Result<EvalParams>{
+    let names = params.vector_names();
+    let mut vector = params.to_vector();
+    let mut current = params.clone();
+    let mut best_error = error(samples, &current, k);
+    println!("tuning {} weights on {} positions, k {:.3}, error {:.6}", vector.len(), samples.len(), k, best_error);
+
+    for pass in 1..=passes{
+        let now = Instant::now();
+        let mut changed = 0;
+        for index in 0..vector.len(){
+            for step in [1, -1]{
+                vector[index] += step;
+                current.from_vector(&vector);
+                let new_error = error(samples, &current, k);
+                if new_error < best_error{
+                    best_error = new_error;
+                    changed += 1;
+                    break;
+                }
+                vector[index] -= step;
+            }
+        }
+        current.from_vector(&vector);
+        current.write(output)?;
+        println!("pass {} changed {} weights, error {:.6}, took {:?}", pass, changed, best_error, now.elapsed());
+        if changed == 0{
+            break;
+        }
+    }
+
+    for ((name, tuned), original) in names.iter().zip(&vector).zip(params.to_vector()){
+        if *tuned != original{
+            println!("{} {} -> {}", name, original, tuned);
+        }
+    }
+    Ok(current)
+}
+
+// rust_chess [--evalfile <file>] tune <dataset> <output> [passes], the tuning starts from the loaded parameters
+pub fn run(dataset: &str, output: &str, passes: u32) -> io::Result<()>{
+    let samples = load_dataset(dataset)?;
+    println!("loaded {} positions from {}", samples.len(), dataset);
+    let params = params::current();
+    let k = fit_k(&samples, &params);
+    tune(&samples, &params, k, passes, output)?;
+    println!("tuned parameters written to {}", output);
+    Ok(())
+}
@@ -1,4 +1,5 @@
 use chess::{Square, BitBoard, Board};
+use std::str::FromStr;
 use std::ops::BitAnd;
 
 pub fn print_board(fen:String){
@@ -8,7 +9,7 @@ pub fn print_board(fen:String){
         let mut count = 0;
         for c in s.chars() {
             if count < 8{
-                if c.is_digit(10){
+                if c.is_ascii_digit(){
                     let times = c. to_digit(10);
                     match times{
                         None=>{},
@@ -30,7 +31,7 @@ pub fn print_board(fen:String){
             
 
         }
-        println!("");
+        println!();
     }
 
 }
@@ -49,8 +50,37 @@ pub fn mirror_square(square: &Square) -> Square{
 }
 
 
+// the square as seen from the side of the given color, so white tables can be used for black
+pub fn relative_square(square: Square, color: chess::Color) -> Square{
+    match color{
+        chess::Color::White=>square,
+        chess::Color::Black=>mirror_square(&square)
+    }
+}
+
+
+// the same position with the board flipped vertically and the colors swapped
+pub fn mirror_board(board: &Board) -> Board{
+    let fen = board.to_string();
+    let fields: Vec<&str> = fen.split(' ').collect();
+    let swap_case = |c: char| if c.is_ascii_uppercase() {c.to_ascii_lowercase()} else {c.to_ascii_uppercase()};
+
+    let placement = fields[0].split('/').rev().map(|rank| rank.chars().map(swap_case).collect::<String>()).collect::<Vec<String>>().join("/");
+    let side = if fields[1] == "w" {"b"} else {"w"};
+    let mut castling: Vec<char> = fields[2].chars().map(swap_case).collect();
+    castling.sort_by_key(|c| (c.is_ascii_lowercase(), match c.to_ascii_lowercase() {'k'=>0, 'q'=>1, _=>2}));
+    let castling: String = castling.into_iter().collect();
+    let en_passant = match fields[3]{
+        "-"=>"-".to_string(),
+        square=>square.chars().map(|c| match c {'3'=>'6', '6'=>'3', c=>c}).collect()
+    };
+    let mirrored = format!("{} {} {} {} {}", placement, side, castling, en_passant, fields[4..].join(" "));
+    Board::from_str(&mirrored).expect("the mirror of a valid position is valid")
+}
+
+
 pub fn distance(a: Square, b:Square) -> i32{
-    std::cmp::max(((b.get_rank().to_index() as i32- a.get_rank().to_index() as i32) as i32 ).abs(), ((b.get_file().to_index() as i32- a.get_file().to_index() as i32)as i32).abs())
+    std::cmp::max((b.get_rank().to_index() as i32- a.get_rank().to_index() as i32).abs(), (b.get_file().to_index() as i32- a.get_file().to_index() as i32).abs())
 } 
 
 